@@ -4,19 +4,29 @@ use std::rc::Rc;
 use std::str;
 use std::time::{Duration, Instant};
 
-use crate::components::{ComponentBehaviour, Rigidbody};
+use crate::atlas::RgbaImage;
+use crate::capture::FrameCapture;
+use crate::components::{ComponentBehaviour, Rigidbody, Shape, Transform};
+use crate::debug::{DebugDraw, DebugSpace};
 use crate::gameobject::GameObject;
 use crate::input::{Input, InputInfo};
+use crate::lighting::LightMap;
 use crate::math::Vec2;
-use crate::renderer::{Shader, ShaderProgram};
+use crate::renderer::{
+    Batch, BlendMode, DrawKey, LilahTexture, Material, PostProcess, RenderStats, ScalingPolicy,
+    ScreenLayout, Shader, ShaderProgram, SortLayer, Uniform,
+};
+use crate::text::GlyphCache;
 use crate::time::Timer;
 use crate::world::WorldState;
 use debug_print::debug_println;
 use glam::Mat4;
+use indexmap::IndexMap;
 use ruwren::{
-    create_module, BasicFileLoader, Class, FunctionHandle, FunctionSignature, Handle, ModuleLibrary, VMConfig, VMWrapper, VM
+    create_module, BasicFileLoader, Class, FunctionHandle, FunctionSignature, Handle, ModuleLibrary,
+    SlotType, VMConfig, VMError, VMWrapper, VM,
 };
-use sdl2::event::Event;
+use sdl2::event::{Event, WindowEvent};
 use sdl2::keyboard::Keycode;
 use sdl2::mixer::{AUDIO_S16LSB, DEFAULT_CHANNELS};
 use sdl2::mouse::MouseButton;
@@ -26,7 +36,6 @@ use sdl2::{AudioSubsystem, EventPump, Sdl};
 
 lazy_mut! {
     pub static mut DEBUG_PROGRAM: Option<ShaderProgram> = None;
-    pub static mut LINES: Vec<(Vec2, Vec2, crate::renderer::Color)> = Vec::new();
 }
 
 #[macro_export]
@@ -41,6 +50,28 @@ macro_rules! embed_script {
 }
 pub use embed_script;
 
+#[macro_export]
+macro_rules! embed_material {
+    ($name: expr, $frag: expr, $app:ident) => {
+        $app.add_material($name, $crate::application::App::DEFAULT_VERT, include_str!($frag));
+    };
+    ($name: expr, $vert: expr, $frag: expr, $app:ident) => {
+        $app.add_material($name, include_str!($vert), include_str!($frag));
+    };
+}
+pub use embed_material;
+
+#[macro_export]
+macro_rules! load_material {
+    ($name: expr, $frag: expr, $app:ident) => {
+        $app.load_material($name, None, $frag);
+    };
+    ($name: expr, $vert: expr, $frag: expr, $app:ident) => {
+        $app.load_material($name, Some($vert), $frag);
+    };
+}
+pub use load_material;
+
 #[macro_export]
 macro_rules! LilahTypeError {
     ($class: ty, $arg: literal, $t: ty) => {
@@ -155,12 +186,16 @@ pub struct Scripting {
     /// wren VM
     pub vm: VMWrapper,
     /// loaded modules. loaded as Module Name(String), Source(String).
-    pub modules: HashMap<String, String>,
+    /// Kept sorted by behaviour order, ties run in load order.
+    pub modules: IndexMap<String, String>,
+    /// Module Name(String) -> static order of the behaviour.
+    orders: HashMap<String, i32>,
 }
 
 impl Scripting {
     pub fn new() -> Self {
-        let mut modules = HashMap::new();
+        let mut modules = IndexMap::new();
+        let mut orders = HashMap::new();
         //let loader = BasicFileLoader::new().base_dir("");
 
         let mut lib = ModuleLibrary::new();
@@ -185,10 +220,13 @@ impl Scripting {
         vm.interpret("Trail", include_str!("scripts/Trail.wren")).expect("Failed");
         modules.insert("ParticleSystem".to_string(), "".to_string());
         modules.insert("Trail".to_string(), "".to_string());
+        orders.insert("ParticleSystem".to_string(), 0);
+        orders.insert("Trail".to_string(), 0);
 
         Self {
             vm: vm,
             modules: modules,
+            orders: orders,
         }
     }
 
@@ -197,7 +235,6 @@ impl Scripting {
         let modded_source = source.split("Behaviour {").collect::<Vec<&str>>();
         let static_helpers = 
         format!("\n\tstatic gameobject {{ __gameobject }}\n\tstatic gameobject=(v) {{ __gameobject = GameObjectRef.new(v) }}\n\tstatic gamebehaviour {{ gameobject.behaviourData({}, __uuid) }}\n\tstatic gamebehaviour=(v) {{__uuid = v}}\n\tconstruct new(g) {{ super(g, {}) }}", mod_name, mod_name);
-    
         let src = format!(
             "{}Behaviour {{ {} {}\nvar {} = {}.new()",
             modded_source[0],
@@ -217,6 +254,32 @@ impl Scripting {
                 panic!("Script Error: Could not load Module->{}\n{}", mod_name, e)
             }
         }
+
+        // static order is optional, asking the class tells whether it has one
+        let class = Scripting::get_class_handle(&self.vm, &mod_name, &mod_name);
+        self.vm.set_slot_handle(0, &class);
+        let order = match self.vm.call(FunctionSignature::new_getter("order")) {
+            Ok(_) => match self.vm.execute(|vm| vm.get_slot_double(0)) {
+                Some(o) => o as i32,
+                None => {
+                    LilahError!(Scripting, format!("{}.order must be a Num", mod_name));
+                    0
+                }
+            },
+            Err(VMError::Runtime { error, .. })
+                if error.contains("does not implement 'order'") =>
+            {
+                0
+            }
+            Err(e) => {
+                LilahError!(Scripting, e);
+                0
+            }
+        };
+
+        self.orders.insert(mod_name, order);
+        let orders = &self.orders;
+        self.modules.sort_by(|a, _, b, _| orders[a].cmp(&orders[b]));
     }
 
     pub fn tick(&mut self, app: &mut App, state: &mut WorldState) {
@@ -258,6 +321,10 @@ impl Scripting {
                     Scripting::call_handle(&self.vm, &class, &frame_setter);
 
                     for g in &mut state.gameobjects {
+                        if !g.1.active {
+                            continue;
+                        }
+
                         let behaviours = g.1.wrap_all::<ComponentBehaviour>();
                         for b in behaviours {
                             if b.get_component() == m.0  {
@@ -289,7 +356,7 @@ impl Scripting {
                     Scripting::call_handle(&self.vm, &class, &frame_setter);
                     
                     for g in &mut state.gameobjects {
-                        if !g.1.has_behaviour {
+                        if !g.1.has_behaviour || !g.1.active {
                             continue;
                         }
 
@@ -417,7 +484,29 @@ pub struct App {
     pub time: Timer,
     pub default_program: ShaderProgram,
     pub text_program: ShaderProgram,
+    /// Sprites, text and tiles are queued here and drawn in as few calls as possible.
+    pub batch: Batch,
+    /// 1x1 white texture for untextured quads such as Shapes.
+    pub white_texture: LilahTexture,
+    /// Debug lines, boxes, circles, arrows and labels. Off until Lilah.debug is set.
+    pub debug: DebugDraw,
+    /// Shapes drawn for this frame only, from Draw in wren or draw_shape. World::draw takes them.
+    pub shapes: Vec<(Shape, Transform)>,
+    /// Light map drawn over the world while any Light2D or Scene ambient is active.
+    pub lights: LightMap,
+    /// Glyph pages every Text draws from.
+    pub glyphs: GlyphCache,
+    /// Screenshots and recordings, fed by present_frame.
+    pub capture: FrameCapture,
+    /// name -> material. Components that name a missing material draw with the default program.
+    pub materials: HashMap<String, Material>,
+    /// Offscreen rendering and post processing passes.
+    pub post: PostProcess,
+    scaling: ScalingPolicy,
+    layout: ScreenLayout,
     pub sort_dirty: bool,
+    /// Named draw order bands, first drawn first. Components name one, unknown names use world.
+    pub sort_layers: Vec<SortLayer>,
     event_pump: EventPump,
     _audio_context: AudioSubsystem,
     window: Window,
@@ -427,15 +516,18 @@ impl App {
     pub const DEFAULT_VERT: &'static str = r#"
     #version 330
     out vec2 texCoord;
+    out vec4 tint;
 
-    in vec2 position;
-    in vec2 vertexTexCoord;
+    layout(location = 0) in vec2 position;
+    layout(location = 1) in vec2 vertexTexCoord;
+    layout(location = 2) in vec4 vertexTint;
 
     uniform mat4 mvp;
 
     void main() {
         gl_Position = mvp * vec4(position, 0.0, 1.0);
         texCoord = vertexTexCoord;
+        tint = vertexTint;
     }
     "#;
 
@@ -444,9 +536,9 @@ impl App {
     out vec4 FragColor;
 
     in vec2 texCoord;
+    in vec4 tint;
 
     uniform sampler2D texture0;
-    uniform vec4 tint;
 
     void main() {
         vec4 tex = texture(texture0, texCoord) * tint;
@@ -459,9 +551,9 @@ impl App {
     out vec4 FragColor;
 
     in vec2 texCoord;
+    in vec4 tint;
 
     uniform sampler2D texture0;
-    uniform vec4 tint;
 
     void main() {
         vec4 tex = texture(texture0, texCoord);
@@ -469,12 +561,38 @@ impl App {
     }
     "#;
 
+    pub const POST_VERT: &'static str = r#"
+    #version 330
+    out vec2 texCoord;
+
+    layout(location = 0) in vec2 position;
+    layout(location = 1) in vec2 vertexTexCoord;
+
+    void main() {
+        gl_Position = vec4(position, 0.0, 1.0);
+        texCoord = vertexTexCoord;
+    }
+    "#;
+
+    pub const POST_FRAG: &'static str = r#"
+    #version 330
+    out vec4 FragColor;
+
+    in vec2 texCoord;
+
+    uniform sampler2D texture0;
+
+    void main() {
+        FragColor = texture(texture0, texCoord);
+    }
+    "#;
+
     pub const DEBUG_VERT: &'static str = r#"
     #version 330
 
-    in vec2 opacity;
+    layout(location = 1) in vec2 opacity;
     out vec2 o;
-    in vec2 position;
+    layout(location = 0) in vec2 position;
     uniform mat4 mvp;
 
     void main() {
@@ -515,6 +633,7 @@ impl App {
         let win: Window = video_subsystem
             .window(window_title, window_size.x as u32, window_size.y as u32)
             .position_centered()
+            .resizable()
             .opengl()
             .build()
             .unwrap();
@@ -560,31 +679,238 @@ impl App {
           
             gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
             gl::ClearColor(0.0, 0.0, 0.0, 1.0);
-
-            let aspect = (window_size.x/window_size.y) as f32;
-
-            *crate::math::PROJECTION_MATRIX = Mat4::orthographic_rh_gl(
-                -window_size.x as f32/2.0,
-                window_size.x as f32/2.0,
-                -window_size.y as f32/2.0,
-                window_size.y as f32/2.0,
-                1000.0,
-                -1000.0,
-            );
-
-            *crate::application::LINES = Vec::new();
         }
 
-        Self {
+        let white_texture = unsafe {
+            let mut texture = LilahTexture::new();
+            texture.set_filtering(gl::NEAREST);
+            let _ = texture.load_as_dyn(image::ImageBuffer::from_pixel(1, 1, image::Rgba([255; 4])));
+            texture
+        };
+
+        let mut app = Self {
             gl_context: gl_ctx,
             window: win,
             event_pump,
             input: Input::new(),
             time: Timer::new(),
             sort_dirty: true,
+            sort_layers: SortLayer::defaults(),
             _audio_context: audio_context,
             default_program: program,
-            text_program: text_program
+            text_program: text_program,
+            batch: unsafe { Batch::new() },
+            white_texture,
+            debug: unsafe { DebugDraw::new() },
+            shapes: vec![],
+            lights: unsafe { LightMap::new() },
+            glyphs: GlyphCache::new(),
+            capture: FrameCapture::new(),
+            materials: HashMap::new(),
+            post: unsafe { PostProcess::new(App::POST_VERT, App::POST_FRAG) },
+            scaling: ScalingPolicy::Window,
+            layout: ScalingPolicy::Window.layout((window_size.x as u32, window_size.y as u32)),
+        };
+        app.on_resize();
+        app
+    }
+
+    fn set_projection(size: Vec2) {
+        unsafe {
+            *crate::math::PROJECTION_MATRIX = Mat4::orthographic_rh_gl(
+                -size.x as f32 / 2.0,
+                size.x as f32 / 2.0,
+                -size.y as f32 / 2.0,
+                size.y as f32 / 2.0,
+                1000.0,
+                -1000.0,
+            );
+        }
+    }
+
+    /// Draws shape at t for this frame only, sorted with everything else by its sort settings.
+    pub fn draw_shape(&mut self, shape: Shape, t: Transform) {
+        self.shapes.push((shape, t));
+    }
+
+    /// Sets how the world fits the window. Defaults to ScalingPolicy::Window.
+    pub fn set_scaling(&mut self, scaling: ScalingPolicy) {
+        self.scaling = scaling;
+        self.on_resize();
+    }
+
+    pub fn get_scaling(&self) -> ScalingPolicy {
+        self.scaling
+    }
+
+    /// Renders the world at a fixed internal resolution, scaled up to fit the window.
+    /// integer_scale keeps pixels square for pixel art. None goes back to the window size.
+    /// Kept for older code, same as set_scaling with Letterbox, IntegerScale or Window.
+    pub fn set_resolution(&mut self, resolution: Option<Vec2>, integer_scale: bool) {
+        self.set_scaling(match resolution {
+            None => ScalingPolicy::Window,
+            Some(r) if integer_scale => ScalingPolicy::IntegerScale(r),
+            Some(r) => ScalingPolicy::Letterbox(r),
+        });
+    }
+
+    /// Size the world is drawn at, the internal resolution if one is set.
+    pub fn get_render_size(&self) -> Vec2 {
+        let (w, h) = self.layout.render_size;
+        Vec2::new(w as f64, h as f64)
+    }
+
+    /// World units visible on screen under the current scaling policy.
+    pub fn get_view_size(&self) -> Vec2 {
+        self.layout.view_size
+    }
+
+    /// Recomputes the layout and projection from the current window size.
+    pub fn on_resize(&mut self) {
+        self.layout = self.scaling.layout(self.window.size());
+        App::set_projection(self.layout.view_size);
+
+        let (x, y, w, h) = self.layout.viewport;
+        unsafe {
+            *crate::math::VIEWPORT = [x as f32, y as f32, w as f32, h as f32];
+        }
+    }
+
+    /// Points drawing at part of the screen given as x, y from the top left, width, height in 0 to 1.
+    /// Sets the gl viewport inside the render area along with the projection and math::VIEWPORT.
+    pub fn set_camera_viewport(&mut self, rect: [f64; 4]) {
+        let [rx, ry, rw, rh] = rect;
+        App::set_projection(Vec2::new(
+            self.layout.view_size.x * rw,
+            self.layout.view_size.y * rh,
+        ));
+
+        let (x, y, w, h) = self.layout.viewport;
+        let (ax, ay, aw, ah) = self.post.render_area(&self.layout, self.window.size());
+        unsafe {
+            *crate::math::VIEWPORT = [
+                (x as f64 + rx * w as f64) as f32,
+                (y as f64 + ry * h as f64) as f32,
+                (rw * w as f64) as f32,
+                (rh * h as f64) as f32,
+            ];
+            gl::Viewport(
+                (ax as f64 + rx * aw as f64).round() as i32,
+                (ay as f64 + (1.0 - ry - rh) * ah as f64).round() as i32,
+                (rw * aw as f64).round() as i32,
+                (rh * ah as f64).round() as i32,
+            );
+        }
+    }
+
+    /// Appends a full screen pass drawn with a material linked with App::POST_VERT.
+    pub fn add_post_pass(&mut self, name: &str, material: &str) {
+        self.post.add_pass(name, material);
+    }
+
+    pub fn set_post_pass(&mut self, name: &str, enabled: bool) {
+        self.post.set_enabled(name, enabled);
+    }
+
+    /// Index of the sort layer called name, falling back to world and then the first layer.
+    pub fn sort_layer_index(&self, name: &str) -> usize {
+        self.sort_layers
+            .iter()
+            .position(|l| l.name == name)
+            .or_else(|| self.sort_layers.iter().position(|l| l.name == "world"))
+            .unwrap_or(0)
+    }
+
+    /// Orders the named layer by world y instead of sort.
+    pub fn set_y_sort(&mut self, name: &str, y_sort: bool) {
+        match self.sort_layers.iter_mut().find(|l| l.name == name) {
+            Some(layer) => {
+                layer.y_sort = y_sort;
+                self.sort_dirty = true;
+            }
+            None => {
+                LilahNotFoundError!(App, SortLayer, name);
+            }
+        }
+    }
+
+    /// Where something on the named sort layer at world y with sort is drawn.
+    pub fn draw_key(&self, layer: &str, y: f64, sort: u32) -> DrawKey {
+        let layer = self.sort_layer_index(layer);
+        let y_sort = self.sort_layers.get(layer).is_some_and(|l| l.y_sort);
+        DrawKey {
+            layer,
+            depth: if y_sort { -y } else { 0.0 },
+            sort,
+        }
+    }
+
+    /// Starts the frame's world drawing, offscreen when post processing is active.
+    pub fn begin_render(&mut self) {
+        unsafe {
+            self.post.begin(&self.layout, self.window.size());
+        }
+    }
+
+    /// Runs post processing and draws the result to the window.
+    pub fn end_render(&mut self, textures: &HashMap<String, LilahTexture>) {
+        unsafe {
+            self.batch.flush();
+            self.post.end(
+                &self.materials,
+                textures,
+                &self.layout,
+                self.window.size(),
+                self.time.time() as f32,
+            );
+        }
+    }
+
+    /// Compiles a material from source. Use App::DEFAULT_VERT as vert unless the material needs its own.
+    pub fn add_material(&mut self, name: &str, vert: &str, frag: &str) {
+        match unsafe { Material::new(vert, frag) } {
+            Ok(material) => {
+                self.materials.insert(name.to_string(), material);
+                debug_println!("Material loaded: {}", name);
+            }
+            Err(e) => {
+                LilahError!(Material, e);
+            }
+        }
+    }
+
+    /// Compiles a material from glsl files. vert defaults to App::DEFAULT_VERT.
+    pub fn load_material(&mut self, name: &str, vert: Option<&str>, frag: &str) {
+        let vert_source = match vert {
+            Some(file) => std::fs::read_to_string(file),
+            None => Ok(App::DEFAULT_VERT.to_string()),
+        };
+
+        match (vert_source, std::fs::read_to_string(frag)) {
+            (Ok(v), Ok(f)) => self.add_material(name, &v, &f),
+            (Err(e), _) | (_, Err(e)) => {
+                LilahError!(Material, e);
+            }
+        }
+    }
+
+    /// Sets a default uniform on a loaded material.
+    pub fn set_material_uniform(&mut self, name: &str, uniform: &str, value: Uniform) {
+        if let Some(m) = self.materials.get_mut(name) {
+            m.uniforms.set(uniform, value);
+        } else {
+            let name = name.to_string();
+            LilahNotFoundError!(App, Material, name);
+        }
+    }
+
+    /// Sets the blend used by components on a loaded material that do not set their own.
+    pub fn set_material_blend(&mut self, name: &str, blend: BlendMode) {
+        if let Some(m) = self.materials.get_mut(name) {
+            m.blend = blend;
+        } else {
+            let name = name.to_string();
+            LilahNotFoundError!(App, Material, name);
         }
     }
 
@@ -610,6 +936,7 @@ impl App {
                 }
             }
         }
+        self.on_resize();
     }
 
     pub fn get_fullscreen(&self) -> bool {
@@ -633,6 +960,7 @@ impl App {
                 }
             }
         }
+        self.on_resize();
     }
 
     pub fn delta_time(&self) -> f64 {
@@ -649,8 +977,15 @@ impl App {
             self.event_pump.mouse_state().y() as f64,
         ));
 
+        let mut resized = false;
         for event in self.event_pump.poll_iter() {
             match event {
+                Event::Window {
+                    win_event: WindowEvent::SizeChanged(..),
+                    ..
+                } => {
+                    resized = true;
+                }
                 Event::Quit { .. }
                 | Event::KeyDown {
                     keycode: Some(Keycode::Escape),
@@ -707,6 +1042,10 @@ impl App {
             }
         }
 
+        if resized {
+            self.on_resize();
+        }
+
         false
     }
 
@@ -724,8 +1063,82 @@ impl App {
 
     /// Draws canvas and sleeps until next frame
     pub fn present_frame(&mut self) {
+        unsafe {
+            self.batch.end_frame();
+        }
+
+        // frames are encoded in the background, errors from that show up on later frames
+        let time = self.time.time();
+        let captured = if self.capture.wants_frame(time) {
+            let frame = self.capture_frame();
+            self.capture.capture(frame, time)
+        } else {
+            self.capture.poll()
+        };
+        if let Err(e) = captured {
+            LilahError!(App, e);
+        }
+
         self.window.gl_swap_window();
     }
+
+    /// Reads back what was drawn this frame, top row first.
+    /// Call after World::draw and before present_frame, the back buffer is undefined once swapped.
+    pub fn capture_frame(&self) -> RgbaImage {
+        let (width, height) = self.window.drawable_size();
+        let mut pixels = vec![0u8; (width * height * 4) as usize];
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+            gl::ReadBuffer(gl::BACK);
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+            gl::ReadPixels(
+                0,
+                0,
+                width as i32,
+                height as i32,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                pixels.as_mut_ptr() as *mut _,
+            );
+        }
+
+        // alpha is whatever blending left in the window, not what was seen
+        for alpha in pixels.iter_mut().skip(3).step_by(4) {
+            *alpha = 255;
+        }
+
+        let mut frame = RgbaImage::from_raw(width, height, pixels).unwrap();
+        image::imageops::flip_vertical_in_place(&mut frame);
+        frame
+    }
+
+    /// Saves the next frame to path, the format comes from the extension.
+    pub fn screenshot(&mut self, path: &str) {
+        self.capture.screenshot(path);
+    }
+
+    /// Records frames from delay seconds from now for duration seconds.
+    /// A .gif path writes one animated gif, any other path is a prefix for numbered pngs.
+    /// Frames are capped by capture.max_size and capture.max_fps, and skipped while the encoder
+    /// is behind.
+    pub fn record(&mut self, path: &str, delay: f64, duration: f64) {
+        let start = self.time.time() + delay.max(0.0);
+        if let Err(e) = self.capture.record(path, start, start + duration) {
+            LilahError!(App, e);
+        }
+    }
+
+    /// Ends the recording in progress early and writes it.
+    pub fn stop_recording(&mut self) {
+        if let Err(e) = self.capture.stop() {
+            LilahError!(App, e);
+        }
+    }
+
+    /// Draw calls, quads and culling counters from the last frame.
+    pub fn render_stats(&self) -> RenderStats {
+        self.batch.stats()
+    }
 }
 
 impl Scripting {
@@ -817,29 +1230,189 @@ impl Scripting {
             let _ = vm.set_slot_new_foreign(
                 "math",
                 "Vec2",
-                Vec2::new(app.window.size().0 as f64, app.window.size().1 as f64),
+                app.get_view_size(),
                 1,
             );
         });
 
         Scripting::call_setter(&self.vm, &class, "screen_size");
 
+        let stats = app.render_stats();
+        self.vm.execute(|vm| {
+            vm.set_slot_double(1, stats.draw_calls as f64);
+        });
+        Scripting::call_setter(&self.vm, &class, "draw_calls");
+
+        self.vm.execute(|vm| {
+            vm.set_slot_double(1, stats.quads as f64);
+        });
+        Scripting::call_setter(&self.vm, &class, "quads");
+
+        self.vm.execute(|vm| {
+            vm.set_slot_double(1, stats.drawn as f64);
+        });
+        Scripting::call_setter(&self.vm, &class, "drawn");
+
+        self.vm.execute(|vm| {
+            vm.set_slot_double(1, stats.culled as f64);
+        });
+        Scripting::call_setter(&self.vm, &class, "culled");
+
+        // only gameobjects changed on the rust side cross over, the wren map is patched in place
+        let (dirty, removed) = state.take_changes();
+        Scripting::call_getter(&self.vm, &class, "gameobjects");
+
+        let full = self.vm.execute(|vm| vm.get_slot_type(0) != SlotType::Map);
+        if full {
+            self.vm.execute(|vm| {
+                vm.set_slot_new_map(1);
+
+                for (uuid, g) in &state.gameobjects {
+                    g.send_to_wren(2, vm);
+                    vm.set_slot_string(3, uuid);
+                    vm.set_map_value(1, 3, 2);
+                }
+            });
+            Scripting::call_setter(&self.vm, &class, "gameobjects");
+        } else {
+            self.vm.execute(|vm| {
+                for uuid in &removed {
+                    vm.set_slot_string(1, uuid);
+                    if let Some(true) = vm.get_map_contains_key(0, 1) {
+                        vm.remove_map_value(0, 1, 2);
+                    }
+                }
+
+                for uuid in &dirty {
+                    if let Some(g) = state.gameobjects.get(uuid) {
+                        vm.set_slot_string(1, uuid);
+                        if let Some(true) = vm.get_map_contains_key(0, 1) {
+                            vm.get_map_value(0, 1, 2);
+                            if let Some(wg) = vm.get_slot_foreign_mut::<GameObject>(2) {
+                                *wg = g.clone();
+                                continue;
+                            }
+                        }
+
+                        g.send_to_wren(2, vm);
+                        vm.set_map_value(0, 1, 2);
+                    }
+                }
+            });
+        }
+
+        self.vm.execute(|vm| {
+            vm.set_slot_new_list(1);
+
+            for uuid in &dirty {
+                vm.set_slot_string(2, uuid);
+                vm.insert_in_list(1, -1, 2);
+            }
+        });
+        Scripting::call_fn(&self.vm, &class, "refresh_handles", 1);
+
+        // instances spawned from rust start over with fresh data and behaviours
+        let spawned = state.take_spawned();
+        if !spawned.is_empty() {
+            self.vm.execute(|vm| {
+                vm.set_slot_new_list(1);
+
+                for uuid in &spawned {
+                    vm.set_slot_string(2, uuid);
+                    vm.insert_in_list(1, -1, 2);
+                }
+            });
+            Scripting::call_fn(&self.vm, &class, "reset_spawned", 1);
+        }
+
+        self.vm.execute(|vm| {
+            vm.set_slot_new_map(1);
+
+            for (name, pool) in state.pools() {
+                vm.set_slot_new_map(2);
+
+                pool.prefab.send_to_wren(3, vm);
+                vm.set_slot_string(4, "prefab");
+                vm.set_map_value(2, 4, 3);
+
+                vm.set_slot_new_list(3);
+                for uuid in pool.free() {
+                    vm.set_slot_string(4, uuid);
+                    vm.insert_in_list(3, -1, 4);
+                }
+                vm.set_slot_string(4, "free");
+                vm.set_map_value(2, 4, 3);
+
+                vm.set_slot_new_map(3);
+                for uuid in pool.members() {
+                    vm.set_slot_string(4, uuid);
+                    vm.set_slot_bool(5, true);
+                    vm.set_map_value(3, 4, 5);
+                }
+                vm.set_slot_string(4, "members");
+                vm.set_map_value(2, 4, 3);
+
+                vm.set_slot_string(4, name);
+                vm.set_map_value(1, 4, 2);
+            }
+        });
+        Scripting::call_setter(&self.vm, &class, "pools");
+
+        self.vm.execute(|vm| {
+            vm.set_slot_new_list(1);
+
+            for uuid in state.gameobjects.keys() {
+                vm.set_slot_string(2, uuid);
+                vm.insert_in_list(1, -1, 2);
+            }
+        });
+        Scripting::call_setter(&self.vm, &class, "gameobject_order");
+
         self.vm.execute(|vm| {
             vm.set_slot_new_map(1);
 
-            for i in state.gameobjects.iter().enumerate() {
-                i.1.1.clone().send_to_wren(2, vm);
-                vm.set_slot_string(3, i.1.0.clone());
+            for (tag, uuids) in state.tag_index() {
+                vm.set_slot_new_list(2);
+                for uuid in uuids {
+                    vm.set_slot_string(3, uuid);
+                    vm.insert_in_list(2, -1, 3);
+                }
+                vm.set_slot_string(3, tag);
                 vm.set_map_value(1, 3, 2);
             }
         });
-        Scripting::call_setter(&self.vm, &class, "gameobjects");
+        Scripting::call_setter(&self.vm, &class, "tags");
+
+        self.vm.execute(|vm| {
+            vm.set_slot_new_map(1);
+
+            for (name, uuids) in state.name_index() {
+                vm.set_slot_new_list(2);
+                for uuid in uuids {
+                    vm.set_slot_string(3, uuid);
+                    vm.insert_in_list(2, -1, 3);
+                }
+                vm.set_slot_string(3, name);
+                vm.set_map_value(1, 3, 2);
+            }
+        });
+        Scripting::call_setter(&self.vm, &class, "names");
 
         self.vm.execute(|vm| {
             vm.set_slot_bool(1, app.get_fullscreen());
         });
         Scripting::call_setter(&self.vm, &class, "fullscreen");
 
+        self.vm.execute(|vm| {
+            vm.set_slot_bool(1, app.debug.enabled);
+        });
+        Scripting::call_setter(&self.vm, &class, "debug");
+
+        self.vm.execute(|vm| {
+            vm.set_slot_bool(1, app.debug.colliders);
+        });
+        Scripting::call_setter(&self.vm, &class, "debug_colliders");
+
         let is_pressed = Scripting::get_fn_handle(&self.vm, "is_pressed", 1);
 
         for entry in &mut app.input.mappings {
@@ -987,17 +1560,18 @@ impl Scripting {
     pub fn receive_state(&self, app: &mut App, state: &mut WorldState) {
         let state_class = Scripting::get_class_handle(&self.vm, "app", "Lilah");
         let ui_class = Scripting::get_class_handle(&self.vm, "app", "UI");
+        let typewriter_class = Scripting::get_class_handle(&self.vm, "app", "Typewriter");
 
-        Scripting::call_getter(&self.vm, &state_class, "gameobjects_values");
+        Scripting::call_getter(&self.vm, &state_class, "gameobjects_dirty");
 
         self.vm.execute(|vm| {
             if let Some(count) = vm.get_list_count(0) {
                 for i in 0..count {
                     vm.get_list_element(0, i as i32, 1);
 
-                    let go = vm.get_slot_foreign::<GameObject>(1);
-                    if let Some(g) = go {
-                        state.insert(g);
+                    if let Some(g) = vm.get_slot_foreign_mut::<GameObject>(1) {
+                        g.changed = false;
+                        state.receive(g);
                     }
                 }
             }
@@ -1016,7 +1590,48 @@ impl Scripting {
 
                     let go = vm.get_slot_string(1);
                     if let Some(g) = go {
-                        state.gameobjects.remove(&g);
+                        state.remove(&g);
+                    }
+                }
+            }
+        });
+
+        // pools made, grown, spawned from and despawned to in wren, in order
+        Scripting::call_getter(&self.vm, &state_class, "pool_ops");
+
+        self.vm.execute(|vm| {
+            if let Some(count) = vm.get_list_count(0) {
+                for i in 0..count {
+                    vm.get_list_element(0, i as i32, 1);
+                    let len = vm.get_list_count(1).unwrap_or(0);
+                    if len < 2 {
+                        continue;
+                    }
+                    vm.get_list_element(1, 0, 2);
+                    vm.get_list_element(1, 1, 3);
+                    let (Some(op), Some(key)) = (vm.get_slot_string(2), vm.get_slot_string(3))
+                    else {
+                        continue;
+                    };
+
+                    match op.as_str() {
+                        "create" if len > 2 => {
+                            vm.get_list_element(1, 2, 4);
+                            if let Some(prefab) = vm.get_slot_foreign::<GameObject>(4) {
+                                state.adopt_pool(&key, prefab);
+                            }
+                        }
+                        "add" if len > 2 => {
+                            vm.get_list_element(1, 2, 4);
+                            if let Some(uuid) = vm.get_slot_string(4) {
+                                state.add_to_pool(&key, &uuid);
+                            }
+                        }
+                        "take" => state.take_from_pool(&key),
+                        "give" => {
+                            state.despawn(&key);
+                        }
+                        _ => {}
                     }
                 }
             }
@@ -1032,9 +1647,231 @@ impl Scripting {
             }
         });
 
+        Scripting::call_getter(&self.vm, &state_class, "debug");
+
+        self.vm.execute(|vm| {
+            if let Some(enabled) = vm.get_slot_bool(0) {
+                app.debug.enabled = enabled;
+            }
+        });
+
+        Scripting::call_getter(&self.vm, &state_class, "debug_colliders");
+
+        self.vm.execute(|vm| {
+            if let Some(colliders) = vm.get_slot_bool(0) {
+                app.debug.colliders = colliders;
+            }
+        });
+
+        Scripting::call_getter(&self.vm, &state_class, "debug_draws");
+
+        self.vm.execute(|vm| {
+            if let Some(count) = vm.get_list_count(0) {
+                for i in 0..count {
+                    vm.get_list_element(0, i as i32, 1);
+                    Scripting::receive_debug_draw(vm, app);
+                }
+            }
+        });
+
+        Scripting::call_getter(&self.vm, &state_class, "shapes");
+
+        self.vm.execute(|vm| {
+            if let Some(count) = vm.get_list_count(0) {
+                for i in 0..count {
+                    vm.get_list_element(0, i as i32, 1);
+                    for j in 0..6 {
+                        vm.get_list_element(1, j, j as usize + 2);
+                    }
+                    if let Some((shape, t)) =
+                        crate::components::immediate_shape_from_slot(vm, 2, 8)
+                    {
+                        app.draw_shape(shape, t);
+                    }
+                }
+            }
+        });
+
+        Scripting::call_getter(&self.vm, &state_class, "post_toggles");
+
+        self.vm.execute(|vm| {
+            if let Some(count) = vm.get_list_count(0) {
+                for i in 0..count {
+                    vm.get_list_element(0, i as i32, 1);
+                    vm.get_list_element(1, 0, 2);
+                    vm.get_list_element(1, 1, 3);
+
+                    if let (Some(name), Some(enabled)) = (vm.get_slot_string(2), vm.get_slot_bool(3)) {
+                        app.set_post_pass(&name, enabled);
+                    }
+                }
+            }
+        });
+
+        Scripting::call_getter(&self.vm, &state_class, "material_blends");
+
+        self.vm.execute(|vm| {
+            if let Some(count) = vm.get_list_count(0) {
+                for i in 0..count {
+                    vm.get_list_element(0, i as i32, 1);
+                    vm.get_list_element(1, 0, 2);
+                    vm.get_list_element(1, 1, 3);
+
+                    match (
+                        vm.get_slot_string(2),
+                        vm.get_slot_string(3).and_then(|b| BlendMode::from_name(&b)),
+                    ) {
+                        (Some(name), Some(blend)) => app.set_material_blend(&name, blend),
+                        _ => {
+                            LilahError!(Lilah, "setMaterialBlend takes a material name and a blend mode name");
+                        }
+                    }
+                }
+            }
+        });
+
+        Scripting::call_getter(&self.vm, &state_class, "stop_recording");
+
+        self.vm.execute(|vm| {
+            if vm.get_slot_bool(0) == Some(true) {
+                app.stop_recording();
+            }
+        });
+
+        Scripting::call_getter(&self.vm, &state_class, "recordings");
+
+        self.vm.execute(|vm| {
+            if let Some(count) = vm.get_list_count(0) {
+                for i in 0..count {
+                    vm.get_list_element(0, i as i32, 1);
+                    vm.get_list_element(1, 0, 2);
+                    vm.get_list_element(1, 1, 3);
+                    vm.get_list_element(1, 2, 4);
+
+                    match (vm.get_slot_string(2), vm.get_slot_double(3), vm.get_slot_double(4)) {
+                        (Some(path), Some(delay), Some(duration)) => {
+                            app.record(&path, delay, duration)
+                        }
+                        _ => {
+                            LilahError!(Lilah, "record takes a path, a delay and a duration");
+                        }
+                    }
+                }
+            }
+        });
+
+        Scripting::call_getter(&self.vm, &state_class, "screenshots");
+
+        self.vm.execute(|vm| {
+            if let Some(count) = vm.get_list_count(0) {
+                for i in 0..count {
+                    vm.get_list_element(0, i as i32, 1);
+
+                    match vm.get_slot_string(1) {
+                        Some(path) => app.screenshot(&path),
+                        None => {
+                            LilahError!(Lilah, "screenshot takes a path");
+                        }
+                    }
+                }
+            }
+        });
+
+        Scripting::call_getter(&self.vm, &state_class, "y_sort_toggles");
+
+        self.vm.execute(|vm| {
+            if let Some(count) = vm.get_list_count(0) {
+                for i in 0..count {
+                    vm.get_list_element(0, i as i32, 1);
+                    vm.get_list_element(1, 0, 2);
+                    vm.get_list_element(1, 1, 3);
+
+                    if let (Some(name), Some(enabled)) = (vm.get_slot_string(2), vm.get_slot_bool(3)) {
+                        app.set_y_sort(&name, enabled);
+                    }
+                }
+            }
+        });
+
+        Scripting::call_getter(&self.vm, &state_class, "post_uniforms");
+
+        self.vm.execute(|vm| {
+            if let Some(count) = vm.get_list_count(0) {
+                for i in 0..count {
+                    vm.get_list_element(0, i as i32, 1);
+                    vm.get_list_element(1, 0, 2);
+                    vm.get_list_element(1, 1, 3);
+                    vm.get_list_element(1, 2, 4);
+
+                    match (
+                        vm.get_slot_string(2),
+                        vm.get_slot_string(3),
+                        crate::components::uniform_from_slot(vm, 4, 5),
+                    ) {
+                        (Some(pass), Some(name), Some(value)) => {
+                            app.post.set_uniform(&pass, &name, value);
+                        }
+                        _ => {
+                            LilahError!(Lilah, "setPostUniform takes a pass name, a uniform name and a uniform value");
+                        }
+                    }
+                }
+            }
+        });
+
         Scripting::call_fn(&self.vm, &state_class, "clear", 0);
 
         Scripting::call_fn(&self.vm, &ui_class, "tick", 0);
+        Scripting::call_fn(&self.vm, &typewriter_class, "tick", 0);
+    }
+
+    /// Queues one Debug draw from slot 1, a list of kind, screen, color, duration and two arguments.
+    fn receive_debug_draw(vm: &VM, app: &mut App) {
+        for i in 0..6 {
+            vm.get_list_element(1, i, i as usize + 2);
+        }
+
+        let kind = vm.get_slot_string(2).unwrap_or_default();
+        let space = match vm.get_slot_bool(3) {
+            Some(true) => DebugSpace::Screen,
+            _ => DebugSpace::World,
+        };
+        let color = match crate::components::color_from_slot(vm, 4, 8) {
+            Some(color) => color,
+            None => {
+                LilahTypeError!(Debug, 3, List);
+                return;
+            }
+        };
+        let duration = vm.get_slot_double(5).unwrap_or(0f64);
+        let a = vm.get_slot_foreign::<Vec2>(6).copied();
+
+        match (kind.as_str(), a) {
+            ("line", Some(a)) => match vm.get_slot_foreign::<Vec2>(7) {
+                Some(b) => app.debug.line(a, *b, color, space, duration),
+                None => LilahTypeError!(Debug, 2, Vec2),
+            },
+            ("arrow", Some(a)) => match vm.get_slot_foreign::<Vec2>(7) {
+                Some(b) => app.debug.arrow(a, *b, color, space, duration),
+                None => LilahTypeError!(Debug, 2, Vec2),
+            },
+            ("box", Some(a)) => match vm.get_slot_foreign::<Vec2>(7) {
+                Some(b) => app.debug.rect(a, *b, color, space, duration),
+                None => LilahTypeError!(Debug, 2, Vec2),
+            },
+            ("circle", Some(a)) => match vm.get_slot_double(7) {
+                Some(radius) => app.debug.circle(a, radius, color, space, duration),
+                None => LilahTypeError!(Debug, 2, f64),
+            },
+            ("text", Some(a)) => match vm.get_slot_string(7) {
+                Some(text) => app.debug.text(a, &text, color, space, duration),
+                None => LilahTypeError!(Debug, 2, String),
+            },
+            (_, None) => LilahTypeError!(Debug, 1, Vec2),
+            (kind, _) => {
+                LilahError!(Debug, format!("Unknown debug draw {}", kind));
+            }
+        }
     }
 }
 
@@ -0,0 +1,474 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use image::{ImageBuffer, ImageError, Rgba};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::renderer::{LilahTexture, TextureCoords};
+
+pub type RgbaImage = ImageBuffer<Rgba<u8>, Vec<u8>>;
+
+#[derive(Debug, Error)]
+pub enum AtlasError {
+    #[error("Image {0} is larger than the atlas page")]
+    TooLarge(String),
+    #[error{"{0}"}]
+    ImageError(#[from] ImageError),
+    #[error{"{0}"}]
+    IoError(#[from] std::io::Error),
+    #[error{"{0}"}]
+    JsonError(#[from] serde_json::Error),
+}
+
+/// Where an image lives inside an atlas. uvs are normalized to the page.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct AtlasRegion {
+    pub page: usize,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    pub uv_min: [f32; 2],
+    pub uv_max: [f32; 2],
+}
+
+impl AtlasRegion {
+    /// Maps uvs relative to the source image into the page.
+    pub fn map_uvs(&self, uvs: [TextureCoords; 4]) -> [TextureCoords; 4] {
+        uvs.map(|uv| {
+            [
+                self.uv_min[0] + uv[0] * (self.uv_max[0] - self.uv_min[0]),
+                self.uv_min[1] + uv[1] * (self.uv_max[1] - self.uv_min[1]),
+            ]
+        })
+    }
+}
+
+/// Page size and named regions. Saved next to the page images for offline atlases.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct AtlasLayout {
+    pub page_size: u32,
+    pub pages: usize,
+    pub regions: HashMap<String, AtlasRegion>,
+}
+
+struct Shelf {
+    x: u32,
+    y: u32,
+    height: u32,
+}
+
+/// Shelf packer for one square page.
+struct PagePacker {
+    size: u32,
+    shelves: Vec<Shelf>,
+    next_y: u32,
+}
+
+impl PagePacker {
+    fn new(size: u32) -> Self {
+        Self {
+            size,
+            shelves: vec![],
+            next_y: 0,
+        }
+    }
+
+    /// Packer for a page loaded from disk, nothing more fits on it.
+    fn full(size: u32) -> Self {
+        Self {
+            size,
+            shelves: vec![],
+            next_y: size,
+        }
+    }
+
+    fn pack(&mut self, width: u32, height: u32) -> Option<(u32, u32)> {
+        if width > self.size {
+            return None;
+        }
+
+        for shelf in &mut self.shelves {
+            if height <= shelf.height && shelf.x + width <= self.size {
+                let pos = (shelf.x, shelf.y);
+                shelf.x += width;
+                return Some(pos);
+            }
+        }
+
+        if self.next_y + height <= self.size {
+            let pos = (0, self.next_y);
+            self.shelves.push(Shelf {
+                x: width,
+                y: self.next_y,
+                height,
+            });
+            self.next_y += height;
+            return Some(pos);
+        }
+
+        None
+    }
+}
+
+/// Space set aside for an image on a page, without the padding around it.
+/// Images no larger than it can be written over it again.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Slot {
+    page: usize,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+}
+
+impl Slot {
+    fn fits(&self, width: u32, height: u32) -> bool {
+        width <= self.width && height <= self.height
+    }
+}
+
+/// Packs images into pages, spilling into new pages as they fill.
+/// Freed slots are handed out whole again before packing new space.
+struct Packer {
+    page_size: u32,
+    padding: u32,
+    pages: Vec<PagePacker>,
+    free: Vec<Slot>,
+}
+
+impl Packer {
+    fn new(page_size: u32) -> Self {
+        Self {
+            page_size,
+            padding: 1,
+            pages: vec![],
+            free: vec![],
+        }
+    }
+
+    fn pack(&mut self, width: u32, height: u32) -> Option<AtlasRegion> {
+        self.alloc(width, height).map(|slot| self.region(&slot, width, height))
+    }
+
+    fn alloc(&mut self, width: u32, height: u32) -> Option<Slot> {
+        // the smallest freed slot it fits in
+        let reused = self
+            .free
+            .iter()
+            .enumerate()
+            .filter(|(_, slot)| slot.fits(width, height))
+            .min_by_key(|(_, slot)| slot.width * slot.height)
+            .map(|(i, _)| i);
+        if let Some(i) = reused {
+            return Some(self.free.swap_remove(i));
+        }
+
+        let (w, h) = (width + self.padding, height + self.padding);
+        if w > self.page_size || h > self.page_size {
+            return None;
+        }
+
+        let mut found = None;
+        for (i, page) in self.pages.iter_mut().enumerate() {
+            if let Some(pos) = page.pack(w, h) {
+                found = Some((i, pos));
+                break;
+            }
+        }
+
+        let (page, (x, y)) = match found {
+            Some(f) => f,
+            None => {
+                let mut page = PagePacker::new(self.page_size);
+                let pos = page.pack(w, h)?;
+                self.pages.push(page);
+                (self.pages.len() - 1, pos)
+            }
+        };
+
+        Some(Slot {
+            page,
+            x,
+            y,
+            width,
+            height,
+        })
+    }
+
+    fn free(&mut self, slot: Slot) {
+        self.free.push(slot);
+    }
+
+    /// Region for an image of width and height written at the top left of slot.
+    fn region(&self, slot: &Slot, width: u32, height: u32) -> AtlasRegion {
+        let size = self.page_size as f32;
+        AtlasRegion {
+            page: slot.page,
+            x: slot.x,
+            y: slot.y,
+            width,
+            height,
+            uv_min: [slot.x as f32 / size, slot.y as f32 / size],
+            uv_max: [(slot.x + width) as f32 / size, (slot.y + height) as f32 / size],
+        }
+    }
+}
+
+/// Offline atlas builder. Packs images on the cpu and saves the pages and layout to disk
+/// so they can be loaded with WorldState::load_atlas or embedded with embed_atlas!.
+pub struct AtlasBuilder {
+    packer: Packer,
+    pages: Vec<RgbaImage>,
+    regions: HashMap<String, AtlasRegion>,
+}
+
+impl AtlasBuilder {
+    //builder
+    pub fn new(page_size: u32) -> Self {
+        Self {
+            packer: Packer::new(page_size),
+            pages: vec![],
+            regions: HashMap::new(),
+        }
+    }
+
+    /// Empty pixels between images. Defaults to 1.
+    pub fn padding(mut self, padding: u32) -> Self {
+        self.packer.padding = padding;
+        self
+    }
+    //builder end
+
+    pub fn add(&mut self, name: &str, img: &RgbaImage) -> Result<AtlasRegion, AtlasError> {
+        let region = self
+            .packer
+            .pack(img.width(), img.height())
+            .ok_or_else(|| AtlasError::TooLarge(name.to_string()))?;
+
+        while self.pages.len() <= region.page {
+            self.pages
+                .push(RgbaImage::new(self.packer.page_size, self.packer.page_size));
+        }
+
+        image::imageops::replace(
+            &mut self.pages[region.page],
+            img,
+            region.x as i64,
+            region.y as i64,
+        );
+        self.regions.insert(name.to_string(), region);
+        Ok(region)
+    }
+
+    /// Adds an image file under its path, the same name load_texture uses.
+    pub fn add_file(&mut self, file: &str) -> Result<AtlasRegion, AtlasError> {
+        let img = image::open(Path::new(file))?.into_rgba8();
+        self.add(file, &img)
+    }
+
+    pub fn build(self) -> (Vec<RgbaImage>, AtlasLayout) {
+        let layout = AtlasLayout {
+            page_size: self.packer.page_size,
+            pages: self.pages.len(),
+            regions: self.regions,
+        };
+        (self.pages, layout)
+    }
+
+    /// Writes name_0.png, name_1.png, ... and name.json into dir.
+    pub fn save(self, dir: &str, name: &str) -> Result<AtlasLayout, AtlasError> {
+        let dir = Path::new(dir);
+        let (pages, layout) = self.build();
+
+        for (i, page) in pages.iter().enumerate() {
+            page.save(dir.join(format!("{}_{}.png", name, i)))?;
+        }
+        fs::write(
+            dir.join(format!("{}.json", name)),
+            serde_json::to_string_pretty(&layout)?,
+        )?;
+
+        Ok(layout)
+    }
+}
+
+/// Atlas living on the gpu. Sprites and Text whose texture_id has a region draw from the page instead.
+pub struct Atlas {
+    packer: Packer,
+    pub pages: Vec<LilahTexture>,
+    pub regions: HashMap<String, AtlasRegion>,
+    /// name -> space set aside for it, which can be larger than its region after it shrank.
+    slots: HashMap<String, Slot>,
+}
+
+impl Atlas {
+    pub fn new(page_size: u32) -> Self {
+        Self {
+            packer: Packer::new(page_size),
+            pages: vec![],
+            regions: HashMap::new(),
+            slots: HashMap::new(),
+        }
+    }
+
+    /// Uploads pages built offline. Later inserts go on new pages.
+    pub unsafe fn from_pages(layout: AtlasLayout, pages: Vec<RgbaImage>) -> Self {
+        let mut atlas = Atlas::new(layout.page_size);
+
+        for page in pages {
+            let mut texture = LilahTexture::new();
+            texture.set_wrapping(gl::CLAMP_TO_EDGE);
+            texture.set_filtering(gl::NEAREST);
+            let _ = texture.load_as_dyn(page);
+
+            atlas.pages.push(texture);
+            atlas.packer.pages.push(PagePacker::full(layout.page_size));
+        }
+
+        // offline regions can only be reused up to their own size, their padding is unknown
+        atlas.slots = layout
+            .regions
+            .iter()
+            .map(|(name, r)| {
+                let slot = Slot {
+                    page: r.page,
+                    x: r.x,
+                    y: r.y,
+                    width: r.width,
+                    height: r.height,
+                };
+                (name.clone(), slot)
+            })
+            .collect();
+        atlas.regions = layout.regions;
+        atlas
+    }
+
+    /// Packs img under name and uploads it. The space under name is reused while img still fits
+    /// in it, otherwise it is freed for later images once img is packed elsewhere.
+    pub unsafe fn insert(&mut self, name: &str, img: &RgbaImage) -> Option<AtlasRegion> {
+        let (width, height) = (img.width(), img.height());
+        let slot = match self.slots.get(name).copied() {
+            Some(old) if old.fits(width, height) => old,
+            old => {
+                let slot = self.packer.alloc(width, height)?;
+                if let Some(old) = old {
+                    self.packer.free(old);
+                }
+                slot
+            }
+        };
+        let region = self.packer.region(&slot, width, height);
+
+        while self.pages.len() <= region.page {
+            let mut texture = LilahTexture::new();
+            texture.set_wrapping(gl::CLAMP_TO_EDGE);
+            texture.set_filtering(gl::NEAREST);
+            texture.allocate(self.packer.page_size, self.packer.page_size);
+            self.pages.push(texture);
+        }
+
+        self.pages[region.page].write_region(region.x, region.y, img);
+        self.slots.insert(name.to_string(), slot);
+        self.regions.insert(name.to_string(), region);
+        Some(region)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&AtlasRegion> {
+        self.regions.get(name)
+    }
+}
+
+/// Pixel size of a texture id, taken from its atlas region when it was packed.
+pub fn texture_size(
+    atlas: Option<&Atlas>,
+    textures: &HashMap<String, LilahTexture>,
+    id: &str,
+) -> Option<(u32, u32)> {
+    if let Some(region) = atlas.and_then(|a| a.get(id)) {
+        return Some((region.width, region.height));
+    }
+
+    textures
+        .get(id)
+        .map(|t| (t.size.x as u32, t.size.y as u32))
+}
+
+/// Resolves a texture id to the texture to bind and the uvs to draw with,
+/// preferring the atlas page when the id was packed.
+pub fn resolve_texture<'t>(
+    atlas: Option<&'t Atlas>,
+    textures: &'t HashMap<String, LilahTexture>,
+    id: &str,
+    uvs: [TextureCoords; 4],
+) -> Option<(&'t LilahTexture, [TextureCoords; 4])> {
+    if let Some((atlas, region)) = atlas.and_then(|a| a.get(id).map(|r| (a, r))) {
+        return Some((&atlas.pages[region.page], region.map_uvs(uvs)));
+    }
+
+    textures.get(id).map(|t| (t, uvs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn slot(page: usize, x: u32, y: u32, width: u32, height: u32) -> Slot {
+        Slot {
+            page,
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    #[test]
+    fn packs_along_shelves() {
+        let mut packer = Packer::new(64);
+
+        assert_eq!(packer.alloc(10, 10), Some(slot(0, 0, 0, 10, 10)));
+        assert_eq!(packer.alloc(10, 10), Some(slot(0, 11, 0, 10, 10)));
+        // too tall for the first shelf, starts one below it
+        assert_eq!(packer.alloc(10, 20), Some(slot(0, 0, 11, 10, 20)));
+    }
+
+    #[test]
+    fn spills_into_new_pages() {
+        let mut packer = Packer::new(16);
+
+        assert_eq!(packer.alloc(15, 15), Some(slot(0, 0, 0, 15, 15)));
+        assert_eq!(packer.alloc(4, 4), Some(slot(1, 0, 0, 4, 4)));
+        // padding counts toward the page size
+        assert_eq!(packer.alloc(16, 16), None);
+        assert!(packer.pack(16, 16).is_none());
+    }
+
+    #[test]
+    fn reuses_the_smallest_freed_slot() {
+        let mut packer = Packer::new(64);
+        let large = packer.alloc(20, 20).unwrap();
+        let small = packer.alloc(8, 8).unwrap();
+        packer.free(large);
+        packer.free(small);
+
+        assert_eq!(packer.alloc(6, 6), Some(small));
+        assert_eq!(packer.alloc(6, 6), Some(large));
+        // nothing left to reuse, packs new space
+        assert_eq!(packer.alloc(6, 6), Some(slot(0, 30, 0, 6, 6)));
+        assert!(packer.free.is_empty());
+    }
+
+    #[test]
+    fn regions_cover_the_image_not_the_slot() {
+        let packer = Packer::new(64);
+        let region = packer.region(&slot(0, 16, 32, 16, 16), 8, 8);
+
+        assert_eq!((region.x, region.y, region.width, region.height), (16, 32, 8, 8));
+        assert_eq!(region.uv_min, [0.25, 0.5]);
+        assert_eq!(region.uv_max, [0.375, 0.625]);
+    }
+}
@@ -0,0 +1,327 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, Sender, SyncSender};
+use std::thread::{self, JoinHandle};
+
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::imageops::{self, FilterType};
+use image::{Delay, DynamicImage, Frame, ImageError};
+use thiserror::Error;
+
+use crate::atlas::RgbaImage;
+
+#[derive(Debug, Error)]
+pub enum CaptureError {
+    #[error("Capture {0}: {1}")]
+    Save(String, ImageError),
+    #[error("Capture {0}: {1}")]
+    Io(String, std::io::Error),
+}
+
+/// Work handed from the render thread to the encoding thread.
+enum Job {
+    Screenshot(RgbaImage, String),
+    Start { path: String, gif: bool, max_size: u32 },
+    Frame(RgbaImage, f64),
+    Finish,
+}
+
+/// Frames from start to end in App time, as tracked on the render thread.
+struct Recording {
+    start: f64,
+    end: f64,
+    /// Time of the last frame sent, frames closer than the frame rate cap are skipped.
+    last: Option<f64>,
+}
+
+/// A recording being written on the encoding thread.
+struct Encoding {
+    path: String,
+    max_size: u32,
+    count: usize,
+    /// Streams frames into the file as they come, None for numbered pngs.
+    gif: Option<GifEncoder<BufWriter<File>>>,
+    /// Gif frame waiting on the next one to know how long it was shown, and the last delay.
+    pending: Option<(RgbaImage, f64)>,
+    shown: f64,
+}
+
+impl Encoding {
+    fn new(path: String, gif: bool, max_size: u32) -> Result<Self, CaptureError> {
+        let gif = if gif {
+            let file =
+                File::create(Path::new(&path)).map_err(|e| CaptureError::Io(path.clone(), e))?;
+            let mut encoder = GifEncoder::new(BufWriter::new(file));
+            encoder
+                .set_repeat(Repeat::Infinite)
+                .map_err(|e| CaptureError::Save(path.clone(), e))?;
+            Some(encoder)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            path,
+            max_size,
+            count: 0,
+            gif,
+            pending: None,
+            shown: 0.1,
+        })
+    }
+
+    fn add(&mut self, frame: RgbaImage, time: f64) -> Result<(), CaptureError> {
+        let frame = fit(frame, self.max_size);
+        self.count += 1;
+        if self.gif.is_none() {
+            return save(&frame, &format!("{}_{:05}.png", self.stem(), self.count));
+        }
+
+        // each frame lasts until the next was shown
+        match self.pending.replace((frame, time)) {
+            Some((previous, shown_at)) => {
+                self.shown = time - shown_at;
+                self.encode(previous)
+            }
+            None => Ok(()),
+        }
+    }
+
+    fn encode(&mut self, frame: RgbaImage) -> Result<(), CaptureError> {
+        let delay = Delay::from_numer_denom_ms((self.shown * 1000.0).round().max(10.0) as u32, 1);
+        match &mut self.gif {
+            Some(encoder) => encoder
+                .encode_frame(Frame::from_parts(frame, 0, 0, delay))
+                .map_err(|e| CaptureError::Save(self.path.clone(), e)),
+            None => Ok(()),
+        }
+    }
+
+    /// The path without a .png extension, numbered frames are written next to it.
+    fn stem(&self) -> String {
+        match self.path.strip_suffix(".png") {
+            Some(stem) => stem.to_string(),
+            None => self.path.clone(),
+        }
+    }
+
+    /// Writes the last gif frame, as long as the one before it.
+    fn finish(mut self) -> Result<(), CaptureError> {
+        match self.pending.take() {
+            Some((frame, _)) => self.encode(frame),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Scales frame down so its longest side is at most max_size, 0 keeps it as is.
+fn fit(frame: RgbaImage, max_size: u32) -> RgbaImage {
+    let longest = frame.width().max(frame.height());
+    if max_size == 0 || longest <= max_size {
+        return frame;
+    }
+
+    let scale = max_size as f64 / longest as f64;
+    let width = ((frame.width() as f64 * scale).round() as u32).max(1);
+    let height = ((frame.height() as f64 * scale).round() as u32).max(1);
+    imageops::resize(&frame, width, height, FilterType::Triangle)
+}
+
+/// Saves without alpha, frames are opaque and some formats refuse it.
+fn save(frame: &RgbaImage, path: &str) -> Result<(), CaptureError> {
+    DynamicImage::ImageRgba8(frame.clone())
+        .into_rgb8()
+        .save(path)
+        .map_err(|e| CaptureError::Save(path.to_string(), e))
+}
+
+/// Runs jobs until the render thread hangs up, sending back every error.
+fn work(jobs: Receiver<Job>, errors: Sender<CaptureError>) {
+    let mut encoding: Option<Encoding> = None;
+    for job in jobs {
+        let done = match job {
+            Job::Screenshot(frame, path) => save(&frame, &path),
+            Job::Start {
+                path,
+                gif,
+                max_size,
+            } => {
+                let previous = encoding.take().map_or(Ok(()), Encoding::finish);
+                match Encoding::new(path, gif, max_size) {
+                    Ok(e) => {
+                        encoding = Some(e);
+                        previous
+                    }
+                    Err(e) => previous.and(Err(e)),
+                }
+            }
+            Job::Frame(frame, time) => match &mut encoding {
+                Some(e) => e.add(frame, time),
+                None => Ok(()),
+            },
+            Job::Finish => encoding.take().map_or(Ok(()), Encoding::finish),
+        };
+        if let Err(e) = done {
+            let _ = errors.send(e);
+        }
+    }
+
+    if let Some(e) = encoding.and_then(|e| e.finish().err()) {
+        let _ = errors.send(e);
+    }
+}
+
+/// Screenshots and recordings waiting on frames. App::present_frame feeds it before swapping.
+/// Frames are scaled and encoded on a worker thread. At most QUEUE jobs wait for it, recorded
+/// frames that come while the queue is full are skipped, so a slow encoder lowers the frame rate.
+pub struct FrameCapture {
+    /// Longest side of recorded frames in pixels, larger frames are scaled down. 0 keeps them.
+    /// Screenshots are always full size.
+    pub max_size: u32,
+    /// Most frames recorded per second, frames shown sooner after the last are skipped.
+    pub max_fps: f64,
+    screenshots: Vec<String>,
+    recording: Option<Recording>,
+    jobs: Option<SyncSender<Job>>,
+    errors: Option<Receiver<CaptureError>>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl Default for FrameCapture {
+    fn default() -> Self {
+        Self {
+            max_size: 640,
+            max_fps: 30.0,
+            screenshots: vec![],
+            recording: None,
+            jobs: None,
+            errors: None,
+            worker: None,
+        }
+    }
+}
+
+impl FrameCapture {
+    /// Jobs waiting on the worker before recorded frames are skipped.
+    const QUEUE: usize = 4;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Saves the next frame to path, the format comes from the extension.
+    pub fn screenshot(&mut self, path: &str) {
+        self.screenshots.push(path.to_string());
+    }
+
+    /// Records frames shown between start and end, in App time.
+    /// A .gif path writes one animated gif as frames are shown, any other path is a prefix
+    /// for numbered pngs. Replaces a recording in progress.
+    pub fn record(&mut self, path: &str, start: f64, end: f64) -> Result<(), CaptureError> {
+        self.recording = Some(Recording {
+            start,
+            end,
+            last: None,
+        });
+        self.send(Job::Start {
+            path: path.to_string(),
+            gif: path.to_lowercase().ends_with(".gif"),
+            max_size: self.max_size,
+        });
+        self.poll()
+    }
+
+    /// Ends the recording in progress early. The rest of it is written in the background.
+    pub fn stop(&mut self) -> Result<(), CaptureError> {
+        if self.recording.take().is_some() {
+            self.send(Job::Finish);
+        }
+        self.poll()
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    /// Whether the frame shown at time has to be read back.
+    pub fn wants_frame(&self, time: f64) -> bool {
+        let interval = if self.max_fps > 0.0 { 1.0 / self.max_fps } else { 0.0 };
+        !self.screenshots.is_empty()
+            || self.recording.as_ref().is_some_and(|r| {
+                time >= r.start && (time >= r.end || r.last.map_or(true, |l| time - l >= interval))
+            })
+    }
+
+    /// Hands the frame shown at time to every waiting screenshot and the recording.
+    /// Returns the first error from the worker since the last call.
+    pub fn capture(&mut self, frame: RgbaImage, time: f64) -> Result<(), CaptureError> {
+        for path in std::mem::take(&mut self.screenshots) {
+            self.send(Job::Screenshot(frame.clone(), path));
+        }
+
+        self.record_frame(frame, time);
+        self.poll()
+    }
+
+    /// The oldest error from the worker not returned yet, the rest wait for later calls.
+    pub fn poll(&mut self) -> Result<(), CaptureError> {
+        match self.errors.as_ref().and_then(|errors| errors.try_iter().next()) {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    fn record_frame(&mut self, frame: RgbaImage, time: f64) {
+        let Some(recording) = &mut self.recording else {
+            return;
+        };
+        if time < recording.start {
+            return;
+        }
+
+        let end = recording.end;
+        if time <= end && self.try_send(Job::Frame(frame, time)) {
+            if let Some(recording) = &mut self.recording {
+                recording.last = Some(time);
+            }
+        }
+        if time >= end {
+            self.recording = None;
+            self.send(Job::Finish);
+        }
+    }
+
+    /// Queues job, waiting for room if the queue is full. Starts the worker on first use.
+    fn send(&mut self, job: Job) {
+        if let Some(jobs) = self.queue() {
+            let _ = jobs.send(job);
+        }
+    }
+
+    /// Queues job only if there is room. Returns whether it was queued.
+    fn try_send(&mut self, job: Job) -> bool {
+        self.queue().is_some_and(|jobs| jobs.try_send(job).is_ok())
+    }
+
+    fn queue(&mut self) -> Option<&SyncSender<Job>> {
+        if self.jobs.is_none() {
+            let (jobs, receiver) = mpsc::sync_channel(Self::QUEUE);
+            let (sender, errors) = mpsc::channel();
+            self.jobs = Some(jobs);
+            self.errors = Some(errors);
+            self.worker = Some(thread::spawn(move || work(receiver, sender)));
+        }
+        self.jobs.as_ref()
+    }
+}
+
+impl Drop for FrameCapture {
+    /// Waits for the worker to write everything queued, at most QUEUE jobs.
+    fn drop(&mut self) {
+        self.jobs = None;
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
@@ -1,13 +1,20 @@
+use crate::atlas::{self, Atlas};
 use crate::gameobject::GameObjectId;
 use crate::math::{self, Vec2};
-use crate::renderer::{Buffer, Color, LilahTexture, Vertex, VertexArray};
-use crate::world::StateUpdateContainer;
+use crate::renderer::{
+    self, BlendMode, Buffer, Color, LilahTexture, Pos, TextureCoords, Uniform, Uniforms, Vertex,
+    VertexArray,
+};
+use crate::text::{
+    LayoutOptions, LilahFont, PlacedGlyph, TextAlign, TextEffects, VerticalAlign, PADDING,
+};
+use crate::tilemap::ChunkedLayer;
 use crate::{application::App, gameobject::GameObject};
-use crate::{set_attribute, LilahNotFoundError, LilahPanic, LilahTypeError, LilahTypePanic};
+use crate::{
+    set_attribute, LilahError, LilahNotFoundError, LilahPanic, LilahTypeError, LilahTypePanic,
+};
 use gl::types::*;
 use glam::{Mat4, Quat, Vec3};
-use image::{DynamicImage, Rgba};
-use rusttype::{point, Font, Scale};
 use ruwren::{create_module, send_foreign, Class, ModuleLibrary, SlotType, VM};
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
@@ -73,6 +80,8 @@ pub struct Rigidbody {
     pub colliding: Option<GameObjectId>,
     /// If set to false colliding is still populated but the rigidbody will not correct its velocity when collisions are detected.
     pub solid: bool,
+    /// Casts shadows from the collider for every Light2D.
+    pub occluder: bool,
 }
 
 /// Sprite Component for GameObjects
@@ -94,9 +103,42 @@ pub struct Sprite {
 
     pub sort: u32,
     pub sort_dirty: bool,
+    /// Name of an App::sort_layers entry, sort orders within it.
+    pub sort_layer: String,
+    /// Added to the Transform y when the sort layer is y-sorted, so feet can be the sort point.
+    pub sort_pivot: f64,
+
+    /// Texture coordinates of the current cell, counter clockwise from the bottom left.
+    uvs: [TextureCoords; 4],
+
+    /// Name of a material in App::materials, None draws with the default program.
+    pub material: Option<String>,
+    /// Overrides for the material uniforms.
+    pub uniforms: Uniforms,
+    /// None uses the material's blend, or alpha without one.
+    pub blend: Option<BlendMode>,
+
+    /// Mirrors the cell without touching the Transform, so colliders stay put.
+    pub flip_x: bool,
+    pub flip_y: bool,
+    pub fill: SpriteFill,
+
+    /// Fraction of camera motion the sprite follows, 1 moves with the world and 0 with the camera.
+    pub parallax: Vec2,
+    /// Repeats the sprite endlessly along x and y.
+    pub repeat: (bool, bool),
+}
 
-    vertex_buffer: Option<Buffer>,
-    vertex_array: Option<VertexArray>,
+/// How a Sprite covers its area.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SpriteFill {
+    /// The cell at its own size.
+    Simple,
+    /// Stretches the cell to size keeping the border unscaled, for resizable panels.
+    /// border is left, right, top, bottom in texture pixels.
+    Sliced { size: Vec2, border: [f64; 4] },
+    /// Repeats the cell across size from the bottom left, clipping the last row and column.
+    Tiled { size: Vec2 },
 }
 
 #[serde_as]
@@ -108,6 +150,21 @@ pub struct Layer {
     pub collision: bool,
     pub tile_sheet: String,
     pub current_tile_item: i32,
+    /// Fraction of camera motion the layer follows, 1 moves with the world and 0 with the camera.
+    #[serde(default = "Layer::default_parallax")]
+    pub parallax: (f32, f32),
+    /// Repeats the layer endlessly along x and y.
+    #[serde(default)]
+    pub repeat: (bool, bool),
+    /// Name of an App::sort_layers entry, None draws the layer on world at its index.
+    #[serde(default)]
+    pub sort_layer: Option<String>,
+}
+
+impl Layer {
+    fn default_parallax() -> (f32, f32) {
+        (1.0, 1.0)
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -149,6 +206,23 @@ pub struct Scene {
     pub markers: Vec<Marker>,
     pub transforms: Vec<Vec<Transform>>,
     pub rigidbodies: Vec<Rigidbody>,
+    /// layer index -> material name and uniform overrides for that tile layer.
+    pub layer_materials: HashMap<usize, (String, Uniforms)>,
+    /// layer index -> parallax factor and x, y repetition, filled from the scene file on load.
+    pub layer_parallax: HashMap<usize, (Vec2, (bool, bool))>,
+    /// layer index -> App::sort_layers entry. Tiles of y-sorted layers are drawn one by one between
+    /// objects by their bottom edge, without parallax.
+    pub layer_sort_layers: HashMap<usize, String>,
+    /// Min and max corners of every tile relative to the scene, filled on load for culling.
+    tile_bounds: Vec<Vec<(Vec2, Vec2)>>,
+    /// Min and max corners around each layer relative to the scene, None for empty layers.
+    layer_bounds: Vec<Option<(Vec2, Vec2)>>,
+    /// Each layer baked into static chunk meshes, rebuilt only where tiles changed.
+    chunks: Vec<ChunkedLayer>,
+    /// Light everywhere while the scene is active, None leaves App::lights.ambient.
+    pub ambient: Option<Color>,
+    /// Whether collision layer tiles cast shadows for every Light2D.
+    pub occluders: bool,
 }
 
 /// Animator Component for GameObjects
@@ -180,20 +254,52 @@ pub struct Text {
     text: String,
     font_size: u32,
     font: String,
-    texture_id: String,
     changed: bool,
+    /// Wraps lines at spaces to fit, None keeps each line whole.
+    max_width: Option<f64>,
+    /// Height of the box lines are aligned in, it grows to fit them.
+    min_height: Option<f64>,
+    align: TextAlign,
+    valign: VerticalAlign,
+    line_spacing: f64,
+    /// Size of the laid out lines in pixels, from the last load.
+    bounds: Vec2,
+    line_count: usize,
+    /// Glyphs from the last load, drawn from App::glyphs.
+    glyphs: Vec<PlacedGlyph>,
+    /// App::glyphs generation the glyphs were cached in, they are cached again once it moves on.
+    glyph_generation: usize,
+    /// Text box plus padding in pixels, zero until the first load.
+    box_size: Vec2,
 
     pub color: Color,
+    /// Outline thickness in pixels, 0 draws none.
+    pub outline_width: f64,
+    pub outline_color: Color,
+    /// Drop shadow offset in pixels, y up. Zero draws none.
+    pub shadow_offset: Vec2,
+    pub shadow_color: Color,
+    pub effects: TextEffects,
+    /// Glyphs shown by a typewriter reveal, None shows all of them.
+    pub revealed: Option<f64>,
+    /// Glyphs revealed per second, 0 leaves revealed to scripts.
+    pub reveal_speed: f64,
 
     sort: u32,
     sort_dirty: bool,
-
-    vertex_buffer: Option<Buffer>,
-    vertex_array: Option<VertexArray>,
+    /// Name of an App::sort_layers entry, sort orders within it.
+    pub sort_layer: String,
+    /// Added to the Transform y when the sort layer is y-sorted, so feet can be the sort point.
+    pub sort_pivot: f64,
+
+    /// Name of a material in App::materials, None draws with the default text program.
+    pub material: Option<String>,
+    /// Overrides for the material uniforms.
+    pub uniforms: Uniforms,
+    /// None uses the material's blend, or alpha without one.
+    pub blend: Option<BlendMode>,
 }
 
-pub struct Debug {}
-
 #[derive(Clone)]
 pub struct Line {
     pub parent: String,
@@ -206,6 +312,90 @@ pub struct Line {
     pub vertex_buffer: Buffer,
     pub sort: u32,
     pub sort_dirty: bool,
+    /// Name of a material in App::materials linked with App::DEBUG_VERT, None draws with the debug program.
+    pub material: Option<String>,
+    /// Overrides for the material uniforms.
+    pub uniforms: Uniforms,
+    /// None uses the material's blend, or alpha without one.
+    pub blend: Option<BlendMode>,
+}
+
+/// Geometry of a Shape in local space around its Transform.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ShapeKind {
+    /// Width and height, centered.
+    Rect(Vec2),
+    /// Radius, centered.
+    Circle(f64),
+    /// Points in order. Filled polygons are drawn as a fan so they should be convex.
+    Polygon(Vec<Vec2>),
+}
+
+/// Shape Component for GameObjects. Untextured rect, circle or polygon drawn through the batch.
+#[derive(Clone)]
+pub struct Shape {
+    pub parent: String,
+    pub kind: ShapeKind,
+    /// Draws an outline thickness wide when false.
+    pub filled: bool,
+    pub thickness: f64,
+    pub color: Color,
+    pub sort: u32,
+    pub sort_dirty: bool,
+    /// Name of an App::sort_layers entry, sort orders within it.
+    pub sort_layer: String,
+    /// Added to the Transform y when the sort layer is y-sorted, so feet can be the sort point.
+    pub sort_pivot: f64,
+}
+
+/// Light2D Component for GameObjects. Adds light around its Transform in the light map.
+#[derive(Clone)]
+pub struct Light2D {
+    pub parent: String,
+    /// World units the light reaches. Lights at 0 or below are skipped.
+    pub radius: f64,
+    pub color: Color,
+    /// Multiplies color, above 1.0 can brighten past the unlit sprite.
+    pub intensity: f64,
+    /// Power of the falloff curve, 1.0 fades linearly to the radius.
+    pub falloff: f64,
+    /// Whether occluders block this light.
+    pub shadows: bool,
+    /// Size of the light for soft shadow edges, 0 casts hard shadows.
+    pub softness: f64,
+}
+
+/// Camera Component for GameObjects. Views the world from the GameObject's Transform.
+#[derive(PartialEq, Clone)]
+pub struct Camera {
+    pub parent: String,
+    /// Multiplies the Transform scale, 2.0 shows everything twice as big. Kept above 0 by set_zoom.
+    pub zoom: f64,
+    /// Radians, added to the Transform rotation.
+    pub rotation: f32,
+    /// Name or uuid of the GameObject to follow.
+    pub target: Option<String>,
+    /// Seconds to close most of the distance to the target, 0 snaps to it.
+    pub smoothing: f64,
+    /// Half size of the box around the camera the target can move in without being followed.
+    pub dead_zone: Vec2,
+    /// Min and max corners the view is kept inside.
+    pub bounds: Option<(Vec2, Vec2)>,
+    /// Name or uuid of a GameObject with a Scene whose extents are used as bounds.
+    pub bounds_scene: Option<String>,
+    /// 0 to 1, shake strength is trauma squared.
+    pub trauma: f64,
+    /// Trauma lost per second.
+    pub trauma_decay: f64,
+    pub max_shake_offset: Vec2,
+    pub max_shake_angle: f32,
+    /// Part of the screen drawn to as x, y from the top left, width, height in 0 to 1.
+    pub viewport: [f64; 4],
+    /// Cameras draw from the lowest order up. The first one drawn is the primary camera.
+    pub order: i32,
+    shake_offset: Vec2,
+    shake_angle: f32,
+    shake_time: f64,
 }
 
 //component impls
@@ -342,6 +532,14 @@ impl Scene {
             transforms: vec![],
             rigidbodies: vec![],
             markers: vec![],
+            layer_materials: HashMap::new(),
+            layer_parallax: HashMap::new(),
+            layer_sort_layers: HashMap::new(),
+            tile_bounds: vec![],
+            layer_bounds: vec![],
+            chunks: vec![],
+            ambient: None,
+            occluders: false,
         }
     }
 
@@ -349,26 +547,39 @@ impl Scene {
         &mut self,
         app: &mut App,
         textures: &HashMap<String, LilahTexture>,
+        atlas: Option<&Atlas>,
         scenes: &HashMap<String, SceneData>,
     ) {
         let this_scene = &scenes[self.file.clone().as_str()];
         self.markers = this_scene.markers.clone();
+        let sheet_ids: HashMap<&str, usize> = this_scene
+            .tile_sheets
+            .iter()
+            .enumerate()
+            .map(|(i, sheet)| (sheet.path.as_str(), i))
+            .collect();
+
+        for (index, layer) in this_scene.layers.iter().enumerate() {
+            // settings made from wren before loading win over the file
+            self.layer_parallax.entry(index).or_insert((
+                Vec2::new(layer.parallax.0 as f64, layer.parallax.1 as f64),
+                layer.repeat,
+            ));
+            if let Some(sort_layer) = &layer.sort_layer {
+                self.layer_sort_layers
+                    .entry(index)
+                    .or_insert_with(|| sort_layer.clone());
+            }
 
-        for layer in &this_scene.layers {
             let mut current_tiles = vec![];
             let mut current_trans = vec![];
 
             for tile in &layer.tiles {
-                let mut current_sheet = "".to_string();
-                let mut current_sheet_id = 0;
-                for sheet in 0..this_scene.tile_sheets.len() {
-                    if this_scene.tile_sheets[sheet].path == tile.1.sheet {
-                        current_sheet = this_scene.tile_sheets[sheet].absolute_path.to_string();
-                        current_sheet_id = sheet;
-                        break;
-                    }
-                }
-                let mut new_tile = Sprite::new(current_sheet.as_str());
+                let current_sheet_id = sheet_ids.get(tile.1.sheet.as_str()).copied();
+                let current_sheet = current_sheet_id
+                    .map_or("", |sheet| this_scene.tile_sheets[sheet].absolute_path.as_str());
+                let current_sheet_id = current_sheet_id.unwrap_or(0);
+                let mut new_tile = Sprite::new(current_sheet);
                 new_tile.cut_sprite_sheet(
                     tile.1.sheet_id.0 as i32,
                     tile.1.sheet_id.1 as i32,
@@ -404,29 +615,181 @@ impl Scene {
 
         for i in &mut self.tiles {
             for j in i {
-                j.load(app, textures);
+                j.load(app, textures, atlas);
+            }
+        }
+
+        let layers = self.tiles.len();
+        self.tile_bounds = vec![vec![]; layers];
+        self.layer_bounds = vec![None; layers];
+        self.chunks = vec![ChunkedLayer::default(); layers];
+        for layer in 0..layers {
+            self.mark_layer_dirty(layer);
+        }
+        self.rebuild_chunks(textures, atlas);
+    }
+
+    /// Works out the bounds and chunks of a layer again, rebuilding them before the next draw.
+    /// Call after changing tiles or transforms of the layer directly.
+    pub fn mark_layer_dirty(&mut self, layer: usize) {
+        let (Some(tiles), Some(transforms)) = (self.tiles.get(layer), self.transforms.get(layer))
+        else {
+            return;
+        };
+        if layer >= self.tile_bounds.len() {
+            return;
+        }
+
+        let bounds: Vec<(Vec2, Vec2)> = tiles
+            .iter()
+            .zip(transforms)
+            .map(|(tile, trans)| tile.world_bounds(trans))
+            .collect();
+        self.layer_bounds[layer] = bounds.iter().copied().reduce(math::union);
+        self.chunks[layer] = ChunkedLayer::new(&bounds);
+        self.tile_bounds[layer] = bounds;
+    }
+
+    /// Index of the first tile on layer covering position, relative to the scene.
+    pub fn tile_at(&self, layer: usize, position: Vec2) -> Option<usize> {
+        self.tile_bounds.get(layer)?.iter().position(|(min, max)| {
+            position.x >= min.x && position.x < max.x && position.y >= min.y && position.y < max.y
+        })
+    }
+
+    /// Shows another cell of the tile's sheet. Only the chunk holding it is rebuilt.
+    pub fn set_tile_cell(&mut self, layer: usize, tile: usize, cell: (i32, i32)) {
+        let Some(sprite) = self.tiles.get_mut(layer).and_then(|l| l.get_mut(tile)) else {
+            return;
+        };
+        sprite.index_cut = cell;
+        sprite.anim_sprite_sheet(cell.0, cell.1);
+
+        if let Some(chunks) = self.chunks.get_mut(layer) {
+            chunks.mark_dirty(tile);
+        }
+    }
+
+    /// Builds the chunks marked dirty since the last call. Called every frame while loading.
+    pub fn rebuild_chunks(
+        &mut self,
+        textures: &HashMap<String, LilahTexture>,
+        atlas: Option<&Atlas>,
+    ) {
+        for (layer, chunks) in self.chunks.iter_mut().enumerate() {
+            if chunks.is_dirty() {
+                unsafe {
+                    chunks.rebuild(&self.tiles[layer], &self.transforms[layer], textures, atlas);
+                }
             }
         }
     }
 
+    /// Draws one layer from its chunk meshes, once for every repetition in view.
     pub fn draw(
         &self,
         sort: usize,
         app: &mut App,
         textures: &HashMap<String, LilahTexture>,
         t: &Transform,
+        view: (Vec2, Vec2),
     ) {
-        if sort > self.tiles.len() {
+        let Some(Some(layer)) = self.layer_bounds.get(sort).copied() else {
             return;
+        };
+
+        let (factor, repeat) = self
+            .layer_parallax
+            .get(&sort)
+            .copied()
+            .unwrap_or((Vec2::ONE, (false, false)));
+        let offsets = math::parallax_offsets(
+            (layer.0 + t.position, layer.1 + t.position),
+            factor,
+            repeat,
+            view,
+        );
+        if offsets.is_empty() {
+            app.batch.count(0, self.tiles[sort].len() as u32);
+        }
+        for offset in offsets {
+            self.draw_layer(sort, app, textures, t.position + offset, view);
+        }
+    }
+
+    /// Draws a single tile, given as layer and tile index, when it is inside view.
+    pub fn draw_tile(
+        &self,
+        (sort, tile): (usize, usize),
+        app: &mut App,
+        textures: &HashMap<String, LilahTexture>,
+        atlas: Option<&Atlas>,
+        t: &Transform,
+        view: (Vec2, Vec2),
+    ) {
+        let (sprite, trans) = match (
+            self.tiles.get(sort).and_then(|l| l.get(tile)),
+            self.transforms.get(sort).and_then(|l| l.get(tile)),
+        ) {
+            (Some(sprite), Some(trans)) => (sprite, trans),
+            _ => return,
+        };
+
+        if let Some(bounds) = self.tile_bounds.get(sort).and_then(|b| b.get(tile)) {
+            if !math::overlaps((bounds.0 + t.position, bounds.1 + t.position), view) {
+                app.batch.count(0, 1);
+                return;
+            }
+        }
+
+        let new_trans = Transform::new(trans.position + t.position);
+        match self.layer_materials.get(&sort) {
+            Some((m, u)) => sprite.draw_with(app, textures, atlas, &new_trans, Some(m), u),
+            None => sprite.draw(app, textures, atlas, &new_trans),
         }
+        app.batch.count(1, 0);
+    }
 
-        for j in 0..self.tiles[sort].len() {
-            let trans = &self.transforms[sort][j];
-            let new_trans = Transform::new(trans.position + t.position);
-            self.tiles[sort][j].draw(app, textures, &new_trans);
+    /// World y of the bottom edge of a tile, what y-sorted layers order tiles by.
+    pub fn tile_sort_y(&self, sort: usize, tile: usize, t: &Transform) -> f64 {
+        match self.tile_bounds.get(sort).and_then(|b| b.get(tile)) {
+            Some(bounds) => bounds.0.y + t.position.y,
+            None => self.transforms[sort][tile].position.y + t.position.y,
         }
     }
 
+    /// Draws the chunks of one layer with the scene at position, culling each against view.
+    fn draw_layer(
+        &self,
+        sort: usize,
+        app: &mut App,
+        textures: &HashMap<String, LilahTexture>,
+        position: Vec2,
+        view: (Vec2, Vec2),
+    ) {
+        let Some(chunks) = self.chunks.get(sort) else {
+            return;
+        };
+
+        let no_overrides = Uniforms::default();
+        let (material, overrides) = match self.layer_materials.get(&sort) {
+            Some((m, u)) => (Some(m), u),
+            None => (None, &no_overrides),
+        };
+        let (program, uniforms) = renderer::select_material(
+            &app.materials,
+            &app.default_program,
+            material,
+            overrides,
+            textures,
+        );
+        let blend = renderer::select_blend(&app.materials, material, None);
+
+        let (drawn, culled) =
+            unsafe { chunks.draw(&mut app.batch, program, &uniforms, blend, position, view) };
+        app.batch.count(drawn, culled);
+    }
+
     //for wren
     fn wren_as_component(&self, vm: &VM) {
         send_foreign!(vm, "game", "Scene", Box::new(self.clone()) as Box<dyn Component> => 0);
@@ -436,6 +799,33 @@ impl Scene {
         vm.set_slot_string(0, self.parent.clone());
     }
 
+    /// Min and max corners around every tile, offset by t. None before the scene is loaded.
+    pub fn extents(&self, t: &Transform) -> Option<(Vec2, Vec2)> {
+        self.layer_bounds
+            .iter()
+            .flatten()
+            .copied()
+            .reduce(math::union)
+            .map(|(min, max)| (min + t.position, max + t.position))
+    }
+
+    fn wren_get_ambient(&self, vm: &VM) {
+        match &self.ambient {
+            Some(color) => {
+                vm.set_slot_new_list(0);
+                for (i, c) in [color.r, color.g, color.b, color.a].iter().enumerate() {
+                    vm.set_slot_double(1, *c as f64);
+                    vm.insert_in_list(0, i as i32, 1);
+                }
+            }
+            None => vm.set_slot_null(0),
+        }
+    }
+
+    fn wren_get_occluders(&self, vm: &VM) {
+        vm.set_slot_bool(0, self.occluders);
+    }
+
     fn wren_markers(&self, vm: &VM) {
         vm.set_slot_new_list(0);
         for i in self.markers.iter().enumerate() {
@@ -813,6 +1203,7 @@ impl Rigidbody {
             scale: Vec2::ONE,
             colliding: None,
             solid: true,
+            occluder: false,
         }
     }
 
@@ -827,9 +1218,35 @@ impl Rigidbody {
             position: Vec2::ZERO,
             colliding: None,
             solid: true,
+            occluder: false,
         }
     }
 
+    /// Corners of the collider in world space, counter clockwise from the top left.
+    pub fn collider_corners(&self) -> Vec<Vec2> {
+        let model = Mat4::from_scale_rotation_translation(
+            Vec3::new(
+                (self.bounds.x * self.scale.x) as f32,
+                (self.bounds.y * self.scale.y) as f32,
+                1.0,
+            ),
+            Quat::from_rotation_z(self.rotation),
+            Vec3::new(
+                (self.position.x + self.pivot.x) as f32,
+                (self.position.y + self.pivot.y) as f32,
+                0.0,
+            ),
+        );
+
+        [[-0.5, 0.5], [-0.5, -0.5], [0.5, -0.5], [0.5, 0.5]]
+            .iter()
+            .map(|[x, y]| {
+                let p = model.transform_point3(Vec3::new(*x, *y, 0.0));
+                Vec2::new(p.x as f64, p.y as f64)
+            })
+            .collect()
+    }
+
     pub fn update_vel_y(&mut self, dt: f64) {
         self.position.y += self.velocity.y * dt;
     }
@@ -912,6 +1329,19 @@ impl Rigidbody {
         }
     }
 
+    fn wren_occluder_getter(&self, vm: &VM) {
+        vm.set_slot_bool(0, self.occluder);
+    }
+
+    fn wren_occluder_setter(&mut self, vm: &VM) {
+        match vm.get_slot_bool(1) {
+            Some(occluder) => self.occluder = occluder,
+            None => {
+                LilahTypeError!(Rigidbody, 1, bool);
+            }
+        }
+    }
+
     fn wren_colliding_getter(&mut self, vm: &VM) {
         if let Some(coll) = self.colliding.clone() {
             vm.set_slot_new_map(0);
@@ -1045,6 +1475,20 @@ impl Rigidbody {
         }
     }
 
+    fn wren_set_occluder_from_gameobject(vm: &VM) {
+        match vm.get_slot_foreign_mut::<GameObject>(1) {
+            Some(comp) => match vm.get_slot_bool(2) {
+                Some(occluder) => comp.get_mut::<Rigidbody>().occluder = occluder,
+                None => {
+                    LilahTypeError!(Rigidbody, 2, bool);
+                }
+            },
+            None => {
+                LilahTypeError!(Rigidbody, 1, GameObject);
+            }
+        }
+    }
+
     fn wren_update_vel_from_gameobject(vm: &VM) {
         match vm.get_slot_foreign_mut::<GameObject>(1) {
             Some(comp) => match vm.get_slot_foreign::<Vec2>(2) {
@@ -1404,221 +1848,185 @@ impl Animator {
     }
 }
 
-impl Text {
-    #[rustfmt::skip]
-    const DEF_VERTICES: [Vertex; 4] =  [
-        Vertex([-0.5, -0.5],  [0.0, 1.0]),
-        Vertex([ 0.5, -0.5],  [1.0, 1.0]),
-        Vertex([ 0.5,  0.5],  [1.0, 0.0]),
-        Vertex([-0.5,  0.5],  [0.0, 0.0]),
-    ];
-
-    #[rustfmt::skip]
-    const DEF_INDICES: [i32; 6] = [
-        0, 1, 2,
-        2, 3, 0
-    ];
-
-    pub fn new(t: &str, font: &str) -> Self {
+impl Shape {
+    pub fn new(kind: ShapeKind) -> Self {
         Self {
             parent: String::from(""),
-            text: t.to_string(),
-            font_size: 24,
-            font: font.to_string(),
-            texture_id: Uuid::new_v4().to_string(),
-            changed: true,
-            vertex_array: None,
-            vertex_buffer: None,
-            color: Color::new(1.0, 1.0, 1.0, 1.0),
+            kind,
+            filled: true,
+            thickness: 1.0,
+            color: Color::WHITE,
+            sort: 0,
             sort_dirty: true,
-            sort: 1000,
+            sort_layer: String::from("world"),
+            sort_pivot: 0.0,
         }
     }
 
-    pub fn set_sort(&mut self, s: u32) {
-        self.sort_dirty = true;
-        self.sort = s;
+    pub fn rect(size: Vec2) -> Self {
+        Shape::new(ShapeKind::Rect(size))
     }
 
-    pub fn get_sort(&self) -> u32 {
-        self.sort
+    pub fn circle(radius: f64) -> Self {
+        Shape::new(ShapeKind::Circle(radius))
     }
 
-    pub fn get_text(&self) -> &String {
-        &self.text
+    pub fn polygon(points: Vec<Vec2>) -> Self {
+        Shape::new(ShapeKind::Polygon(points))
     }
 
-    pub fn set_text(&mut self, t: &str) {
-        self.text = t.to_string();
-        self.changed = true;
+    pub fn with_color(mut self, color: Color) -> Shape {
+        self.color = color;
+        self
     }
 
-    pub fn get_font(&self) -> &String {
-        &self.font
+    pub fn outlined(mut self, thickness: f64) -> Shape {
+        self.filled = false;
+        self.thickness = thickness;
+        self
     }
 
-    pub fn set_font(&mut self, t: &str) {
-        self.font = t.to_string();
-        self.changed = true;
+    pub fn with_sort(mut self, sort: u32) -> Shape {
+        self.sort = sort;
+        self
     }
 
-    pub fn get_font_size(&self) -> u32 {
-        self.font_size
+    pub fn with_sort_layer(mut self, layer: &str) -> Shape {
+        self.sort_layer = layer.to_string();
+        self
     }
 
-    pub fn set_font_size(&mut self, s: u32) {
-        self.font_size = s;
-        self.changed = true;
+    pub fn build(self) -> Shape {
+        self
+    }
+
+    pub fn set_sort(&mut self, s: u32) {
+        self.sort = s;
+        self.sort_dirty = true;
+    }
+
+    pub fn get_sort(&self) -> u32 {
+        self.sort
     }
 
-    pub fn load(&mut self, app: &mut App, fonts: &HashMap<String, Font>) -> StateUpdateContainer {
+    pub fn check_dirty(&mut self) -> bool {
         if self.sort_dirty {
             self.sort_dirty = false;
-            app.sort_dirty = true;
+            true
+        } else {
+            false
         }
+    }
 
-        if self.vertex_array.is_none() {
-            unsafe {
-                let vao = VertexArray::new();
-                vao.bind();
-
-                let vbo = Buffer::new(gl::ARRAY_BUFFER);
-                vbo.set_data(&Text::DEF_VERTICES, gl::STATIC_DRAW);
+    /// Outline of the shape in local space. Circles get more segments as they grow.
+    pub fn local_points(&self) -> Vec<Vec2> {
+        match &self.kind {
+            ShapeKind::Rect(size) => {
+                let half = *size / 2.0;
+                vec![
+                    Vec2::new(-half.x, -half.y),
+                    Vec2::new(half.x, -half.y),
+                    Vec2::new(half.x, half.y),
+                    Vec2::new(-half.x, half.y),
+                ]
+            }
+            ShapeKind::Circle(radius) => {
+                let segments = (radius.abs().sqrt() * 4.0).clamp(12.0, 96.0) as usize;
+                (0..segments)
+                    .map(|i| {
+                        let a = i as f64 / segments as f64 * std::f64::consts::TAU;
+                        Vec2::new(a.cos() * radius, a.sin() * radius)
+                    })
+                    .collect()
+            }
+            ShapeKind::Polygon(points) => points.clone(),
+        }
+    }
 
-                let ibo = Buffer::new(gl::ELEMENT_ARRAY_BUFFER);
-                ibo.set_data(&Text::DEF_INDICES, gl::STATIC_DRAW);
+    fn world_points(&self, t: &Transform) -> Vec<[f32; 2]> {
+        let model = Mat4::from_scale_rotation_translation(
+            Vec3::new(t.scale.x as f32, t.scale.y as f32, 1.0),
+            Quat::from_rotation_z(t.rotation),
+            Vec3::new(
+                t.position.x as f32 + t.pivot.x as f32,
+                t.position.y as f32 + t.pivot.y as f32,
+                0.0,
+            ),
+        );
 
-                let pos_attrib = app.text_program.get_attrib_location("position").unwrap();
-                set_attribute!(vao, pos_attrib, Vertex::0, gl::FLOAT);
-                let color_attrib = app
-                    .text_program
-                    .get_attrib_location("vertexTexCoord")
-                    .unwrap();
-                set_attribute!(vao, color_attrib, Vertex::1, gl::FLOAT);
+        self.local_points()
+            .iter()
+            .map(|p| {
+                let p = model.transform_point3(Vec3::new(p.x as f32, p.y as f32, 0.0));
+                [p.x, p.y]
+            })
+            .collect()
+    }
 
-                self.vertex_array = Some(vao);
-                self.vertex_buffer = Some(vbo);
-            }
+    /// Min and max corners in world space, padded by half the outline. None without points.
+    pub fn world_bounds(&self, t: &Transform) -> Option<(Vec2, Vec2)> {
+        let points = self.world_points(t);
+        if points.is_empty() {
+            return None;
         }
 
-        if self.changed {
-            self.changed = false;
+        let (min, max) = math::bounds_of(&points);
+        let pad = if self.filled { 0.0 } else { self.thickness / 2.0 };
+        Some((min - Vec2::new(pad, pad), max + Vec2::new(pad, pad)))
+    }
 
-            if let Some(font) = fonts.get(&self.font) {
-                let scale = Scale::uniform(self.font_size as f32);
-                let colour = (255, 255, 255);
-                let v_metrics = font.v_metrics(scale);
-
-                // layout the glyphs in a line with 20 pixels padding
-                let glyphs: Vec<_> = font
-                    .layout(&self.text, scale, point(20.0, 20.0 + v_metrics.ascent))
-                    .collect();
-
-                // work out the layout size
-                let glyphs_height = (v_metrics.ascent - v_metrics.descent).ceil() as u32;
-                let glyphs_width = {
-                    let min_x = glyphs
-                        .first()
-                        .map(|g| g.pixel_bounding_box().unwrap().min.x)
-                        .unwrap();
-                    let max_x = glyphs
-                        .last()
-                        .map(|g| g.pixel_bounding_box().unwrap().max.x)
-                        .unwrap();
-                    (max_x - min_x) as u32
-                };
+    /// Queues the shape as quads on the white texture. Fills are fanned from the first point,
+    /// outlines are one quad per edge extended by half the thickness so corners meet.
+    pub fn draw(&self, app: &mut App, t: &Transform) {
+        let points = self.world_points(t);
+        let uvs = [[0.5, 0.5]; 4];
+        let mut quads = vec![];
 
-                // Create a new rgba image with some padding
-                let mut image: image::ImageBuffer<Rgba<u8>, Vec<u8>> =
-                    DynamicImage::new_rgba8(glyphs_width + 40, glyphs_height + 40).to_rgba8();
-
-                // Loop through the glyphs in the text, positing each one on a line
-                for glyph in glyphs {
-                    if let Some(bounding_box) = glyph.pixel_bounding_box() {
-                        // Draw the glyph into the image per-pixel by using the draw closure
-                        glyph.draw(|x, y, v| {
-                            image.put_pixel(
-                                // Offset the position by the glyph bounding box
-                                x + bounding_box.min.x as u32,
-                                y + bounding_box.min.y as u32,
-                                // Turn the coverage into an alpha value
-                                Rgba([255, 255, 255, (v * 255.0) as u8]),
-                            )
-                        });
-                    }
+        if self.filled {
+            for i in 1..points.len().saturating_sub(1) {
+                quads.push([points[0], points[i], points[i + 1], points[i + 1]]);
+            }
+        } else if points.len() > 1 {
+            let half = self.thickness as f32 / 2.0;
+            // open polygons with two points are a single segment
+            let edges = if points.len() == 2 { 1 } else { points.len() };
+            for i in 0..edges {
+                let (a, b) = (points[i], points[(i + 1) % points.len()]);
+                let (dx, dy) = (b[0] - a[0], b[1] - a[1]);
+                let len = (dx * dx + dy * dy).sqrt();
+                if len == 0.0 {
+                    continue;
                 }
 
-                StateUpdateContainer {
-                    textures: Some((self.texture_id.clone(), image)),
-                    sfx: None,
-                }
-            } else {
-                let f = self.font.clone();
-                LilahNotFoundError!(Text, String, f);
-                StateUpdateContainer {
-                    textures: None,
-                    sfx: None,
-                }
-            }
-        } else {
-            StateUpdateContainer {
-                textures: None,
-                sfx: None,
+                let (dx, dy) = (dx / len * half, dy / len * half);
+                let (a, b) = ([a[0] - dx, a[1] - dy], [b[0] + dx, b[1] + dy]);
+                quads.push([
+                    [a[0] + dy, a[1] - dx],
+                    [b[0] + dy, b[1] - dx],
+                    [b[0] - dy, b[1] + dx],
+                    [a[0] - dy, a[1] + dx],
+                ]);
             }
         }
-    }
-
-    pub fn draw(&self, app: &mut App, textures: &HashMap<String, LilahTexture>, t: &Transform) {
-        let model = Mat4::IDENTITY
-            * Mat4::from_scale_rotation_translation(
-                Vec3::new(
-                    textures[&self.texture_id].size.x as f32,
-                    textures[&self.texture_id].size.y as f32,
-                    1.0,
-                ) * Vec3::new(t.scale.x as f32, t.scale.y as f32, 1.0),
-                Quat::from_rotation_z(t.rotation),
-                Vec3::new(
-                    t.position.x as f32 + t.pivot.x as f32, // + (textures[&self.texture_id].size.x / 2.0) as f32,
-                    t.position.y as f32 + t.pivot.y as f32, // - (textures[&self.texture_id].size.y / 2.0) as f32,
-                    0.0,
-                ),
-            );
-
-        let view = unsafe { *crate::math::VIEW_MATRIX };
-        let projection = unsafe { *crate::math::PROJECTION_MATRIX };
-
-        let mvp = projection * view * model;
 
         unsafe {
-            textures[&self.texture_id].bind();
-
-            app.text_program.apply();
-
-            self.vertex_array.as_ref().unwrap().bind();
-
-            let mat_attr =
-                gl::GetUniformLocation(app.text_program.id, CString::new("mvp").unwrap().as_ptr());
-            gl::UniformMatrix4fv(mat_attr, 1, gl::FALSE as GLboolean, &mvp.to_cols_array()[0]);
-
-            let tint_attr =
-                gl::GetUniformLocation(app.text_program.id, CString::new("tint").unwrap().as_ptr());
-            gl::Uniform4f(
-                tint_attr,
-                self.color.r,
-                self.color.g,
-                self.color.b,
-                self.color.a,
-            );
-
-            let sort_attr =
-                gl::GetUniformLocation(app.text_program.id, CString::new("sort").unwrap().as_ptr());
-            gl::Uniform1f(sort_attr, self.sort as f32);
-
-            gl::DrawElements(gl::TRIANGLES, 6, gl::UNSIGNED_INT, 0 as *const _);
+            app.batch.set_blend(BlendMode::Alpha);
+        }
+        for corners in quads {
+            unsafe {
+                app.batch.push_quad(
+                    &app.default_program,
+                    &app.white_texture,
+                    &[],
+                    corners,
+                    uvs,
+                    &self.color,
+                );
+            }
         }
     }
 
-    //wren
+    //for wren
     fn wren_as_component(&self, vm: &VM) {
         send_foreign!(vm, "game", "Component", Box::new(self.clone()) as Box<dyn Component> => 0);
     }
@@ -1627,33 +2035,1231 @@ impl Text {
         vm.set_slot_string(0, self.parent.clone());
     }
 
-    fn wren_get_text(&self, vm: &VM) {
-        vm.set_slot_string(0, self.text.clone());
+    fn wren_get_color(&self, vm: &VM) {
+        vm.set_slot_new_list(0);
+        for (i, c) in [self.color.r, self.color.g, self.color.b, self.color.a]
+            .iter()
+            .enumerate()
+        {
+            vm.set_slot_double(1, *c as f64);
+            vm.insert_in_list(0, i as i32, 1);
+        }
     }
 
-    fn wren_get_font(&self, vm: &VM) {
-        vm.set_slot_string(0, self.font.clone());
+    fn wren_get_filled(&self, vm: &VM) {
+        vm.set_slot_bool(0, self.filled);
     }
 
-    fn wren_get_font_size(&self, vm: &VM) {
-        vm.set_slot_double(0, self.font_size as f64);
+    fn wren_get_thickness(&self, vm: &VM) {
+        vm.set_slot_double(0, self.thickness);
     }
 
-    fn wren_set_text(&mut self, vm: &VM) {
-        let a = vm.get_slot_string(1);
-        if let Some(a) = a {
-            self.set_text(&a);
-        } else {
-            LilahTypeError!(Text, 1, String);
-        }
+    fn wren_get_sort(&self, vm: &VM) {
+        vm.set_slot_double(0, self.sort as f64);
     }
 
-    fn wren_set_font(&mut self, vm: &VM) {
-        let a = vm.get_slot_string(1);
-        if let Some(a) = a {
-            self.set_font(&a);
-        } else {
-            LilahTypeError!(Text, 1, String);
+    fn wren_set_color_from_gameobject(vm: &VM) {
+        match vm.get_slot_foreign_mut::<GameObject>(1) {
+            Some(comp) => match color_from_slot(vm, 2, 3) {
+                Some(color) => {
+                    comp.get_mut::<Shape>().color = color;
+                }
+                None => {
+                    LilahTypeError!(Shape, 2, List);
+                }
+            },
+            None => {
+                LilahTypeError!(Shape, 1, GameObject);
+            }
+        }
+    }
+
+    fn wren_set_filled_from_gameobject(vm: &VM) {
+        match vm.get_slot_foreign_mut::<GameObject>(1) {
+            Some(comp) => match vm.get_slot_bool(2) {
+                Some(filled) => {
+                    comp.get_mut::<Shape>().filled = filled;
+                }
+                None => {
+                    LilahTypeError!(Shape, 2, bool);
+                }
+            },
+            None => {
+                LilahTypeError!(Shape, 1, GameObject);
+            }
+        }
+    }
+
+    fn wren_set_thickness_from_gameobject(vm: &VM) {
+        match vm.get_slot_foreign_mut::<GameObject>(1) {
+            Some(comp) => match vm.get_slot_double(2) {
+                Some(thickness) => {
+                    comp.get_mut::<Shape>().thickness = thickness;
+                }
+                None => {
+                    LilahTypeError!(Shape, 2, f64);
+                }
+            },
+            None => {
+                LilahTypeError!(Shape, 1, GameObject);
+            }
+        }
+    }
+
+    fn wren_set_sort_from_gameobject(vm: &VM) {
+        match vm.get_slot_foreign_mut::<GameObject>(1) {
+            Some(comp) => match vm.get_slot_double(2) {
+                Some(sort) => {
+                    comp.get_mut::<Shape>().set_sort(sort as u32);
+                }
+                None => {
+                    LilahTypeError!(Shape, 2, f64);
+                }
+            },
+            None => {
+                LilahTypeError!(Shape, 1, GameObject);
+            }
+        }
+    }
+
+    fn wren_set_geometry_from_gameobject(vm: &VM) {
+        match vm.get_slot_foreign_mut::<GameObject>(1) {
+            Some(comp) => match shape_kind_from_slot(vm, 2, 3, 4) {
+                Some(kind) => {
+                    comp.get_mut::<Shape>().kind = kind;
+                }
+                None => {
+                    LilahTypeError!(Shape, 3, Geometry);
+                }
+            },
+            None => {
+                LilahTypeError!(Shape, 1, GameObject);
+            }
+        }
+    }
+}
+
+impl Light2D {
+    pub fn new(radius: f64) -> Self {
+        Self {
+            parent: String::from(""),
+            radius,
+            color: Color::WHITE,
+            intensity: 1.0,
+            falloff: 1.0,
+            shadows: true,
+            softness: 0.0,
+        }
+    }
+
+    pub fn with_color(mut self, color: Color) -> Light2D {
+        self.color = color;
+        self
+    }
+
+    pub fn with_intensity(mut self, intensity: f64) -> Light2D {
+        self.intensity = intensity;
+        self
+    }
+
+    pub fn with_falloff(mut self, falloff: f64) -> Light2D {
+        self.falloff = falloff;
+        self
+    }
+
+    pub fn with_shadows(mut self, shadows: bool) -> Light2D {
+        self.shadows = shadows;
+        self
+    }
+
+    pub fn with_softness(mut self, softness: f64) -> Light2D {
+        self.softness = softness;
+        self
+    }
+
+    pub fn build(self) -> Light2D {
+        self
+    }
+
+    /// Points shadows are cast from. Just the center for hard shadows, otherwise count points
+    /// on a ring softness wide whose shadows are averaged.
+    pub fn shadow_samples(&self, position: Vec2, count: usize) -> Vec<Vec2> {
+        if self.softness <= 0.0 || count < 2 {
+            return vec![position];
+        }
+
+        (0..count)
+            .map(|i| {
+                let angle = std::f64::consts::TAU * i as f64 / count as f64;
+                position + Vec2::new(angle.cos(), angle.sin()) * self.softness
+            })
+            .collect()
+    }
+
+    //for wren
+    fn wren_as_component(&self, vm: &VM) {
+        send_foreign!(vm, "game", "Component", Box::new(self.clone()) as Box<dyn Component> => 0);
+    }
+
+    fn wren_get_parent(&self, vm: &VM) {
+        vm.set_slot_string(0, self.parent.clone());
+    }
+
+    fn wren_get_radius(&self, vm: &VM) {
+        vm.set_slot_double(0, self.radius);
+    }
+
+    fn wren_get_color(&self, vm: &VM) {
+        vm.set_slot_new_list(0);
+        for (i, c) in [self.color.r, self.color.g, self.color.b, self.color.a]
+            .iter()
+            .enumerate()
+        {
+            vm.set_slot_double(1, *c as f64);
+            vm.insert_in_list(0, i as i32, 1);
+        }
+    }
+
+    fn wren_get_intensity(&self, vm: &VM) {
+        vm.set_slot_double(0, self.intensity);
+    }
+
+    fn wren_get_falloff(&self, vm: &VM) {
+        vm.set_slot_double(0, self.falloff);
+    }
+
+    fn wren_get_shadows(&self, vm: &VM) {
+        vm.set_slot_bool(0, self.shadows);
+    }
+
+    fn wren_get_softness(&self, vm: &VM) {
+        vm.set_slot_double(0, self.softness);
+    }
+
+    fn wren_set_radius_from_gameobject(vm: &VM) {
+        match vm.get_slot_foreign_mut::<GameObject>(1) {
+            Some(comp) => match vm.get_slot_double(2) {
+                Some(radius) if radius > 0.0 => {
+                    comp.get_mut::<Light2D>().radius = radius;
+                }
+                Some(radius) => {
+                    LilahError!(Light2D, format!("Radius must be above 0, got {}", radius));
+                }
+                None => {
+                    LilahTypeError!(Light2D, 2, f64);
+                }
+            },
+            None => {
+                LilahTypeError!(Light2D, 1, GameObject);
+            }
+        }
+    }
+
+    fn wren_set_color_from_gameobject(vm: &VM) {
+        match vm.get_slot_foreign_mut::<GameObject>(1) {
+            Some(comp) => match color_from_slot(vm, 2, 3) {
+                Some(color) => {
+                    comp.get_mut::<Light2D>().color = color;
+                }
+                None => {
+                    LilahTypeError!(Light2D, 2, List);
+                }
+            },
+            None => {
+                LilahTypeError!(Light2D, 1, GameObject);
+            }
+        }
+    }
+
+    fn wren_set_intensity_from_gameobject(vm: &VM) {
+        match vm.get_slot_foreign_mut::<GameObject>(1) {
+            Some(comp) => match vm.get_slot_double(2) {
+                Some(intensity) => {
+                    comp.get_mut::<Light2D>().intensity = intensity;
+                }
+                None => {
+                    LilahTypeError!(Light2D, 2, f64);
+                }
+            },
+            None => {
+                LilahTypeError!(Light2D, 1, GameObject);
+            }
+        }
+    }
+
+    fn wren_set_falloff_from_gameobject(vm: &VM) {
+        match vm.get_slot_foreign_mut::<GameObject>(1) {
+            Some(comp) => match vm.get_slot_double(2) {
+                Some(falloff) => {
+                    comp.get_mut::<Light2D>().falloff = falloff;
+                }
+                None => {
+                    LilahTypeError!(Light2D, 2, f64);
+                }
+            },
+            None => {
+                LilahTypeError!(Light2D, 1, GameObject);
+            }
+        }
+    }
+
+    fn wren_set_shadows_from_gameobject(vm: &VM) {
+        match vm.get_slot_foreign_mut::<GameObject>(1) {
+            Some(comp) => match vm.get_slot_bool(2) {
+                Some(shadows) => {
+                    comp.get_mut::<Light2D>().shadows = shadows;
+                }
+                None => {
+                    LilahTypeError!(Light2D, 2, bool);
+                }
+            },
+            None => {
+                LilahTypeError!(Light2D, 1, GameObject);
+            }
+        }
+    }
+
+    fn wren_set_softness_from_gameobject(vm: &VM) {
+        match vm.get_slot_foreign_mut::<GameObject>(1) {
+            Some(comp) => match vm.get_slot_double(2) {
+                Some(softness) => {
+                    comp.get_mut::<Light2D>().softness = softness;
+                }
+                None => {
+                    LilahTypeError!(Light2D, 2, f64);
+                }
+            },
+            None => {
+                LilahTypeError!(Light2D, 1, GameObject);
+            }
+        }
+    }
+}
+
+/// Reads an immediate shape queued by Draw in wren, spread over slots first to first + 5 as
+/// kind, geometry, position, color, thickness (0 fills), sort.
+pub(crate) fn immediate_shape_from_slot(
+    vm: &VM,
+    first: usize,
+    scratch: usize,
+) -> Option<(Shape, Transform)> {
+    let Some(kind) = shape_kind_from_slot(vm, first, first + 1, scratch) else {
+        LilahTypeError!(Draw, 2, Geometry);
+        return None;
+    };
+    let Some(position) = vm.get_slot_foreign::<Vec2>(first + 2).copied() else {
+        LilahTypeError!(Draw, 3, Vec2);
+        return None;
+    };
+    let Some(color) = color_from_slot(vm, first + 3, scratch) else {
+        LilahTypeError!(Draw, 4, List);
+        return None;
+    };
+
+    let mut shape = Shape::new(kind).with_color(color);
+    let thickness = vm.get_slot_double(first + 4).unwrap_or(0f64);
+    if thickness > 0.0 {
+        shape = shape.outlined(thickness);
+    }
+    shape.sort = vm.get_slot_double(first + 5).unwrap_or(0f64) as u32;
+    Some((shape, Transform::new(position)))
+}
+
+/// Sends color to wren as [r, g, b, a].
+pub(crate) fn color_to_slot(vm: &VM, color: &Color, slot: usize, scratch: usize) {
+    vm.set_slot_new_list(slot);
+    for (i, c) in [color.r, color.g, color.b, color.a].iter().enumerate() {
+        vm.set_slot_double(scratch, *c as f64);
+        vm.insert_in_list(slot, i as i32, scratch);
+    }
+}
+
+/// Reads [r, g, b, a] from wren.
+pub(crate) fn color_from_slot(vm: &VM, slot: usize, scratch: usize) -> Option<Color> {
+    if !matches!(vm.get_slot_type(slot), SlotType::List) {
+        return None;
+    }
+    let count = vm.get_list_count(slot)?;
+    if count != 4 {
+        LilahError!(Color, format!("Expected a color of 4 numbers, got {}", count));
+        return None;
+    }
+
+    let mut c = [0.0f32; 4];
+    for (i, v) in c.iter_mut().enumerate() {
+        vm.get_list_element(slot, i as i32, scratch);
+        *v = vm.get_slot_double(scratch)? as f32;
+    }
+    Some(Color::new(c[0], c[1], c[2], c[3]))
+}
+
+/// Reads a shape from a kind string and its geometry: "rect" and a Vec2 size,
+/// "circle" and a radius, or "polygon" and a list of Vec2.
+fn shape_kind_from_slot(vm: &VM, kind: usize, value: usize, scratch: usize) -> Option<ShapeKind> {
+    match vm.get_slot_string(kind)?.as_str() {
+        "rect" => vm
+            .get_slot_foreign::<Vec2>(value)
+            .map(|size| ShapeKind::Rect(*size)),
+        "circle" => vm.get_slot_double(value).map(ShapeKind::Circle),
+        "polygon" => {
+            let mut points = vec![];
+            for i in 0..vm.get_list_count(value)? {
+                vm.get_list_element(value, i as i32, scratch);
+                points.push(*vm.get_slot_foreign::<Vec2>(scratch)?);
+            }
+            Some(ShapeKind::Polygon(points))
+        }
+        _ => None,
+    }
+}
+
+impl Camera {
+    pub fn new() -> Self {
+        Self {
+            parent: String::from(""),
+            zoom: 1.0,
+            rotation: 0.0,
+            target: None,
+            smoothing: 0.0,
+            dead_zone: Vec2::ZERO,
+            bounds: None,
+            bounds_scene: None,
+            trauma: 0.0,
+            trauma_decay: 1.0,
+            max_shake_offset: Vec2::new(16.0, 16.0),
+            max_shake_angle: 0.1,
+            viewport: [0.0, 0.0, 1.0, 1.0],
+            order: 0,
+            shake_offset: Vec2::ZERO,
+            shake_angle: 0.0,
+            shake_time: 0.0,
+        }
+    }
+
+    pub fn with_zoom(mut self, zoom: f64) -> Camera {
+        self.set_zoom(zoom);
+        self
+    }
+
+    /// Zoom of 0 or below would collapse or mirror the view, it is refused and the old zoom kept.
+    pub fn set_zoom(&mut self, zoom: f64) {
+        if zoom > 0.0 {
+            self.zoom = zoom;
+        } else {
+            LilahError!(Camera, format!("Zoom must be above 0, got {}", zoom));
+        }
+    }
+
+    pub fn following(mut self, key: &str) -> Camera {
+        self.target = Some(key.to_string());
+        self
+    }
+
+    pub fn with_smoothing(mut self, smoothing: f64) -> Camera {
+        self.smoothing = smoothing;
+        self
+    }
+
+    pub fn with_dead_zone(mut self, dead_zone: Vec2) -> Camera {
+        self.dead_zone = dead_zone;
+        self
+    }
+
+    pub fn with_bounds(mut self, min: Vec2, max: Vec2) -> Camera {
+        self.bounds = Some((min, max));
+        self
+    }
+
+    pub fn bounded_by_scene(mut self, key: &str) -> Camera {
+        self.bounds_scene = Some(key.to_string());
+        self
+    }
+
+    pub fn with_viewport(mut self, viewport: [f64; 4]) -> Camera {
+        self.viewport = viewport;
+        self
+    }
+
+    pub fn with_order(mut self, order: i32) -> Camera {
+        self.order = order;
+        self
+    }
+
+    pub fn build(self) -> Camera {
+        self
+    }
+
+    /// Adds trauma, clamped to 1.
+    pub fn shake(&mut self, amount: f64) {
+        self.trauma = (self.trauma + amount).clamp(0.0, 1.0);
+    }
+
+    /// Half the world size visible through this camera.
+    pub fn half_extents(&self, view_size: Vec2, t: &Transform) -> Vec2 {
+        Vec2::new(
+            view_size.x * self.viewport[2] / (2.0 * self.zoom * t.scale.x),
+            view_size.y * self.viewport[3] / (2.0 * self.zoom * t.scale.y),
+        )
+    }
+
+    /// Moves position toward target, leaving it alone while the target is inside the dead zone.
+    pub fn follow(&self, position: Vec2, target: Vec2, dt: f64) -> Vec2 {
+        let mut goal = position;
+        let diff = target - position;
+        if diff.x.abs() > self.dead_zone.x {
+            goal.x = target.x - self.dead_zone.x * diff.x.signum();
+        }
+        if diff.y.abs() > self.dead_zone.y {
+            goal.y = target.y - self.dead_zone.y * diff.y.signum();
+        }
+
+        if self.smoothing <= 0.0 {
+            return goal;
+        }
+        Vec2::lerp(position, goal, 1.0 - (-dt / self.smoothing).exp())
+    }
+
+    /// Keeps a view of half_extents around position inside bounds, centering on axes bounds are too small for.
+    pub fn clamp(position: Vec2, half_extents: Vec2, bounds: (Vec2, Vec2)) -> Vec2 {
+        let axis = |p: f64, half: f64, min: f64, max: f64| {
+            if max - min < half * 2.0 {
+                (min + max) / 2.0
+            } else {
+                p.clamp(min + half, max - half)
+            }
+        };
+
+        Vec2::new(
+            axis(position.x, half_extents.x, bounds.0.x, bounds.1.x),
+            axis(position.y, half_extents.y, bounds.0.y, bounds.1.y),
+        )
+    }
+
+    /// Advances the shake and decays trauma.
+    pub fn update(&mut self, dt: f64) {
+        self.shake_time += dt;
+        let shake = self.trauma * self.trauma;
+
+        self.shake_offset = Vec2::new(
+            self.max_shake_offset.x * shake * Camera::noise(1.0, self.shake_time),
+            self.max_shake_offset.y * shake * Camera::noise(2.0, self.shake_time),
+        );
+        self.shake_angle =
+            self.max_shake_angle * (shake * Camera::noise(3.0, self.shake_time)) as f32;
+
+        self.trauma = (self.trauma - self.trauma_decay * dt).max(0.0);
+    }
+
+    /// Smooth noise in -1 to 1, seed picks the channel.
+    fn noise(seed: f64, t: f64) -> f64 {
+        (t * 23.0 + seed).sin() * 0.5
+            + (t * 37.0 + seed * 1.7).sin() * 0.3
+            + (t * 59.0 + seed * 2.3).sin() * 0.2
+    }
+
+    pub fn view_matrix(&self, t: &Transform) -> Mat4 {
+        let position = t.position + self.shake_offset;
+        let rotation = t.rotation + self.rotation + self.shake_angle;
+
+        Mat4::from_scale(Vec3::new(
+            (t.scale.x * self.zoom) as f32,
+            (t.scale.y * self.zoom) as f32,
+            1.0,
+        )) * Mat4::from_rotation_z(-rotation)
+            * Mat4::from_translation(Vec3::new(-position.x as f32, -position.y as f32, 0.0))
+    }
+
+    /// Points the viewport, projection and view matrix at this camera.
+    pub fn apply(&self, app: &mut App, t: &Transform) {
+        app.set_camera_viewport(self.viewport);
+        unsafe {
+            *crate::math::VIEW_MATRIX = self.view_matrix(t);
+        }
+    }
+
+    //for wren
+    fn wren_as_component(&self, vm: &VM) {
+        send_foreign!(vm, "game", "Component", Box::new(self.clone()) as Box<dyn Component> => 0);
+    }
+
+    fn wren_get_parent(&self, vm: &VM) {
+        vm.set_slot_string(0, self.parent.clone());
+    }
+
+    fn wren_zoom_getter(&self, vm: &VM) {
+        vm.set_slot_double(0, self.zoom);
+    }
+
+    fn wren_rotation_getter(&self, vm: &VM) {
+        vm.set_slot_double(0, self.rotation as f64);
+    }
+
+    fn wren_target_getter(&self, vm: &VM) {
+        match &self.target {
+            Some(target) => vm.set_slot_string(0, target.clone()),
+            None => vm.set_slot_null(0),
+        }
+    }
+
+    fn wren_smoothing_getter(&self, vm: &VM) {
+        vm.set_slot_double(0, self.smoothing);
+    }
+
+    fn wren_dead_zone_getter(&self, vm: &VM) {
+        send_foreign!(vm, "math", "Vec2", self.dead_zone => 0);
+    }
+
+    fn wren_bounds_getter(&self, vm: &VM) {
+        match self.bounds {
+            Some((min, max)) => {
+                vm.set_slot_new_list(0);
+                vm.set_slot_new_foreign_scratch("math", "Vec2", min, 1, 2);
+                vm.insert_in_list(0, 0, 1);
+                vm.set_slot_new_foreign_scratch("math", "Vec2", max, 1, 2);
+                vm.insert_in_list(0, 1, 1);
+            }
+            None => vm.set_slot_null(0),
+        }
+    }
+
+    fn wren_trauma_getter(&self, vm: &VM) {
+        vm.set_slot_double(0, self.trauma);
+    }
+
+    fn wren_viewport_getter(&self, vm: &VM) {
+        vm.set_slot_new_list(0);
+        for (i, v) in self.viewport.iter().enumerate() {
+            vm.set_slot_double(1, *v);
+            vm.insert_in_list(0, i as i32, 1);
+        }
+    }
+
+    fn wren_order_getter(&self, vm: &VM) {
+        vm.set_slot_double(0, self.order as f64);
+    }
+
+    fn wren_set_zoom_from_gameobject(vm: &VM) {
+        match vm.get_slot_foreign_mut::<GameObject>(1) {
+            Some(comp) => match vm.get_slot_double(2) {
+                Some(zoom) => {
+                    comp.get_mut::<Camera>().set_zoom(zoom);
+                }
+                None => {
+                    LilahTypeError!(Camera, 2, f64);
+                }
+            },
+            None => {
+                LilahTypeError!(Camera, 1, GameObject);
+            }
+        }
+    }
+
+    fn wren_set_rotation_from_gameobject(vm: &VM) {
+        match vm.get_slot_foreign_mut::<GameObject>(1) {
+            Some(comp) => match vm.get_slot_double(2) {
+                Some(rotation) => {
+                    comp.get_mut::<Camera>().rotation = rotation as f32;
+                }
+                None => {
+                    LilahTypeError!(Camera, 2, f64);
+                }
+            },
+            None => {
+                LilahTypeError!(Camera, 1, GameObject);
+            }
+        }
+    }
+
+    fn wren_set_target_from_gameobject(vm: &VM) {
+        match vm.get_slot_foreign_mut::<GameObject>(1) {
+            Some(comp) => match vm.get_slot_type(2) {
+                SlotType::String => {
+                    comp.get_mut::<Camera>().target = vm.get_slot_string(2);
+                }
+                SlotType::Null => {
+                    comp.get_mut::<Camera>().target = None;
+                }
+                _ => {
+                    LilahTypeError!(Camera, 2, String);
+                }
+            },
+            None => {
+                LilahTypeError!(Camera, 1, GameObject);
+            }
+        }
+    }
+
+    fn wren_set_smoothing_from_gameobject(vm: &VM) {
+        match vm.get_slot_foreign_mut::<GameObject>(1) {
+            Some(comp) => match vm.get_slot_double(2) {
+                Some(smoothing) => {
+                    comp.get_mut::<Camera>().smoothing = smoothing;
+                }
+                None => {
+                    LilahTypeError!(Camera, 2, f64);
+                }
+            },
+            None => {
+                LilahTypeError!(Camera, 1, GameObject);
+            }
+        }
+    }
+
+    fn wren_set_dead_zone_from_gameobject(vm: &VM) {
+        match vm.get_slot_foreign_mut::<GameObject>(1) {
+            Some(comp) => match vm.get_slot_foreign::<Vec2>(2) {
+                Some(dead_zone) => {
+                    comp.get_mut::<Camera>().dead_zone = *dead_zone;
+                }
+                None => {
+                    LilahTypeError!(Camera, 2, Vec2);
+                }
+            },
+            None => {
+                LilahTypeError!(Camera, 1, GameObject);
+            }
+        }
+    }
+
+    fn wren_set_bounds_from_gameobject(vm: &VM) {
+        match vm.get_slot_foreign_mut::<GameObject>(1) {
+            Some(comp) => {
+                let camera = comp.get_mut::<Camera>();
+                if matches!(vm.get_slot_type(2), SlotType::Null) {
+                    camera.bounds = None;
+                    camera.bounds_scene = None;
+                    return;
+                }
+
+                match (vm.get_slot_foreign::<Vec2>(2), vm.get_slot_foreign::<Vec2>(3)) {
+                    (Some(min), Some(max)) => {
+                        camera.bounds = Some((*min, *max));
+                        camera.bounds_scene = None;
+                    }
+                    _ => {
+                        LilahTypeError!(Camera, 2, Vec2);
+                    }
+                }
+            }
+            None => {
+                LilahTypeError!(Camera, 1, GameObject);
+            }
+        }
+    }
+
+    fn wren_clamp_to_scene_from_gameobject(vm: &VM) {
+        match vm.get_slot_foreign_mut::<GameObject>(1) {
+            Some(comp) => match vm.get_slot_string(2) {
+                Some(key) => {
+                    comp.get_mut::<Camera>().bounds_scene = Some(key);
+                }
+                None => {
+                    LilahTypeError!(Camera, 2, String);
+                }
+            },
+            None => {
+                LilahTypeError!(Camera, 1, GameObject);
+            }
+        }
+    }
+
+    fn wren_shake_from_gameobject(vm: &VM) {
+        match vm.get_slot_foreign_mut::<GameObject>(1) {
+            Some(comp) => match vm.get_slot_double(2) {
+                Some(amount) => {
+                    comp.get_mut::<Camera>().shake(amount);
+                }
+                None => {
+                    LilahTypeError!(Camera, 2, f64);
+                }
+            },
+            None => {
+                LilahTypeError!(Camera, 1, GameObject);
+            }
+        }
+    }
+
+    fn wren_set_trauma_decay_from_gameobject(vm: &VM) {
+        match vm.get_slot_foreign_mut::<GameObject>(1) {
+            Some(comp) => match vm.get_slot_double(2) {
+                Some(decay) => {
+                    comp.get_mut::<Camera>().trauma_decay = decay;
+                }
+                None => {
+                    LilahTypeError!(Camera, 2, f64);
+                }
+            },
+            None => {
+                LilahTypeError!(Camera, 1, GameObject);
+            }
+        }
+    }
+
+    fn wren_set_max_shake_from_gameobject(vm: &VM) {
+        match vm.get_slot_foreign_mut::<GameObject>(1) {
+            Some(comp) => match (vm.get_slot_foreign::<Vec2>(2), vm.get_slot_double(3)) {
+                (Some(offset), Some(angle)) => {
+                    let camera = comp.get_mut::<Camera>();
+                    camera.max_shake_offset = *offset;
+                    camera.max_shake_angle = angle as f32;
+                }
+                (None, _) => {
+                    LilahTypeError!(Camera, 2, Vec2);
+                }
+                (_, None) => {
+                    LilahTypeError!(Camera, 3, f64);
+                }
+            },
+            None => {
+                LilahTypeError!(Camera, 1, GameObject);
+            }
+        }
+    }
+
+    fn wren_set_viewport_from_gameobject(vm: &VM) {
+        match vm.get_slot_foreign_mut::<GameObject>(1) {
+            Some(comp) => {
+                if !matches!(vm.get_slot_type(2), SlotType::List) {
+                    LilahTypeError!(Camera, 2, List);
+                    return;
+                }
+                let count = vm.get_list_count(2).unwrap_or(0);
+                if count != 4 {
+                    LilahError!(Camera, format!("Expected a viewport of 4 numbers, got {}", count));
+                    return;
+                }
+
+                let mut viewport = [0.0; 4];
+                for (i, v) in viewport.iter_mut().enumerate() {
+                    vm.get_list_element(2, i as i32, 3);
+                    match vm.get_slot_double(3) {
+                        Some(n) => *v = n,
+                        None => {
+                            LilahTypeError!(Camera, 2, List);
+                            return;
+                        }
+                    }
+                }
+                comp.get_mut::<Camera>().viewport = viewport;
+            }
+            None => {
+                LilahTypeError!(Camera, 1, GameObject);
+            }
+        }
+    }
+
+    fn wren_set_order_from_gameobject(vm: &VM) {
+        match vm.get_slot_foreign_mut::<GameObject>(1) {
+            Some(comp) => match vm.get_slot_double(2) {
+                Some(order) => {
+                    comp.get_mut::<Camera>().order = order as i32;
+                }
+                None => {
+                    LilahTypeError!(Camera, 2, f64);
+                }
+            },
+            None => {
+                LilahTypeError!(Camera, 1, GameObject);
+            }
+        }
+    }
+}
+
+impl Text {
+    #[rustfmt::skip]
+    const DEF_VERTICES: [Vertex; 4] =  [
+        Vertex([-0.5, -0.5],  [0.0, 1.0]),
+        Vertex([ 0.5, -0.5],  [1.0, 1.0]),
+        Vertex([ 0.5,  0.5],  [1.0, 0.0]),
+        Vertex([-0.5,  0.5],  [0.0, 0.0]),
+    ];
+
+    pub fn new(t: &str, font: &str) -> Self {
+        Self {
+            parent: String::from(""),
+            text: t.to_string(),
+            font_size: 24,
+            font: font.to_string(),
+            changed: true,
+            max_width: None,
+            min_height: None,
+            align: TextAlign::Left,
+            valign: VerticalAlign::Top,
+            line_spacing: 1.0,
+            bounds: Vec2::ZERO,
+            line_count: 0,
+            glyphs: vec![],
+            glyph_generation: 0,
+            box_size: Vec2::ZERO,
+            color: Color::new(1.0, 1.0, 1.0, 1.0),
+            outline_width: 0.0,
+            outline_color: Color::new(0.0, 0.0, 0.0, 1.0),
+            shadow_offset: Vec2::ZERO,
+            shadow_color: Color::new(0.0, 0.0, 0.0, 0.5),
+            effects: TextEffects::default(),
+            revealed: None,
+            reveal_speed: 0.0,
+            sort_dirty: true,
+            sort: 1000,
+            sort_layer: String::from("world"),
+            sort_pivot: 0.0,
+            material: None,
+            uniforms: Uniforms::default(),
+            blend: None,
+        }
+    }
+
+    pub fn set_sort(&mut self, s: u32) {
+        self.sort_dirty = true;
+        self.sort = s;
+    }
+
+    pub fn get_sort(&self) -> u32 {
+        self.sort
+    }
+
+    pub fn get_text(&self) -> &String {
+        &self.text
+    }
+
+    pub fn set_text(&mut self, t: &str) {
+        self.text = t.to_string();
+        self.changed = true;
+    }
+
+    pub fn get_font(&self) -> &String {
+        &self.font
+    }
+
+    pub fn set_font(&mut self, t: &str) {
+        self.font = t.to_string();
+        self.changed = true;
+    }
+
+    pub fn get_font_size(&self) -> u32 {
+        self.font_size
+    }
+
+    pub fn set_font_size(&mut self, s: u32) {
+        self.font_size = s;
+        self.changed = true;
+    }
+
+    pub fn get_max_width(&self) -> Option<f64> {
+        self.max_width
+    }
+
+    pub fn set_max_width(&mut self, width: Option<f64>) {
+        self.max_width = width;
+        self.changed = true;
+    }
+
+    pub fn get_min_height(&self) -> Option<f64> {
+        self.min_height
+    }
+
+    pub fn set_min_height(&mut self, height: Option<f64>) {
+        self.min_height = height;
+        self.changed = true;
+    }
+
+    pub fn get_align(&self) -> TextAlign {
+        self.align
+    }
+
+    pub fn set_align(&mut self, align: TextAlign) {
+        self.align = align;
+        self.changed = true;
+    }
+
+    pub fn get_valign(&self) -> VerticalAlign {
+        self.valign
+    }
+
+    pub fn set_valign(&mut self, valign: VerticalAlign) {
+        self.valign = valign;
+        self.changed = true;
+    }
+
+    pub fn get_line_spacing(&self) -> f64 {
+        self.line_spacing
+    }
+
+    pub fn set_line_spacing(&mut self, spacing: f64) {
+        self.line_spacing = spacing;
+        self.changed = true;
+    }
+
+    /// Width and height of the laid out lines in pixels, before the Transform scale.
+    pub fn get_bounds(&self) -> Vec2 {
+        self.bounds
+    }
+
+    pub fn get_line_count(&self) -> usize {
+        self.line_count
+    }
+
+    fn layout_options(&self) -> LayoutOptions {
+        LayoutOptions {
+            size: self.font_size as f32,
+            max_width: self.max_width.map(|w| w as f32),
+            min_height: self.min_height.map(|h| h as f32),
+            align: self.align,
+            valign: self.valign,
+            line_spacing: self.line_spacing as f32,
+        }
+    }
+
+    /// Copies values from other. The layout is rebuilt on the next load.
+    pub fn reset_from(&mut self, other: &Text) {
+        self.text = other.text.clone();
+        self.font = other.font.clone();
+        self.font_size = other.font_size;
+        self.max_width = other.max_width;
+        self.min_height = other.min_height;
+        self.align = other.align;
+        self.valign = other.valign;
+        self.line_spacing = other.line_spacing;
+        self.color = other.color.clone();
+        self.outline_width = other.outline_width;
+        self.outline_color = other.outline_color.clone();
+        self.shadow_offset = other.shadow_offset;
+        self.shadow_color = other.shadow_color.clone();
+        self.effects = other.effects.clone();
+        self.revealed = other.revealed;
+        self.reveal_speed = other.reveal_speed;
+        self.material = other.material.clone();
+        self.uniforms = other.uniforms.clone();
+        self.blend = other.blend;
+        if self.sort != other.sort {
+            self.set_sort(other.sort);
+        }
+        self.sort_pivot = other.sort_pivot;
+        if self.sort_layer != other.sort_layer {
+            self.set_sort_layer(&other.sort_layer);
+        }
+        self.changed = true;
+    }
+
+    /// Lays the text out again when it changed, caching any new glyphs, and moves the reveal on.
+    pub fn load(&mut self, app: &mut App, fonts: &HashMap<String, LilahFont>) {
+        if self.sort_dirty {
+            self.sort_dirty = false;
+            app.sort_dirty = true;
+        }
+
+        if self.changed || self.glyph_generation != app.glyphs.generation() {
+            self.changed = false;
+            self.relayout(app, fonts);
+        }
+
+        if let Some(revealed) = self.revealed {
+            let advanced = revealed + self.reveal_speed * app.time.delta_time;
+            self.revealed = Some(advanced.min(self.glyphs.len() as f64));
+        }
+    }
+
+    fn relayout(&mut self, app: &mut App, fonts: &HashMap<String, LilahFont>) {
+        if let Some(font) = fonts.get(&self.font) {
+            // markup colors tint each glyph, self.color tints the whole text when drawn
+            let spans =
+                crate::text::parse_markup(&self.text, &Color::WHITE, self.font_size as f32);
+            let layout = crate::text::layout(font, &spans, &self.layout_options());
+            self.bounds = Vec2::new(layout.bounds.0 as f64, layout.bounds.1 as f64);
+            self.line_count = layout.lines;
+            self.box_size = Vec2::new(
+                (layout.width.ceil() + PADDING as f32 * 2.0) as f64,
+                (layout.height.ceil() + PADDING as f32 * 2.0) as f64,
+            );
+
+            unsafe {
+                app.glyphs.cache(&self.font, font, &layout.glyphs);
+            }
+            self.glyphs = layout.glyphs;
+        } else {
+            let f = self.font.clone();
+            LilahNotFoundError!(Text, String, f);
+        }
+        self.glyph_generation = app.glyphs.generation();
+    }
+
+    /// Glyphs drawn, fewer than all of them during a typewriter reveal.
+    pub fn visible_glyphs(&self) -> usize {
+        self.revealed
+            .map_or(self.glyphs.len(), |r| (r.max(0.0) as usize).min(self.glyphs.len()))
+    }
+
+    /// Offsets of the outline copies around each glyph.
+    fn outline_offsets(&self) -> Vec<(f32, f32)> {
+        if self.outline_width <= 0.0 {
+            return vec![];
+        }
+
+        let w = self.outline_width as f32;
+        let d = w * std::f32::consts::FRAC_1_SQRT_2;
+        vec![
+            (w, 0.0),
+            (-w, 0.0),
+            (0.0, w),
+            (0.0, -w),
+            (d, d),
+            (-d, d),
+            (d, -d),
+            (-d, -d),
+        ]
+    }
+
+    /// Maps pixels in the text box, x right and y down from the top left, to world space.
+    /// The box is centered on the Transform.
+    fn model(&self, t: &Transform) -> Mat4 {
+        Mat4::from_scale_rotation_translation(
+            Vec3::new(t.scale.x as f32, t.scale.y as f32, 1.0),
+            Quat::from_rotation_z(t.rotation),
+            Vec3::new(
+                t.position.x as f32 + t.pivot.x as f32,
+                t.position.y as f32 + t.pivot.y as f32,
+                0.0,
+            ),
+        ) * Mat4::from_translation(Vec3::new(
+            -self.box_size.x as f32 / 2.0,
+            self.box_size.y as f32 / 2.0,
+            0.0,
+        )) * Mat4::from_scale(Vec3::new(1.0, -1.0, 1.0))
+    }
+
+    /// World space corners of a rect in the text box, counter clockwise from the bottom left.
+    fn corners(model: &Mat4, min: [f32; 2], max: [f32; 2]) -> [[f32; 2]; 4] {
+        [
+            [min[0], max[1]],
+            [max[0], max[1]],
+            [max[0], min[1]],
+            [min[0], min[1]],
+        ]
+        .map(|p| {
+            let p = model.transform_point3(Vec3::new(p[0], p[1], 0.0));
+            [p.x, p.y]
+        })
+    }
+
+    /// Min and max corners of the text box in world space, grown by anything drawn past it.
+    /// None until the text is laid out.
+    pub fn world_bounds(&self, t: &Transform) -> Option<(Vec2, Vec2)> {
+        if self.box_size.x <= 0.0 {
+            return None;
+        }
+
+        let reach = self.outline_width.max(0.0) as f32
+            + self.shadow_offset.x.abs().max(self.shadow_offset.y.abs()) as f32
+            + self.effects.reach();
+        let min = [-reach, -reach];
+        let max = [self.box_size.x as f32 + reach, self.box_size.y as f32 + reach];
+        Some(math::bounds_of(&Text::corners(&self.model(t), min, max)))
+    }
+
+    /// Draws the shadow, then the outline, then the glyphs, each as a pass over the visible glyphs.
+    pub fn draw(&self, app: &mut App, textures: &HashMap<String, LilahTexture>, t: &Transform) {
+        let visible = self.visible_glyphs();
+        if visible == 0 {
+            return;
+        }
+
+        let (program, uniforms) = renderer::select_material(
+            &app.materials,
+            &app.text_program,
+            self.material.as_ref(),
+            &self.uniforms,
+            textures,
+        );
+
+        let blend = renderer::select_blend(&app.materials, self.material.as_ref(), self.blend);
+        unsafe {
+            app.batch.set_blend(blend);
+        }
+
+        // offset in box pixels and the color replacing the glyph's, None keeps it
+        let mut passes: Vec<((f32, f32), Option<&Color>)> = vec![];
+        if self.shadow_offset != Vec2::ZERO {
+            let offset = (self.shadow_offset.x as f32, -self.shadow_offset.y as f32);
+            passes.push((offset, Some(&self.shadow_color)));
+        }
+        for offset in self.outline_offsets() {
+            passes.push((offset, Some(&self.outline_color)));
+        }
+        passes.push(((0.0, 0.0), None));
+
+        let model = self.model(t);
+        let time = app.time.time() as f32;
+        for (pass_offset, pass_color) in passes {
+            for (i, g) in self.glyphs.iter().take(visible).enumerate() {
+                let Some((texture, cached)) = app.glyphs.get(&self.font, g.size, g.c) else {
+                    continue;
+                };
+
+                let effect = self.effects.offset(i, time);
+                // pen positions snap to pixels so cached glyphs stay crisp
+                let min = [
+                    PADDING as f32 + g.x.round() + cached.offset.0 + pass_offset.0 + effect.0,
+                    PADDING as f32 + g.y.round() + cached.offset.1 + pass_offset.1 + effect.1,
+                ];
+                let max = [min[0] + cached.size.0, min[1] + cached.size.1];
+                let color = pass_color.unwrap_or(&g.color);
+                let tint = Color::new(
+                    self.color.r * color.r,
+                    self.color.g * color.g,
+                    self.color.b * color.b,
+                    self.color.a * color.a,
+                );
+
+                unsafe {
+                    app.batch.push_quad(
+                        program,
+                        texture,
+                        &uniforms,
+                        Text::corners(&model, min, max),
+                        cached.region.map_uvs(Text::DEF_VERTICES.map(|v| v.1)),
+                        &tint,
+                    );
+                }
+            }
+        }
+    }
+
+    //wren
+    fn wren_as_component(&self, vm: &VM) {
+        send_foreign!(vm, "game", "Component", Box::new(self.clone()) as Box<dyn Component> => 0);
+    }
+
+    fn wren_get_parent(&self, vm: &VM) {
+        vm.set_slot_string(0, self.parent.clone());
+    }
+
+    fn wren_get_text(&self, vm: &VM) {
+        vm.set_slot_string(0, self.text.clone());
+    }
+
+    fn wren_get_font(&self, vm: &VM) {
+        vm.set_slot_string(0, self.font.clone());
+    }
+
+    fn wren_get_font_size(&self, vm: &VM) {
+        vm.set_slot_double(0, self.font_size as f64);
+    }
+
+    fn wren_set_text(&mut self, vm: &VM) {
+        let a = vm.get_slot_string(1);
+        if let Some(a) = a {
+            self.set_text(&a);
+        } else {
+            LilahTypeError!(Text, 1, String);
+        }
+    }
+
+    fn wren_set_font(&mut self, vm: &VM) {
+        let a = vm.get_slot_string(1);
+        if let Some(a) = a {
+            self.set_font(&a);
+        } else {
+            LilahTypeError!(Text, 1, String);
         }
     }
 
@@ -1728,6 +3334,285 @@ impl Text {
             LilahTypeError!(Text, 1, GameObject);
         }
     }
+
+    fn wren_get_max_width(&self, vm: &VM) {
+        match self.max_width {
+            Some(w) => vm.set_slot_double(0, w),
+            None => vm.set_slot_null(0),
+        }
+    }
+
+    fn wren_get_min_height(&self, vm: &VM) {
+        match self.min_height {
+            Some(h) => vm.set_slot_double(0, h),
+            None => vm.set_slot_null(0),
+        }
+    }
+
+    fn wren_get_align(&self, vm: &VM) {
+        vm.set_slot_string(0, self.align.name());
+    }
+
+    fn wren_get_valign(&self, vm: &VM) {
+        vm.set_slot_string(0, self.valign.name());
+    }
+
+    fn wren_get_line_spacing(&self, vm: &VM) {
+        vm.set_slot_double(0, self.line_spacing);
+    }
+
+    fn wren_get_bounds(&self, vm: &VM) {
+        send_foreign!(vm, "math", "Vec2", self.bounds => 0);
+    }
+
+    fn wren_get_line_count(&self, vm: &VM) {
+        vm.set_slot_double(0, self.line_count as f64);
+    }
+
+    fn wren_get_outline_width(&self, vm: &VM) {
+        vm.set_slot_double(0, self.outline_width);
+    }
+
+    fn wren_get_outline_color(&self, vm: &VM) {
+        color_to_slot(vm, &self.outline_color, 0, 1);
+    }
+
+    fn wren_get_shadow_offset(&self, vm: &VM) {
+        send_foreign!(vm, "math", "Vec2", self.shadow_offset => 0);
+    }
+
+    fn wren_get_shadow_color(&self, vm: &VM) {
+        color_to_slot(vm, &self.shadow_color, 0, 1);
+    }
+
+    /// [amplitude, speed, spacing]
+    fn wren_get_wave(&self, vm: &VM) {
+        vm.set_slot_new_list(0);
+        let e = &self.effects;
+        for (i, v) in [e.wave_amplitude, e.wave_speed, e.wave_spacing].iter().enumerate() {
+            vm.set_slot_double(1, *v as f64);
+            vm.insert_in_list(0, i as i32, 1);
+        }
+    }
+
+    fn wren_get_shake(&self, vm: &VM) {
+        vm.set_slot_double(0, self.effects.shake as f64);
+    }
+
+    fn wren_get_revealed(&self, vm: &VM) {
+        match self.revealed {
+            Some(r) => vm.set_slot_double(0, r),
+            None => vm.set_slot_null(0),
+        }
+    }
+
+    fn wren_get_reveal_speed(&self, vm: &VM) {
+        vm.set_slot_double(0, self.reveal_speed);
+    }
+
+    fn wren_get_glyph_count(&self, vm: &VM) {
+        vm.set_slot_double(0, self.glyphs.len() as f64);
+    }
+
+    /// Character of the glyph at an index, null past the end.
+    fn wren_glyph(&self, vm: &VM) {
+        match vm
+            .get_slot_double(1)
+            .and_then(|i| self.glyphs.get(i.max(0.0) as usize))
+        {
+            Some(g) => vm.set_slot_string(0, g.c.to_string()),
+            None => vm.set_slot_null(0),
+        }
+    }
+
+    fn wren_set_outline_from_gameobject(vm: &VM) {
+        match vm.get_slot_foreign_mut::<GameObject>(1) {
+            Some(comp) => match (vm.get_slot_double(2), color_from_slot(vm, 3, 4)) {
+                (Some(width), Some(color)) => {
+                    let text = comp.get_mut::<Text>();
+                    text.outline_width = width;
+                    text.outline_color = color;
+                }
+                (None, _) => {
+                    LilahTypeError!(Text, 2, f64);
+                }
+                (_, None) => {
+                    LilahTypeError!(Text, 3, Color);
+                }
+            },
+            None => {
+                LilahTypeError!(Text, 1, GameObject);
+            }
+        }
+    }
+
+    fn wren_set_shadow_from_gameobject(vm: &VM) {
+        match vm.get_slot_foreign_mut::<GameObject>(1) {
+            Some(comp) => match (vm.get_slot_foreign::<Vec2>(2), color_from_slot(vm, 3, 4)) {
+                (Some(offset), Some(color)) => {
+                    let text = comp.get_mut::<Text>();
+                    text.shadow_offset = *offset;
+                    text.shadow_color = color;
+                }
+                (None, _) => {
+                    LilahTypeError!(Text, 2, Vec2);
+                }
+                (_, None) => {
+                    LilahTypeError!(Text, 3, Color);
+                }
+            },
+            None => {
+                LilahTypeError!(Text, 1, GameObject);
+            }
+        }
+    }
+
+    fn wren_set_wave_from_gameobject(vm: &VM) {
+        match vm.get_slot_foreign_mut::<GameObject>(1) {
+            Some(comp) => match (
+                vm.get_slot_double(2),
+                vm.get_slot_double(3),
+                vm.get_slot_double(4),
+            ) {
+                (Some(amplitude), Some(speed), Some(spacing)) => {
+                    let effects = &mut comp.get_mut::<Text>().effects;
+                    effects.wave_amplitude = amplitude as f32;
+                    effects.wave_speed = speed as f32;
+                    effects.wave_spacing = spacing as f32;
+                }
+                _ => {
+                    LilahTypeError!(Text, 2, f64);
+                }
+            },
+            None => {
+                LilahTypeError!(Text, 1, GameObject);
+            }
+        }
+    }
+
+    fn wren_set_shake_from_gameobject(vm: &VM) {
+        match vm.get_slot_foreign_mut::<GameObject>(1) {
+            Some(comp) => match vm.get_slot_double(2) {
+                Some(shake) => comp.get_mut::<Text>().effects.shake = shake as f32,
+                None => {
+                    LilahTypeError!(Text, 2, f64);
+                }
+            },
+            None => {
+                LilahTypeError!(Text, 1, GameObject);
+            }
+        }
+    }
+
+    /// Takes a glyph count, or null to show every glyph.
+    fn wren_set_revealed_from_gameobject(vm: &VM) {
+        match vm.get_slot_foreign_mut::<GameObject>(1) {
+            Some(comp) => match vm.get_slot_type(2) {
+                SlotType::Null => comp.get_mut::<Text>().revealed = None,
+                _ => match vm.get_slot_double(2) {
+                    Some(r) => comp.get_mut::<Text>().revealed = Some(r),
+                    None => {
+                        LilahTypeError!(Text, 2, f64);
+                    }
+                },
+            },
+            None => {
+                LilahTypeError!(Text, 1, GameObject);
+            }
+        }
+    }
+
+    fn wren_set_reveal_speed_from_gameobject(vm: &VM) {
+        match vm.get_slot_foreign_mut::<GameObject>(1) {
+            Some(comp) => match vm.get_slot_double(2) {
+                Some(speed) => comp.get_mut::<Text>().reveal_speed = speed,
+                None => {
+                    LilahTypeError!(Text, 2, f64);
+                }
+            },
+            None => {
+                LilahTypeError!(Text, 1, GameObject);
+            }
+        }
+    }
+
+    /// Takes a width in pixels, or null to stop wrapping.
+    fn wren_set_max_width_from_gameobject(vm: &VM) {
+        match vm.get_slot_foreign_mut::<GameObject>(1) {
+            Some(comp) => match vm.get_slot_type(2) {
+                SlotType::Null => comp.get_mut::<Text>().set_max_width(None),
+                _ => match vm.get_slot_double(2) {
+                    Some(w) => comp.get_mut::<Text>().set_max_width(Some(w)),
+                    None => {
+                        LilahTypeError!(Text, 2, f64);
+                    }
+                },
+            },
+            None => {
+                LilahTypeError!(Text, 1, GameObject);
+            }
+        }
+    }
+
+    /// Takes a height in pixels, or null to fit the lines.
+    fn wren_set_min_height_from_gameobject(vm: &VM) {
+        match vm.get_slot_foreign_mut::<GameObject>(1) {
+            Some(comp) => match vm.get_slot_type(2) {
+                SlotType::Null => comp.get_mut::<Text>().set_min_height(None),
+                _ => match vm.get_slot_double(2) {
+                    Some(h) => comp.get_mut::<Text>().set_min_height(Some(h)),
+                    None => {
+                        LilahTypeError!(Text, 2, f64);
+                    }
+                },
+            },
+            None => {
+                LilahTypeError!(Text, 1, GameObject);
+            }
+        }
+    }
+
+    fn wren_set_align_from_gameobject(vm: &VM) {
+        match vm.get_slot_foreign_mut::<GameObject>(1) {
+            Some(comp) => match vm.get_slot_string(2).and_then(|a| TextAlign::from_name(&a)) {
+                Some(align) => comp.get_mut::<Text>().set_align(align),
+                None => {
+                    LilahTypeError!(Text, 2, TextAlign);
+                }
+            },
+            None => {
+                LilahTypeError!(Text, 1, GameObject);
+            }
+        }
+    }
+
+    fn wren_set_valign_from_gameobject(vm: &VM) {
+        match vm.get_slot_foreign_mut::<GameObject>(1) {
+            Some(comp) => match vm.get_slot_string(2).and_then(|a| VerticalAlign::from_name(&a)) {
+                Some(valign) => comp.get_mut::<Text>().set_valign(valign),
+                None => {
+                    LilahTypeError!(Text, 2, VerticalAlign);
+                }
+            },
+            None => {
+                LilahTypeError!(Text, 1, GameObject);
+            }
+        }
+    }
+
+    fn wren_set_line_spacing_from_gameobject(vm: &VM) {
+        match vm.get_slot_foreign_mut::<GameObject>(1) {
+            Some(comp) => match vm.get_slot_double(2) {
+                Some(spacing) => comp.get_mut::<Text>().set_line_spacing(spacing),
+                None => {
+                    LilahTypeError!(Text, 2, f64);
+                }
+            },
+            None => {
+                LilahTypeError!(Text, 1, GameObject);
+            }
+        }
+    }
 }
 
 impl Sprite {
@@ -1739,12 +3624,6 @@ impl Sprite {
         Vertex([-0.5,  0.5],  [0.0, 0.0]),
     ];
 
-    #[rustfmt::skip]
-    const DEF_INDICES: [i32; 6] = [
-        0, 1, 2,
-        2, 3, 0
-    ];
-
     pub fn new(t_id: &str) -> Self {
         Self {
             parent: String::from(""),
@@ -1753,11 +3632,20 @@ impl Sprite {
             index_cut: (0, 0),
             index: (0, 0),
             texture_id: t_id.to_string(),
-            vertex_array: None,
-            vertex_buffer: None,
             tint: Color::WHITE,
             sort: 0,
             sort_dirty: true,
+            sort_layer: String::from("world"),
+            sort_pivot: 0.0,
+            uvs: Sprite::DEF_VERTICES.map(|v| v.1),
+            material: None,
+            uniforms: Uniforms::default(),
+            blend: None,
+            flip_x: false,
+            flip_y: false,
+            fill: SpriteFill::Simple,
+            parallax: Vec2::ONE,
+            repeat: (false, false),
         }
     }
 
@@ -1779,34 +3667,39 @@ impl Sprite {
         }
     }
 
-    pub fn load(&mut self, app: &mut App, textures: &HashMap<String, LilahTexture>) {
-        unsafe {
-            let vao = VertexArray::new();
-            vao.bind();
-
-            let vbo = Buffer::new(gl::ARRAY_BUFFER);
-            vbo.set_data(&Sprite::DEF_VERTICES, gl::DYNAMIC_DRAW);
-
-            let ibo = Buffer::new(gl::ELEMENT_ARRAY_BUFFER);
-            ibo.set_data(&Sprite::DEF_INDICES, gl::STATIC_DRAW);
-
-            let pos_attrib = app.default_program.get_attrib_location("position").unwrap();
-            set_attribute!(vao, pos_attrib, Vertex::0, gl::FLOAT);
-            let color_attrib = app
-                .default_program
-                .get_attrib_location("vertexTexCoord")
-                .unwrap();
-            set_attribute!(vao, color_attrib, Vertex::1, gl::FLOAT);
-
-            textures[&self.texture_id].bind();
-            app.default_program.set_int_uniform("texture0", 0).unwrap();
-
-            self.vertex_array = Some(vao);
-            self.vertex_buffer = Some(vbo);
+    /// Copies values from other, keeping the loaded texture size.
+    pub fn reset_from(&mut self, other: &Sprite) {
+        self.texture_id = other.texture_id.clone();
+        self.tint = other.tint.clone();
+        self.material = other.material.clone();
+        self.uniforms = other.uniforms.clone();
+        self.blend = other.blend;
+        self.flip_x = other.flip_x;
+        self.flip_y = other.flip_y;
+        self.fill = other.fill.clone();
+        self.parallax = other.parallax;
+        self.repeat = other.repeat;
+        self.sort_pivot = other.sort_pivot;
+        if self.sort_layer != other.sort_layer {
+            self.set_sort_layer(&other.sort_layer);
         }
+        self.size = other.size;
+        self.index_cut = other.index_cut;
+        if self.sort != other.sort {
+            self.set_sort(other.sort);
+        }
+
+        self.anim_sprite_sheet(self.index_cut.0, self.index_cut.1);
+    }
 
-        if let Some(t) = textures.get(&self.texture_id) {
-            self.base_size = (t.size.x as u32, t.size.y as u32);
+    pub fn load(
+        &mut self,
+        _app: &mut App,
+        textures: &HashMap<String, LilahTexture>,
+        atlas: Option<&Atlas>,
+    ) {
+        if let Some(size) = atlas::texture_size(atlas, textures, &self.texture_id) {
+            self.base_size = size;
         } else {
             let id = self.texture_id.clone();
             LilahNotFoundError!(Sprite, Texture, id);
@@ -1822,6 +3715,11 @@ impl Sprite {
         )
     }
 
+    /// Current position on the sprite sheet.
+    pub fn get_index(&self) -> (i32, i32) {
+        self.index
+    }
+
     pub fn cut_sprite_sheet(&mut self, ind: i32, ind2: i32, col: u32, row: u32) {
         self.size = (col, row);
         self.index_cut = (ind, ind2);
@@ -1865,65 +3763,218 @@ impl Sprite {
             precision_f32(zero.1 + ratio.1 - (1.0 / self.base_size.0 as f32) * 2.0, 2),
         );
 
-        let mut new_verts = Sprite::DEF_VERTICES;
-        new_verts[0].1 = [zero.0, one.1];
-        new_verts[1].1 = [one.0, one.1];
-        new_verts[2].1 = [one.0, zero.1];
-        new_verts[3].1 = [zero.0, zero.1];
+        self.uvs = [
+            [zero.0, one.1],
+            [one.0, one.1],
+            [one.0, zero.1],
+            [zero.0, zero.1],
+        ];
+    }
 
-        unsafe {
-            self.vertex_array.as_ref().unwrap().bind();
-            self.vertex_buffer
-                .as_mut()
-                .unwrap()
-                .set_data(&new_verts, gl::DYNAMIC_DRAW);
+    pub fn draw(
+        &self,
+        app: &mut App,
+        textures: &HashMap<String, LilahTexture>,
+        atlas: Option<&Atlas>,
+        t: &Transform,
+    ) {
+        self.draw_with(
+            app,
+            textures,
+            atlas,
+            t,
+            self.material.as_ref(),
+            &self.uniforms,
+        );
+    }
+
+    /// Size of the drawn area before the Transform scale. The cell size unless filling.
+    pub fn extent(&self) -> Vec2 {
+        match &self.fill {
+            SpriteFill::Simple => Vec2::new(self.get_size().0 as f64, self.get_size().1 as f64),
+            SpriteFill::Sliced { size, .. } | SpriteFill::Tiled { size } => *size,
         }
     }
 
-    pub fn draw(&self, app: &mut App, textures: &HashMap<String, LilahTexture>, t: &Transform) {
-        let model = Mat4::IDENTITY
-            * Mat4::from_scale_rotation_translation(
-                Vec3::new(self.get_size().0 as f32, self.get_size().1 as f32, 0.0)
-                    * Vec3::new(t.scale.x as f32, t.scale.y as f32, 0.0),
-                Quat::from_rotation_z(t.rotation),
-                Vec3::new(
-                    t.position.x as f32 + t.pivot.x as f32,
-                    t.position.y as f32 + t.pivot.y as f32,
-                    0.0,
-                ),
-            );
+    fn model(t: &Transform) -> Mat4 {
+        Mat4::from_scale_rotation_translation(
+            Vec3::new(t.scale.x as f32, t.scale.y as f32, 1.0),
+            Quat::from_rotation_z(t.rotation),
+            Vec3::new(
+                t.position.x as f32 + t.pivot.x as f32,
+                t.position.y as f32 + t.pivot.y as f32,
+                0.0,
+            ),
+        )
+    }
+
+    /// World space corners of the drawn area, counter clockwise from the bottom left.
+    fn corners(&self, t: &Transform) -> [[f32; 2]; 4] {
+        let model = Sprite::model(t);
+        let extent = self.extent();
+
+        Sprite::DEF_VERTICES.map(|v| {
+            let pos = v.0;
+            let p = model.transform_point3(Vec3::new(
+                pos[0] * extent.x as f32,
+                pos[1] * extent.y as f32,
+                0.0,
+            ));
+            [p.x, p.y]
+        })
+    }
+
+    /// Min and max corners of the drawn area in world space.
+    pub fn world_bounds(&self, t: &Transform) -> (Vec2, Vec2) {
+        math::bounds_of(&self.corners(t))
+    }
 
-        let view = unsafe { *crate::math::VIEW_MATRIX };
-        let projection = unsafe { *crate::math::PROJECTION_MATRIX };
+    /// Whether the sprite is drawn away from its Transform by parallax or repetition.
+    pub fn has_parallax(&self) -> bool {
+        self.parallax != Vec2::ONE || self.repeat != (false, false)
+    }
 
-        let mvp = projection * view * model;
+    /// Quads making up the drawn area as local min and max, and the matching min and max
+    /// fractions of the cell from its bottom left.
+    fn pieces(&self) -> Vec<([f32; 2], [f32; 2], [f32; 2], [f32; 2])> {
+        let extent = self.extent();
+        let (w, h) = (extent.x as f32, extent.y as f32);
+        let (cw, ch) = (self.get_size().0 as f32, self.get_size().1 as f32);
 
-        unsafe {
-            textures[&self.texture_id].activate(gl::TEXTURE0);
+        match &self.fill {
+            SpriteFill::Simple => vec![([-w / 2.0, -h / 2.0], [w / 2.0, h / 2.0], [0.0, 0.0], [1.0, 1.0])],
+            SpriteFill::Sliced { border, .. } => {
+                let [mut left, mut right, top, bottom] = border.map(|b| b as f32);
+                // a flipped cell shows its right border on the left
+                if self.flip_x {
+                    std::mem::swap(&mut left, &mut right);
+                }
+                let (bottom, top) = if self.flip_y { (top, bottom) } else { (bottom, top) };
+
+                // borders shrink together when the size is smaller than both
+                let sx = (w / (left + right)).min(1.0);
+                let sy = (h / (bottom + top)).min(1.0);
+                let xs = [-w / 2.0, -w / 2.0 + left * sx, w / 2.0 - right * sx, w / 2.0];
+                let ys = [-h / 2.0, -h / 2.0 + bottom * sy, h / 2.0 - top * sy, h / 2.0];
+                let fxs = [0.0, left / cw, 1.0 - right / cw, 1.0];
+                let fys = [0.0, bottom / ch, 1.0 - top / ch, 1.0];
+
+                let mut pieces = vec![];
+                for j in 0..3 {
+                    for i in 0..3 {
+                        if xs[i + 1] > xs[i] && ys[j + 1] > ys[j] {
+                            pieces.push((
+                                [xs[i], ys[j]],
+                                [xs[i + 1], ys[j + 1]],
+                                [fxs[i], fys[j]],
+                                [fxs[i + 1], fys[j + 1]],
+                            ));
+                        }
+                    }
+                }
+                pieces
+            }
+            SpriteFill::Tiled { .. } => {
+                if cw <= 0.0 || ch <= 0.0 {
+                    return vec![];
+                }
 
-            app.default_program.apply();
+                let mut pieces = vec![];
+                let mut y = -h / 2.0;
+                while y < h / 2.0 {
+                    let y1 = (y + ch).min(h / 2.0);
+                    let mut x = -w / 2.0;
+                    while x < w / 2.0 {
+                        let x1 = (x + cw).min(w / 2.0);
+                        pieces.push(([x, y], [x1, y1], [0.0, 0.0], [(x1 - x) / cw, (y1 - y) / ch]));
+                        x += cw;
+                    }
+                    y += ch;
+                }
+                pieces
+            }
+        }
+    }
 
-            self.vertex_array.as_ref().unwrap().bind();
+    /// Texture coordinate at a fraction of the cell from its bottom left, after flipping.
+    fn uv_at(&self, uvs: &[TextureCoords; 4], fx: f32, fy: f32) -> TextureCoords {
+        let fx = if self.flip_x { 1.0 - fx } else { fx };
+        let fy = if self.flip_y { 1.0 - fy } else { fy };
+        [
+            uvs[0][0] + (uvs[1][0] - uvs[0][0]) * fx,
+            uvs[0][1] + (uvs[3][1] - uvs[0][1]) * fy,
+        ]
+    }
 
-            let mat_attr = gl::GetUniformLocation(
-                app.default_program.id,
-                CString::new("mvp").unwrap().as_ptr(),
-            );
-            gl::UniformMatrix4fv(mat_attr, 1, gl::FALSE as GLboolean, &mvp.to_cols_array()[0]);
+    /// Texture and the world space corners and uvs of every quad making up the drawn area at t.
+    /// None while the texture is not loaded.
+    pub fn quads<'t>(
+        &self,
+        textures: &'t HashMap<String, LilahTexture>,
+        atlas: Option<&'t Atlas>,
+        t: &Transform,
+    ) -> Option<(&'t LilahTexture, Vec<([Pos; 4], [TextureCoords; 4])>)> {
+        let (texture, uvs) = atlas::resolve_texture(atlas, textures, &self.texture_id, self.uvs)?;
+        let model = Sprite::model(t);
+
+        let quads = self
+            .pieces()
+            .into_iter()
+            .map(|(min, max, fmin, fmax)| {
+                let corners = [
+                    [min[0], min[1]],
+                    [max[0], min[1]],
+                    [max[0], max[1]],
+                    [min[0], max[1]],
+                ]
+                .map(|[x, y]| {
+                    let p = model.transform_point3(Vec3::new(x, y, 0.0));
+                    [p.x, p.y]
+                });
+                let piece_uvs = [
+                    self.uv_at(&uvs, fmin[0], fmin[1]),
+                    self.uv_at(&uvs, fmax[0], fmin[1]),
+                    self.uv_at(&uvs, fmax[0], fmax[1]),
+                    self.uv_at(&uvs, fmin[0], fmax[1]),
+                ];
+                (corners, piece_uvs)
+            })
+            .collect();
+
+        Some((texture, quads))
+    }
+
+    /// Draws with the given material instead of the sprite's own, used for tile layer materials.
+    pub fn draw_with(
+        &self,
+        app: &mut App,
+        textures: &HashMap<String, LilahTexture>,
+        atlas: Option<&Atlas>,
+        t: &Transform,
+        material: Option<&String>,
+        uniforms: &Uniforms,
+    ) {
+        let Some((texture, quads)) = self.quads(textures, atlas, t) else {
+            return;
+        };
 
-            let tint_attr = gl::GetUniformLocation(
-                app.default_program.id,
-                CString::new("tint").unwrap().as_ptr(),
-            );
-            gl::Uniform4f(
-                tint_attr,
-                self.tint.r,
-                self.tint.g,
-                self.tint.b,
-                self.tint.a,
-            );
+        let (program, uniforms) = renderer::select_material(
+            &app.materials,
+            &app.default_program,
+            material,
+            uniforms,
+            textures,
+        );
+        let blend = renderer::select_blend(&app.materials, material, self.blend);
+        unsafe {
+            app.batch.set_blend(blend);
+        }
 
-            gl::DrawElements(gl::TRIANGLES, 6, gl::UNSIGNED_INT, 0 as *const _);
+        for (corners, uvs) in quads {
+            unsafe {
+                app.batch
+                    .push_quad(program, texture, &uniforms, corners, uvs, &self.tint);
+            }
         }
     }
 
@@ -2034,6 +4085,145 @@ impl Sprite {
             LilahTypeError!(Sprite, 1, GameObject);
         }
     }
+
+    fn wren_get_flip_x(&self, vm: &VM) {
+        vm.set_slot_bool(0, self.flip_x);
+    }
+
+    fn wren_get_flip_y(&self, vm: &VM) {
+        vm.set_slot_bool(0, self.flip_y);
+    }
+
+    fn wren_get_fill(&self, vm: &VM) {
+        let fill = match self.fill {
+            SpriteFill::Simple => "simple",
+            SpriteFill::Sliced { .. } => "sliced",
+            SpriteFill::Tiled { .. } => "tiled",
+        };
+        vm.set_slot_string(0, fill);
+    }
+
+    fn wren_get_parallax(&self, vm: &VM) {
+        send_foreign!(vm, "math", "Vec2", self.parallax => 0);
+    }
+
+    fn wren_get_repeat(&self, vm: &VM) {
+        vm.set_slot_new_list(0);
+        for (i, r) in [self.repeat.0, self.repeat.1].iter().enumerate() {
+            vm.set_slot_bool(1, *r);
+            vm.insert_in_list(0, i as i32, 1);
+        }
+    }
+
+    fn wren_get_extent(&self, vm: &VM) {
+        send_foreign!(vm, "math", "Vec2", self.extent() => 0);
+    }
+
+    fn wren_get_border(&self, vm: &VM) {
+        match self.fill {
+            SpriteFill::Sliced { border, .. } => {
+                vm.set_slot_new_list(0);
+                for (i, b) in border.iter().enumerate() {
+                    vm.set_slot_double(1, *b);
+                    vm.insert_in_list(0, i as i32, 1);
+                }
+            }
+            _ => vm.set_slot_null(0),
+        }
+    }
+
+    fn wren_set_flip_x_from_gameobject(vm: &VM) {
+        if let Some(comp) = vm.get_slot_foreign_mut::<GameObject>(1) {
+            if let Some(flip) = vm.get_slot_bool(2) {
+                comp.get_mut::<Sprite>().flip_x = flip;
+            } else {
+                LilahTypeError!(Sprite, 2, bool);
+            }
+        } else {
+            LilahTypeError!(Sprite, 1, GameObject);
+        }
+    }
+
+    fn wren_set_flip_y_from_gameobject(vm: &VM) {
+        if let Some(comp) = vm.get_slot_foreign_mut::<GameObject>(1) {
+            if let Some(flip) = vm.get_slot_bool(2) {
+                comp.get_mut::<Sprite>().flip_y = flip;
+            } else {
+                LilahTypeError!(Sprite, 2, bool);
+            }
+        } else {
+            LilahTypeError!(Sprite, 1, GameObject);
+        }
+    }
+
+    fn wren_set_parallax_from_gameobject(vm: &VM) {
+        if let Some(comp) = vm.get_slot_foreign_mut::<GameObject>(1) {
+            if let Some(factor) = vm.get_slot_foreign::<Vec2>(2).copied() {
+                comp.get_mut::<Sprite>().parallax = factor;
+            } else {
+                LilahTypeError!(Sprite, 2, Vec2);
+            }
+        } else {
+            LilahTypeError!(Sprite, 1, GameObject);
+        }
+    }
+
+    fn wren_set_repeat_from_gameobject(vm: &VM) {
+        if let Some(comp) = vm.get_slot_foreign_mut::<GameObject>(1) {
+            match (vm.get_slot_bool(2), vm.get_slot_bool(3)) {
+                (Some(x), Some(y)) => comp.get_mut::<Sprite>().repeat = (x, y),
+                (None, _) => {
+                    LilahTypeError!(Sprite, 2, bool);
+                }
+                (_, None) => {
+                    LilahTypeError!(Sprite, 3, bool);
+                }
+            }
+        } else {
+            LilahTypeError!(Sprite, 1, GameObject);
+        }
+    }
+
+    fn wren_set_simple_from_gameobject(vm: &VM) {
+        if let Some(comp) = vm.get_slot_foreign_mut::<GameObject>(1) {
+            comp.get_mut::<Sprite>().fill = SpriteFill::Simple;
+        } else {
+            LilahTypeError!(Sprite, 1, GameObject);
+        }
+    }
+
+    fn wren_set_sliced_from_gameobject(vm: &VM) {
+        if let Some(comp) = vm.get_slot_foreign_mut::<GameObject>(1) {
+            if let Some(size) = vm.get_slot_foreign::<Vec2>(2).copied() {
+                if matches!(vm.get_slot_type(3), SlotType::List) {
+                    let mut border = [0f64; 4];
+                    for (i, b) in border.iter_mut().enumerate() {
+                        vm.get_list_element(3, i as i32, 4);
+                        *b = vm.get_slot_double(4).unwrap_or(0f64);
+                    }
+                    comp.get_mut::<Sprite>().fill = SpriteFill::Sliced { size, border };
+                } else {
+                    LilahTypeError!(Sprite, 3, List);
+                }
+            } else {
+                LilahTypeError!(Sprite, 2, Vec2);
+            }
+        } else {
+            LilahTypeError!(Sprite, 1, GameObject);
+        }
+    }
+
+    fn wren_set_tiled_from_gameobject(vm: &VM) {
+        if let Some(comp) = vm.get_slot_foreign_mut::<GameObject>(1) {
+            if let Some(size) = vm.get_slot_foreign::<Vec2>(2).copied() {
+                comp.get_mut::<Sprite>().fill = SpriteFill::Tiled { size };
+            } else {
+                LilahTypeError!(Sprite, 2, Vec2);
+            }
+        } else {
+            LilahTypeError!(Sprite, 1, GameObject);
+        }
+    }
 }
 
 impl Line {
@@ -2049,6 +4239,9 @@ impl Line {
             vertex_buffer: unsafe { Buffer::new(gl::ARRAY_BUFFER) },
             sort: 0,
             sort_dirty: true,
+            material: None,
+            uniforms: Uniforms::default(),
+            blend: None,
         };
         //res.generate_mesh();
         res
@@ -2063,6 +4256,18 @@ impl Line {
         self.sort_dirty = true;
     }
 
+    /// Copies values from other, keeping the loaded buffers. The mesh is rebuilt on the next check_dirty.
+    pub fn reset_from(&mut self, other: &Line) {
+        self.points = other.points.clone();
+        self.thickness = other.thickness;
+        self.opacity = other.opacity;
+        self.color = other.color.clone();
+        self.material = other.material.clone();
+        self.uniforms = other.uniforms.clone();
+        self.blend = other.blend;
+        self.set_sort(other.sort);
+    }
+
     pub fn check_dirty(&mut self) -> bool {
         if self.sort_dirty {
             self.generate_mesh();
@@ -2160,7 +4365,19 @@ impl Line {
         self.vertex_count = line_mesh.len() as u32 * 3;
     }
 
-    pub fn draw(&self, t: &Transform) {
+    /// Min and max corners around the points padded by the thickest end. Points are in world space.
+    pub fn world_bounds(&self) -> Option<(Vec2, Vec2)> {
+        if self.points.is_empty() {
+            return None;
+        }
+
+        let points: Vec<[f32; 2]> = self.points.iter().map(|p| [p.x as f32, p.y as f32]).collect();
+        let (min, max) = math::bounds_of(&points);
+        let pad = self.thickness[0].max(self.thickness[1]) / 2.0;
+        Some((min - Vec2::new(pad, pad), max + Vec2::new(pad, pad)))
+    }
+
+    pub fn draw(&self, app: &App, textures: &HashMap<String, LilahTexture>, t: &Transform) {
         let model = Mat4::IDENTITY;
         // * Mat4::from_scale_rotation_translation(
         //     Vec3::new(t.scale.x as f32, t.scale.y as f32, 0.0),
@@ -2177,27 +4394,25 @@ impl Line {
         let mvp = projection * view * model;
 
         unsafe {
-            crate::application::DEBUG_PROGRAM
-                .as_mut()
-                .expect("program")
-                .apply();
+            let (program, uniforms) = renderer::select_material(
+                &app.materials,
+                crate::application::DEBUG_PROGRAM.as_ref().expect("program"),
+                self.material.as_ref(),
+                &self.uniforms,
+                textures,
+            );
+            program.apply();
 
             self.vertex_array.bind();
 
             let mat_attr = gl::GetUniformLocation(
-                crate::application::DEBUG_PROGRAM
-                    .as_ref()
-                    .expect("program")
-                    .id,
+                program.id,
                 CString::new("mvp").unwrap().as_ptr(),
             );
             gl::UniformMatrix4fv(mat_attr, 1, gl::FALSE as GLboolean, &mvp.to_cols_array()[0]);
 
             let tint_attr = gl::GetUniformLocation(
-                crate::application::DEBUG_PROGRAM
-                    .as_ref()
-                    .expect("program")
-                    .id,
+                program.id,
                 CString::new("tint").unwrap().as_ptr(),
             );
             gl::Uniform4f(
@@ -2207,8 +4422,14 @@ impl Line {
                 self.color.b,
                 self.color.a,
             );
+            renderer::apply_uniforms(program.id, &uniforms);
 
+            let blend = renderer::select_blend(&app.materials, self.material.as_ref(), self.blend);
+            blend.apply();
             gl::DrawArrays(gl::TRIANGLES, 0, self.vertex_count as i32);
+            if blend != BlendMode::Alpha {
+                BlendMode::Alpha.apply();
+            }
         }
     }
 
@@ -2419,96 +4640,6 @@ impl Line {
     }
 }
 
-impl Debug {
-    pub fn draw_line(start: Vec2, end: Vec2, tint: Color) {
-        let model = Mat4::IDENTITY;
-        let view = unsafe { *crate::math::VIEW_MATRIX };
-        let projection = unsafe { *crate::math::PROJECTION_MATRIX };
-
-        let mvp = projection * view * model;
-
-        unsafe {
-            crate::application::DEBUG_PROGRAM
-                .as_mut()
-                .expect("program")
-                .apply();
-
-            let vao = VertexArray::new();
-            vao.bind();
-
-            let vbo = Buffer::new(gl::ARRAY_BUFFER);
-            vbo.set_data(
-                &[
-                    Vertex([start.x as f32, start.y as f32], [0 as f32, 0 as f32]),
-                    Vertex([end.x as f32, end.y as f32], [1 as f32, 0 as f32]),
-                ],
-                gl::DYNAMIC_DRAW,
-            );
-
-            let ibo = Buffer::new(gl::ELEMENT_ARRAY_BUFFER);
-            ibo.set_data(&[0, 1], gl::STATIC_DRAW);
-
-            let pos_attrib = crate::application::DEBUG_PROGRAM
-                .as_ref()
-                .expect("program")
-                .get_attrib_location("position")
-                .expect("msg");
-            set_attribute!(vao, pos_attrib, Vertex::0, gl::FLOAT);
-
-            let o_attrib = crate::application::DEBUG_PROGRAM
-                .as_ref()
-                .expect("program")
-                .get_attrib_location("opacity")
-                .expect("msg");
-            set_attribute!(vao, o_attrib, Vertex::1, gl::FLOAT);
-
-            let mat_attr = gl::GetUniformLocation(
-                crate::application::DEBUG_PROGRAM
-                    .as_ref()
-                    .expect("program")
-                    .id,
-                CString::new("mvp").unwrap().as_ptr(),
-            );
-            gl::UniformMatrix4fv(mat_attr, 1, gl::FALSE as GLboolean, &mvp.to_cols_array()[0]);
-
-            let tint_attr = gl::GetUniformLocation(
-                crate::application::DEBUG_PROGRAM
-                    .as_ref()
-                    .expect("program")
-                    .id,
-                CString::new("tint").unwrap().as_ptr(),
-            );
-            gl::Uniform4f(tint_attr, tint.r, tint.g, tint.b, tint.a);
-            gl::DrawElements(gl::LINES, 2, gl::UNSIGNED_INT, 0 as *const _);
-        }
-    }
-
-    pub fn wren_draw_line(vm: &VM) {
-        if let Some(start) = vm.get_slot_foreign::<Vec2>(1) {
-            if let Some(end) = vm.get_slot_foreign::<Vec2>(2) {
-                let mut color = Color::new(1.0, 1.0, 1.0, 1.0);
-                vm.get_list_element(3, 0, 4);
-                color.r = vm.get_slot_double(4).unwrap_or(0f64) as f32;
-                vm.get_list_element(3, 1, 4);
-                color.g = vm.get_slot_double(4).unwrap_or(0f64) as f32;
-                vm.get_list_element(3, 2, 4);
-                color.b = vm.get_slot_double(4).unwrap_or(0f64) as f32;
-                vm.get_list_element(3, 3, 4);
-                color.a = vm.get_slot_double(4).unwrap_or(0f64) as f32;
-
-                //Self::draw_line(*start, *end, color);
-                unsafe {
-                    crate::application::LINES.push((*start, *end, color));
-                }
-            } else {
-                LilahTypeError!(Debug, 2, Vec2);
-            }
-        } else {
-            LilahTypeError!(Debug, 1, Vec2);
-        }
-    }
-}
-
 impl ComponentBehaviour {
     pub fn new(s: String) -> Self {
         Self {
@@ -2540,41 +4671,95 @@ impl PartialEq for Sprite {
     fn eq(&self, other: &Self) -> bool {
         self.get_size() == other.get_size() && self.index == other.index
     }
-}
+}
+
+impl Default for Sprite {
+    fn default() -> Self {
+        Sprite::new("")
+    }
+}
+
+impl Default for Rigidbody {
+    fn default() -> Self {
+        Self {
+            parent: String::from(""),
+            bounds: Vec2::ONE,
+            pivot: Vec2::ZERO,
+            scale: Vec2::ONE,
+            rotation: 0.0,
+            velocity: Vec2::ZERO,
+            position: Vec2::ONE,
+            colliding: None,
+            solid: true,
+            occluder: false,
+        }
+    }
+}
+
+//trait impl
+impl Component for Transform {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn send_to_wren(&self, slot: usize, vm: &VM) {
+        send_foreign!(vm, "game", "Transform", self.clone() => slot);
+    }
+
+    fn clone_dyn(&self) -> Box<dyn Component> {
+        Box::new(self.clone())
+    }
+}
+
+impl Component for Scene {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn send_to_wren(&self, slot: usize, vm: &VM) {
+        send_foreign!(vm, "game", "Scene", self.clone() => slot);
+    }
+
+    fn clone_dyn(&self) -> Box<dyn Component> {
+        Box::new(self.clone())
+    }
+}
+
+impl Component for Sprite {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
 
-impl Default for Sprite {
-    fn default() -> Self {
-        Sprite::new("")
+    fn send_to_wren(&self, slot: usize, vm: &VM) {
+        send_foreign!(vm, "game", "Sprite", self.clone() => slot);
     }
-}
 
-impl Default for Rigidbody {
-    fn default() -> Self {
-        Self {
-            parent: String::from(""),
-            bounds: Vec2::ONE,
-            pivot: Vec2::ZERO,
-            scale: Vec2::ONE,
-            rotation: 0.0,
-            velocity: Vec2::ZERO,
-            position: Vec2::ONE,
-            colliding: None,
-            solid: true,
-        }
+    fn clone_dyn(&self) -> Box<dyn Component> {
+        Box::new(self.clone())
     }
 }
 
-//trait impl
-impl Component for Transform {
+impl Component for Rigidbody {
     fn as_any(&self) -> &dyn Any {
         self
     }
+
     fn as_any_mut(&mut self) -> &mut dyn Any {
         self
     }
 
     fn send_to_wren(&self, slot: usize, vm: &VM) {
-        send_foreign!(vm, "game", "Transform", self.clone() => slot);
+        send_foreign!(vm, "game", "Rigidbody", self.clone() => slot);
     }
 
     fn clone_dyn(&self) -> Box<dyn Component> {
@@ -2582,7 +4767,7 @@ impl Component for Transform {
     }
 }
 
-impl Component for Scene {
+impl Component for Animator {
     fn as_any(&self) -> &dyn Any {
         self
     }
@@ -2592,7 +4777,7 @@ impl Component for Scene {
     }
 
     fn send_to_wren(&self, slot: usize, vm: &VM) {
-        send_foreign!(vm, "game", "Scene", self.clone() => slot);
+        send_foreign!(vm, "game", "Animator", self.clone() => slot);
     }
 
     fn clone_dyn(&self) -> Box<dyn Component> {
@@ -2600,16 +4785,17 @@ impl Component for Scene {
     }
 }
 
-impl Component for Sprite {
+impl Component for Shape {
     fn as_any(&self) -> &dyn Any {
         self
     }
+
     fn as_any_mut(&mut self) -> &mut dyn Any {
         self
     }
 
     fn send_to_wren(&self, slot: usize, vm: &VM) {
-        send_foreign!(vm, "game", "Sprite", self.clone() => slot);
+        send_foreign!(vm, "game", "Shape", self.clone() => slot);
     }
 
     fn clone_dyn(&self) -> Box<dyn Component> {
@@ -2617,7 +4803,7 @@ impl Component for Sprite {
     }
 }
 
-impl Component for Rigidbody {
+impl Component for Light2D {
     fn as_any(&self) -> &dyn Any {
         self
     }
@@ -2627,7 +4813,7 @@ impl Component for Rigidbody {
     }
 
     fn send_to_wren(&self, slot: usize, vm: &VM) {
-        send_foreign!(vm, "game", "Rigidbody", self.clone() => slot);
+        send_foreign!(vm, "game", "Light2D", self.clone() => slot);
     }
 
     fn clone_dyn(&self) -> Box<dyn Component> {
@@ -2635,7 +4821,7 @@ impl Component for Rigidbody {
     }
 }
 
-impl Component for Animator {
+impl Component for Camera {
     fn as_any(&self) -> &dyn Any {
         self
     }
@@ -2645,7 +4831,7 @@ impl Component for Animator {
     }
 
     fn send_to_wren(&self, slot: usize, vm: &VM) {
-        send_foreign!(vm, "game", "Animator", self.clone() => slot);
+        send_foreign!(vm, "game", "Camera", self.clone() => slot);
     }
 
     fn clone_dyn(&self) -> Box<dyn Component> {
@@ -2725,6 +4911,429 @@ impl Component for Line {
     }
 }
 
+/// Generates wren_pull, which refreshes a wren component handle from the owning GameObject in slot 1.
+macro_rules! impl_wren_pull {
+    ($($t:ty),*) => {
+        $(
+            impl $t {
+                fn wren_pull(&mut self, vm: &VM) {
+                    match vm.get_slot_foreign::<GameObject>(1) {
+                        Some(go) => match go.wrap_component::<$t>() {
+                            Some(c) => *self = c.clone(),
+                            None => {
+                                let owner = go.id.uuid.clone();
+                                LilahNotFoundError!(GameObject, $t, owner);
+                            }
+                        },
+                        None => {
+                            LilahTypeError!($t, 1, GameObject);
+                        }
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_wren_pull!(Transform, Rigidbody, Sprite, Animator, Text, Line, Scene, Camera, Shape, Light2D);
+
+/// Sort layer accessors for components drawn in sort order.
+macro_rules! impl_sort_layer {
+    ($($t:ty),*) => {
+        $(
+            impl $t {
+                /// Moves to the named App::sort_layers entry.
+                pub fn set_sort_layer(&mut self, layer: &str) {
+                    self.sort_layer = layer.to_string();
+                    self.sort_dirty = true;
+                }
+
+                /// World y the component sorts by in y-sorted layers.
+                pub fn sort_y(&self, t: &Transform) -> f64 {
+                    t.position.y + self.sort_pivot
+                }
+
+                fn wren_get_sort_layer(&self, vm: &VM) {
+                    vm.set_slot_string(0, self.sort_layer.clone());
+                }
+
+                fn wren_get_sort_pivot(&self, vm: &VM) {
+                    vm.set_slot_double(0, self.sort_pivot);
+                }
+
+                fn wren_set_sort_layer_from_gameobject(vm: &VM) {
+                    match vm.get_slot_foreign_mut::<GameObject>(1) {
+                        Some(comp) => match vm.get_slot_string(2) {
+                            Some(layer) => comp.get_mut::<$t>().set_sort_layer(&layer),
+                            None => {
+                                LilahTypeError!($t, 2, String);
+                            }
+                        },
+                        None => {
+                            LilahTypeError!($t, 1, GameObject);
+                        }
+                    }
+                }
+
+                fn wren_set_sort_pivot_from_gameobject(vm: &VM) {
+                    match vm.get_slot_foreign_mut::<GameObject>(1) {
+                        Some(comp) => match vm.get_slot_double(2) {
+                            Some(pivot) => comp.get_mut::<$t>().sort_pivot = pivot,
+                            None => {
+                                LilahTypeError!($t, 2, f64);
+                            }
+                        },
+                        None => {
+                            LilahTypeError!($t, 1, GameObject);
+                        }
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_sort_layer!(Sprite, Text, Shape);
+
+/// Blend mode accessors for components that draw with a material.
+macro_rules! impl_blend {
+    ($($t:ty),*) => {
+        $(
+            impl $t {
+                /// null when the material's blend is used.
+                fn wren_get_blend(&self, vm: &VM) {
+                    match self.blend {
+                        Some(blend) => vm.set_slot_string(0, blend.name()),
+                        None => vm.set_slot_null(0),
+                    }
+                }
+
+                /// Takes a blend mode name, or null to use the material's.
+                fn wren_set_blend_from_gameobject(vm: &VM) {
+                    match vm.get_slot_foreign_mut::<GameObject>(1) {
+                        Some(comp) => {
+                            if matches!(vm.get_slot_type(2), SlotType::Null) {
+                                comp.get_mut::<$t>().blend = None;
+                            } else {
+                                match vm.get_slot_string(2).and_then(|n| BlendMode::from_name(&n)) {
+                                    Some(blend) => comp.get_mut::<$t>().blend = Some(blend),
+                                    None => {
+                                        LilahTypeError!($t, 2, BlendMode);
+                                    }
+                                }
+                            }
+                        }
+                        None => {
+                            LilahTypeError!($t, 1, GameObject);
+                        }
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_blend!(Sprite, Text, Line);
+
+/// Reads a uniform value from wren. Num -> Float, Vec2 -> Vec2, [r, g, b, a] -> Vec4, String -> Texture.
+pub(crate) fn uniform_from_slot(vm: &VM, slot: usize, scratch: usize) -> Option<Uniform> {
+    match vm.get_slot_type(slot) {
+        SlotType::Num => vm.get_slot_double(slot).map(|v| Uniform::Float(v as f32)),
+        SlotType::String => vm.get_slot_string(slot).map(Uniform::Texture),
+        SlotType::Foreign => vm
+            .get_slot_foreign::<Vec2>(slot)
+            .map(|v| Uniform::Vec2([v.x as f32, v.y as f32])),
+        SlotType::List => {
+            let count = vm.get_list_count(slot)?;
+            if count != 4 {
+                LilahError!(Uniform, format!("Expected a list of 4 numbers, got {}", count));
+                return None;
+            }
+
+            let mut v = [0.0f32; 4];
+            for (i, c) in v.iter_mut().enumerate() {
+                vm.get_list_element(slot, i as i32, scratch);
+                *c = vm.get_slot_double(scratch)? as f32;
+            }
+            Some(Uniform::Vec4(v))
+        }
+        _ => None,
+    }
+}
+
+macro_rules! impl_wren_material {
+    ($($t:ty),*) => {
+        $(
+            impl $t {
+                fn wren_get_material(&self, vm: &VM) {
+                    match &self.material {
+                        Some(m) => vm.set_slot_string(0, m.clone()),
+                        None => vm.set_slot_null(0),
+                    }
+                }
+
+                fn wren_set_material_from_gameobject(vm: &VM) {
+                    if let Some(go) = vm.get_slot_foreign_mut::<GameObject>(1) {
+                        if let Some(c) = go.wrap_component_mut::<$t>() {
+                            c.material = vm.get_slot_string(2);
+                        }
+                    } else {
+                        LilahTypeError!($t, 1, GameObject);
+                    }
+                }
+
+                fn wren_set_uniform_from_gameobject(vm: &VM) {
+                    if let Some(go) = vm.get_slot_foreign_mut::<GameObject>(1) {
+                        match (vm.get_slot_string(2), uniform_from_slot(vm, 3, 4)) {
+                            (Some(name), Some(value)) => {
+                                if let Some(c) = go.wrap_component_mut::<$t>() {
+                                    c.uniforms.set(&name, value);
+                                }
+                            }
+                            (None, _) => {
+                                LilahTypeError!($t, 2, String);
+                            }
+                            (_, None) => {
+                                LilahTypeError!($t, 3, Uniform);
+                            }
+                        }
+                    } else {
+                        LilahTypeError!($t, 1, GameObject);
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_wren_material!(Sprite, Text, Line);
+
+impl Scene {
+    /// Null clears the ambient back to App::lights.ambient.
+    fn wren_set_ambient_from_gameobject(vm: &VM) {
+        match vm.get_slot_foreign_mut::<GameObject>(1) {
+            Some(comp) => {
+                if matches!(vm.get_slot_type(2), SlotType::Null) {
+                    comp.get_mut::<Scene>().ambient = None;
+                } else {
+                    match color_from_slot(vm, 2, 3) {
+                        Some(color) => {
+                            comp.get_mut::<Scene>().ambient = Some(color);
+                        }
+                        None => {
+                            LilahTypeError!(Scene, 2, List);
+                        }
+                    }
+                }
+            }
+            None => {
+                LilahTypeError!(Scene, 1, GameObject);
+            }
+        }
+    }
+
+    fn wren_set_occluders_from_gameobject(vm: &VM) {
+        match vm.get_slot_foreign_mut::<GameObject>(1) {
+            Some(comp) => match vm.get_slot_bool(2) {
+                Some(occluders) => {
+                    comp.get_mut::<Scene>().occluders = occluders;
+                }
+                None => {
+                    LilahTypeError!(Scene, 2, bool);
+                }
+            },
+            None => {
+                LilahTypeError!(Scene, 1, GameObject);
+            }
+        }
+    }
+
+    fn wren_set_layer_parallax_from_gameobject(vm: &VM) {
+        if let Some(go) = vm.get_slot_foreign_mut::<GameObject>(1) {
+            match (vm.get_slot_double(2), vm.get_slot_foreign::<Vec2>(3).copied()) {
+                (Some(layer), Some(factor)) => {
+                    if let Some(scene) = go.wrap_component_mut::<Scene>() {
+                        scene
+                            .layer_parallax
+                            .entry(layer as usize)
+                            .or_insert((Vec2::ONE, (false, false)))
+                            .0 = factor;
+                    }
+                }
+                (None, _) => {
+                    LilahTypeError!(Scene, 2, Num);
+                }
+                (_, None) => {
+                    LilahTypeError!(Scene, 3, Vec2);
+                }
+            }
+        } else {
+            LilahTypeError!(Scene, 1, GameObject);
+        }
+    }
+
+    fn wren_set_layer_repeat_from_gameobject(vm: &VM) {
+        if let Some(go) = vm.get_slot_foreign_mut::<GameObject>(1) {
+            match (vm.get_slot_double(2), vm.get_slot_bool(3), vm.get_slot_bool(4)) {
+                (Some(layer), Some(x), Some(y)) => {
+                    if let Some(scene) = go.wrap_component_mut::<Scene>() {
+                        scene
+                            .layer_parallax
+                            .entry(layer as usize)
+                            .or_insert((Vec2::ONE, (false, false)))
+                            .1 = (x, y);
+                    }
+                }
+                (None, _, _) => {
+                    LilahTypeError!(Scene, 2, Num);
+                }
+                (_, None, _) => {
+                    LilahTypeError!(Scene, 3, bool);
+                }
+                (_, _, None) => {
+                    LilahTypeError!(Scene, 4, bool);
+                }
+            }
+        } else {
+            LilahTypeError!(Scene, 1, GameObject);
+        }
+    }
+
+    fn wren_set_layer_sort_layer_from_gameobject(vm: &VM) {
+        if let Some(go) = vm.get_slot_foreign_mut::<GameObject>(1) {
+            match (vm.get_slot_double(2), vm.get_slot_string(3)) {
+                (Some(layer), Some(name)) => {
+                    if let Some(scene) = go.wrap_component_mut::<Scene>() {
+                        scene.layer_sort_layers.insert(layer as usize, name);
+                    }
+                }
+                (None, _) => {
+                    LilahTypeError!(Scene, 2, Num);
+                }
+                (_, None) => {
+                    LilahTypeError!(Scene, 3, String);
+                }
+            }
+        } else {
+            LilahTypeError!(Scene, 1, GameObject);
+        }
+    }
+
+    fn wren_set_layer_material_from_gameobject(vm: &VM) {
+        if let Some(go) = vm.get_slot_foreign_mut::<GameObject>(1) {
+            let Some(layer) = vm.get_slot_double(2) else {
+                LilahTypeError!(Scene, 2, Num);
+                return;
+            };
+            if let Some(scene) = go.wrap_component_mut::<Scene>() {
+                match vm.get_slot_string(3) {
+                    Some(name) => {
+                        let entry = scene
+                            .layer_materials
+                            .entry(layer as usize)
+                            .or_insert_with(|| (name.clone(), Uniforms::default()));
+                        entry.0 = name;
+                    }
+                    None => {
+                        scene.layer_materials.remove(&(layer as usize));
+                    }
+                }
+            }
+        } else {
+            LilahTypeError!(Scene, 1, GameObject);
+        }
+    }
+
+    fn wren_set_layer_uniform_from_gameobject(vm: &VM) {
+        if let Some(go) = vm.get_slot_foreign_mut::<GameObject>(1) {
+            match (
+                vm.get_slot_double(2),
+                vm.get_slot_string(3),
+                uniform_from_slot(vm, 4, 5),
+            ) {
+                (Some(layer), Some(name), Some(value)) => {
+                    if let Some(scene) = go.wrap_component_mut::<Scene>() {
+                        match scene.layer_materials.get_mut(&(layer as usize)) {
+                            Some(m) => m.1.set(&name, value),
+                            None => {
+                                let layer = layer as usize;
+                                LilahNotFoundError!(Scene, Material, layer);
+                            }
+                        }
+                    }
+                }
+                (None, _, _) => {
+                    LilahTypeError!(Scene, 2, Num);
+                }
+                (_, None, _) => {
+                    LilahTypeError!(Scene, 3, String);
+                }
+                (_, _, None) => {
+                    LilahTypeError!(Scene, 4, Uniform);
+                }
+            }
+        } else {
+            LilahTypeError!(Scene, 1, GameObject);
+        }
+    }
+
+    /// Position is in world space, the tile's chunk is rebuilt before the next draw.
+    fn wren_set_tile_from_gameobject(vm: &VM) {
+        if let Some(go) = vm.get_slot_foreign_mut::<GameObject>(1) {
+            match (
+                vm.get_slot_double(2),
+                vm.get_slot_foreign::<Vec2>(3).copied(),
+                vm.get_slot_foreign::<Vec2>(4).copied(),
+            ) {
+                (Some(layer), Some(position), Some(cell)) => {
+                    let offset = go
+                        .wrap_component::<Transform>()
+                        .map_or(Vec2::ZERO, |t| t.position);
+                    if let Some(scene) = go.wrap_component_mut::<Scene>() {
+                        let layer = layer as usize;
+                        match scene.tile_at(layer, position - offset) {
+                            Some(tile) => {
+                                scene.set_tile_cell(layer, tile, (cell.x as i32, cell.y as i32))
+                            }
+                            None => {
+                                LilahNotFoundError!(Scene, Tile, position);
+                            }
+                        }
+                    }
+                }
+                (None, _, _) => {
+                    LilahTypeError!(Scene, 2, Num);
+                }
+                (_, None, _) => {
+                    LilahTypeError!(Scene, 3, Vec2);
+                }
+                (_, _, None) => {
+                    LilahTypeError!(Scene, 4, Vec2);
+                }
+            }
+        } else {
+            LilahTypeError!(Scene, 1, GameObject);
+        }
+    }
+}
+
+impl Sfx {
+    fn wren_pull(&mut self, vm: &VM) {
+        match vm.get_slot_foreign::<GameObject>(1) {
+            Some(go) => match go.wrap_all::<Sfx>().into_iter().find(|s| s.name == self.name) {
+                Some(c) => *self = c.clone(),
+                None => {
+                    let name = self.name.clone();
+                    LilahNotFoundError!(GameObject, Sfx, name);
+                }
+            },
+            None => {
+                LilahTypeError!(Sfx, 1, GameObject);
+            }
+        }
+    }
+}
+
 impl Tickable<Sprite> for Rigidbody {
     fn tick(&mut self, _: f64, d: &Sprite) {
         let sprite_size = d.get_size();
@@ -2765,12 +5374,6 @@ impl Class for Box<dyn Component> {
     }
 }
 
-impl Class for Debug {
-    fn initialize(_: &VM) -> Self {
-        LilahPanic!(Debug, "Cannot instantiate static class");
-    }
-}
-
 impl Class for Transform {
     fn initialize(vm: &VM) -> Transform {
         if let Some(pos) = vm.get_slot_foreign::<Vec2>(1) {
@@ -2823,6 +5426,34 @@ impl Class for Animator {
     }
 }
 
+impl Class for Shape {
+    fn initialize(vm: &VM) -> Shape {
+        match shape_kind_from_slot(vm, 1, 2, 3) {
+            Some(kind) => Shape::new(kind),
+            None => {
+                LilahTypePanic!(Shape, 2, Geometry);
+            }
+        }
+    }
+}
+
+impl Class for Light2D {
+    fn initialize(vm: &VM) -> Light2D {
+        match vm.get_slot_double(1) {
+            Some(radius) => Light2D::new(radius),
+            None => {
+                LilahTypePanic!(Light2D, 1, f64);
+            }
+        }
+    }
+}
+
+impl Class for Camera {
+    fn initialize(_: &VM) -> Camera {
+        Camera::new()
+    }
+}
+
 impl Class for Sfx {
     fn initialize(vm: &VM) -> Sfx {
         if let (Some(b), Some(c)) = (vm.get_slot_string(1), vm.get_slot_string(2)) {
@@ -2852,6 +5483,7 @@ impl Class for Line {
 create_module! (
     class("Transform") crate::components::Transform => transform {
         instance(getter "as_component") wren_as_component,
+        instance(fn "pull", 1) wren_pull,
         instance(getter "position") wren_get_pos,
         instance(getter "scale") wren_get_scale,
         instance(getter "rotation") wren_get_rotation,
@@ -2879,7 +5511,14 @@ create_module! (
     }
 
     class("Sprite") crate::components::Sprite => sprite {
+        instance(getter "sort_layer") wren_get_sort_layer,
+        instance(getter "sort_pivot") wren_get_sort_pivot,
+        static(fn "set_sort_layer", 2) wren_set_sort_layer_from_gameobject,
+        static(fn "set_sort_pivot", 2) wren_set_sort_pivot_from_gameobject,
         instance(getter "as_component") wren_as_component,
+        instance(fn "pull", 1) wren_pull,
+        instance(getter "blend") wren_get_blend,
+        static(fn "set_blend", 2) wren_set_blend_from_gameobject,
         instance(getter "size") wren_get_size,
         instance(getter "tint") wren_get_tint,
         instance(getter "texture_id") wren_get_texture_id,
@@ -2887,8 +5526,25 @@ create_module! (
         instance(getter "parent") wren_get_parent,
         instance(fn "cut_sprite_sheet", 2) wren_cut_sprite_sheet,
         static(fn "cut_sprite_sheet", 3) wren_cut_sprite_sheet_from_gameobject,
+        instance(getter "material") wren_get_material,
         static(fn "set_sort", 2) wren_set_sort_from_gameobject,
-        static(fn "set_tint", 2) wren_set_tint_from_gameobject
+        static(fn "set_tint", 2) wren_set_tint_from_gameobject,
+        static(fn "set_material", 2) wren_set_material_from_gameobject,
+        static(fn "set_uniform", 3) wren_set_uniform_from_gameobject,
+        instance(getter "flip_x") wren_get_flip_x,
+        instance(getter "flip_y") wren_get_flip_y,
+        instance(getter "fill") wren_get_fill,
+        instance(getter "extent") wren_get_extent,
+        instance(getter "border") wren_get_border,
+        instance(getter "parallax") wren_get_parallax,
+        instance(getter "repeat") wren_get_repeat,
+        static(fn "set_parallax", 2) wren_set_parallax_from_gameobject,
+        static(fn "set_repeat", 3) wren_set_repeat_from_gameobject,
+        static(fn "set_flip_x", 2) wren_set_flip_x_from_gameobject,
+        static(fn "set_flip_y", 2) wren_set_flip_y_from_gameobject,
+        static(fn "set_simple", 1) wren_set_simple_from_gameobject,
+        static(fn "set_sliced", 3) wren_set_sliced_from_gameobject,
+        static(fn "set_tiled", 2) wren_set_tiled_from_gameobject
     }
 
     class("Component") Box<dyn crate::components::Component> => component {
@@ -2896,13 +5552,34 @@ create_module! (
 
     class("Scene") crate::components::Scene => scene {
         instance(getter "as_component") wren_as_component,
+        instance(fn "pull", 1) wren_pull,
         instance(getter "markers") wren_markers,
-        instance(getter "parent") wren_get_parent
+        instance(getter "parent") wren_get_parent,
+        instance(getter "ambient") wren_get_ambient,
+        instance(getter "occluders") wren_get_occluders,
+        static(fn "set_ambient", 2) wren_set_ambient_from_gameobject,
+        static(fn "set_occluders", 2) wren_set_occluders_from_gameobject,
+        static(fn "set_layer_parallax", 3) wren_set_layer_parallax_from_gameobject,
+        static(fn "set_layer_repeat", 4) wren_set_layer_repeat_from_gameobject,
+        static(fn "set_layer_sort_layer", 3) wren_set_layer_sort_layer_from_gameobject,
+        static(fn "set_layer_material", 3) wren_set_layer_material_from_gameobject,
+        static(fn "set_layer_uniform", 4) wren_set_layer_uniform_from_gameobject,
+        static(fn "set_tile", 4) wren_set_tile_from_gameobject
     }
 
     class("GameObject") crate::gameobject::GameObject => go {
         instance(fn "getComponent", 1) wren_get_component,
+        instance(fn "resetFrom", 1) wren_reset_from,
+        instance(fn "instance", 0) wren_instance,
+        instance(getter "changed") wren_getter_changed,
+        instance(getter "active") wren_getter_active,
+        instance(setter "active") wren_setter_active,
         instance(fn "addComponent", 1) wren_add_component,
+        instance(fn "hasComponent", 1) wren_has_component,
+        instance(fn "addTag", 1) wren_add_tag,
+        instance(fn "removeTag", 1) wren_remove_tag,
+        instance(fn "hasTag", 1) wren_has_tag,
+        instance(getter "tags") wren_getter_tags,
         instance(getter "id") wren_getter_id,
         instance(getter "components") wren_get_components,
         instance(getter "name") wren_getter_name,
@@ -2912,11 +5589,14 @@ create_module! (
 
     class("Rigidbody") crate::components::Rigidbody => rigidbody {
         instance(getter "as_component") wren_as_component,
+        instance(fn "pull", 1) wren_pull,
         instance(getter "position") wren_pos_getter,
         instance(getter "velocity") wren_vel_getter,
         instance(setter "velocity") wren_vel_setter,
         instance(getter "solid") wren_solid_getter,
         instance(setter "solid") wren_solid_setter,
+        instance(getter "occluder") wren_occluder_getter,
+        instance(setter "occluder") wren_occluder_setter,
         instance(getter "colliding") wren_colliding_getter,
         instance(getter "parent") wren_get_parent,
         static(fn "colliding", 1) wren_colliding_from_gameobject,
@@ -2930,11 +5610,13 @@ create_module! (
         static(fn "update_velocity_x", 2) wren_update_vel_x_from_gameobject,
         static(fn "update_velocity_y", 2) wren_update_vel_y_from_gameobject,
         static(fn "set_solid", 2) wren_set_solid_from_gameobject,
+        static(fn "set_occluder", 2) wren_set_occluder_from_gameobject,
         static(fn "set_rotation", 2) wren_set_rot_from_gameobject
     }
 
     class("Animator") crate::components::Animator => animator {
         instance(getter "as_component") wren_as_component,
+        instance(fn "pull", 1) wren_pull,
         instance(getter "playing") wren_playing_getter,
         instance(getter "frame") wren_frame_getter,
         instance(getter "speed") wren_speed_getter,
@@ -2953,6 +5635,70 @@ create_module! (
         static(fn "set_frame", 2) wren_set_frame_from_gameobject
     }
 
+    class("Shape") crate::components::Shape => shape {
+        instance(getter "sort_layer") wren_get_sort_layer,
+        instance(getter "sort_pivot") wren_get_sort_pivot,
+        static(fn "set_sort_layer", 2) wren_set_sort_layer_from_gameobject,
+        static(fn "set_sort_pivot", 2) wren_set_sort_pivot_from_gameobject,
+        instance(getter "as_component") wren_as_component,
+        instance(fn "pull", 1) wren_pull,
+        instance(getter "parent") wren_get_parent,
+        instance(getter "color") wren_get_color,
+        instance(getter "filled") wren_get_filled,
+        instance(getter "thickness") wren_get_thickness,
+        instance(getter "sort") wren_get_sort,
+        static(fn "set_color", 2) wren_set_color_from_gameobject,
+        static(fn "set_filled", 2) wren_set_filled_from_gameobject,
+        static(fn "set_thickness", 2) wren_set_thickness_from_gameobject,
+        static(fn "set_sort", 2) wren_set_sort_from_gameobject,
+        static(fn "set_geometry", 3) wren_set_geometry_from_gameobject
+    }
+
+    class("Light2D") crate::components::Light2D => light2d {
+        instance(getter "as_component") wren_as_component,
+        instance(fn "pull", 1) wren_pull,
+        instance(getter "parent") wren_get_parent,
+        instance(getter "radius") wren_get_radius,
+        instance(getter "color") wren_get_color,
+        instance(getter "intensity") wren_get_intensity,
+        instance(getter "falloff") wren_get_falloff,
+        instance(getter "shadows") wren_get_shadows,
+        instance(getter "softness") wren_get_softness,
+        static(fn "set_radius", 2) wren_set_radius_from_gameobject,
+        static(fn "set_color", 2) wren_set_color_from_gameobject,
+        static(fn "set_intensity", 2) wren_set_intensity_from_gameobject,
+        static(fn "set_falloff", 2) wren_set_falloff_from_gameobject,
+        static(fn "set_shadows", 2) wren_set_shadows_from_gameobject,
+        static(fn "set_softness", 2) wren_set_softness_from_gameobject
+    }
+
+    class("Camera") crate::components::Camera => camera {
+        instance(getter "as_component") wren_as_component,
+        instance(fn "pull", 1) wren_pull,
+        instance(getter "parent") wren_get_parent,
+        instance(getter "zoom") wren_zoom_getter,
+        instance(getter "rotation") wren_rotation_getter,
+        instance(getter "target") wren_target_getter,
+        instance(getter "smoothing") wren_smoothing_getter,
+        instance(getter "dead_zone") wren_dead_zone_getter,
+        instance(getter "bounds") wren_bounds_getter,
+        instance(getter "trauma") wren_trauma_getter,
+        instance(getter "viewport") wren_viewport_getter,
+        instance(getter "order") wren_order_getter,
+        static(fn "set_zoom", 2) wren_set_zoom_from_gameobject,
+        static(fn "set_rotation", 2) wren_set_rotation_from_gameobject,
+        static(fn "set_target", 2) wren_set_target_from_gameobject,
+        static(fn "set_smoothing", 2) wren_set_smoothing_from_gameobject,
+        static(fn "set_dead_zone", 2) wren_set_dead_zone_from_gameobject,
+        static(fn "set_bounds", 3) wren_set_bounds_from_gameobject,
+        static(fn "clamp_to_scene", 2) wren_clamp_to_scene_from_gameobject,
+        static(fn "shake", 2) wren_shake_from_gameobject,
+        static(fn "set_trauma_decay", 2) wren_set_trauma_decay_from_gameobject,
+        static(fn "set_max_shake", 3) wren_set_max_shake_from_gameobject,
+        static(fn "set_viewport", 2) wren_set_viewport_from_gameobject,
+        static(fn "set_order", 2) wren_set_order_from_gameobject
+    }
+
     class("ComponentBehaviour") crate::components::ComponentBehaviour => component_behaviour {
         instance(getter "as_component") wren_as_component,
         instance(getter "uuid") wren_getter_uuid,
@@ -2960,7 +5706,14 @@ create_module! (
     }
 
     class("Text") crate::components::Text => text {
+        instance(getter "sort_layer") wren_get_sort_layer,
+        instance(getter "sort_pivot") wren_get_sort_pivot,
+        static(fn "set_sort_layer", 2) wren_set_sort_layer_from_gameobject,
+        static(fn "set_sort_pivot", 2) wren_set_sort_pivot_from_gameobject,
         instance(getter "as_component") wren_as_component,
+        instance(fn "pull", 1) wren_pull,
+        instance(getter "blend") wren_get_blend,
+        static(fn "set_blend", 2) wren_set_blend_from_gameobject,
         instance(getter "text") wren_get_text,
         instance(getter "font") wren_get_font,
         instance(getter "font_size") wren_get_font_size,
@@ -2969,12 +5722,44 @@ create_module! (
         static(fn "get_font", 1) wren_get_font_from_gameobject,
         static(fn "get_font_size", 1) wren_get_font_size_from_gameobject,
         static(fn "set_text", 2) wren_set_text_from_gameobject,
+        instance(getter "material") wren_get_material,
         static(fn "set_font", 2) wren_set_font_from_gameobject,
-        static(fn "set_font_size", 2) wren_set_font_size_from_gameobject
+        static(fn "set_font_size", 2) wren_set_font_size_from_gameobject,
+        instance(getter "max_width") wren_get_max_width,
+        instance(getter "min_height") wren_get_min_height,
+        instance(getter "align") wren_get_align,
+        instance(getter "valign") wren_get_valign,
+        instance(getter "line_spacing") wren_get_line_spacing,
+        instance(getter "bounds") wren_get_bounds,
+        instance(getter "line_count") wren_get_line_count,
+        static(fn "set_max_width", 2) wren_set_max_width_from_gameobject,
+        static(fn "set_min_height", 2) wren_set_min_height_from_gameobject,
+        static(fn "set_align", 2) wren_set_align_from_gameobject,
+        static(fn "set_valign", 2) wren_set_valign_from_gameobject,
+        static(fn "set_line_spacing", 2) wren_set_line_spacing_from_gameobject,
+        instance(getter "outline_width") wren_get_outline_width,
+        instance(getter "outline_color") wren_get_outline_color,
+        instance(getter "shadow_offset") wren_get_shadow_offset,
+        instance(getter "shadow_color") wren_get_shadow_color,
+        instance(getter "wave") wren_get_wave,
+        instance(getter "shake") wren_get_shake,
+        instance(getter "revealed") wren_get_revealed,
+        instance(getter "reveal_speed") wren_get_reveal_speed,
+        instance(getter "glyph_count") wren_get_glyph_count,
+        instance(fn "glyph", 1) wren_glyph,
+        static(fn "set_outline", 3) wren_set_outline_from_gameobject,
+        static(fn "set_shadow", 3) wren_set_shadow_from_gameobject,
+        static(fn "set_wave", 4) wren_set_wave_from_gameobject,
+        static(fn "set_shake", 2) wren_set_shake_from_gameobject,
+        static(fn "set_revealed", 2) wren_set_revealed_from_gameobject,
+        static(fn "set_reveal_speed", 2) wren_set_reveal_speed_from_gameobject,
+        static(fn "set_material", 2) wren_set_material_from_gameobject,
+        static(fn "set_uniform", 3) wren_set_uniform_from_gameobject
     }
 
     class("Sfx") crate::components::Sfx => sfx {
         instance(getter "as_component") wren_as_component,
+        instance(fn "pull", 1) wren_pull,
         instance(getter "name") wren_name_getter,
         instance(setter "name") wren_name_setter,
         instance(getter "volume") wren_volume_getter,
@@ -2986,18 +5771,19 @@ create_module! (
         static(fn "play", 2) wren_play_from_gameobject
     }
 
-    class("Debug") crate::components::Debug => debug {
-        static(fn "drawLine", 3) wren_draw_line
-    }
 
     class("Line") crate::components::Line => line {
         instance(getter "as_component") wren_as_component,
+        instance(fn "pull", 1) wren_pull,
+        instance(getter "blend") wren_get_blend,
+        static(fn "set_blend", 2) wren_set_blend_from_gameobject,
         instance(getter "color") wren_get_tint,
         instance(getter "opacity") wren_get_opacity,
         instance(getter "sort") wren_get_sort,
         instance(getter "thickness") wren_get_thickness,
         instance(getter "points") wren_get_points,
         instance(getter "parent") wren_get_parent,
+        instance(getter "material") wren_get_material,
         static(fn "set_sort", 2) wren_set_sort_from_gameobject,
         static(fn "get_sort", 1) wren_get_sort_from_gameobject,
         static(fn "get_thickness", 1) wren_get_thickness_from_gameobject,
@@ -3008,7 +5794,9 @@ create_module! (
         static(fn "pop_point", 1) wren_pop_point_from_gameobject,
         static(fn "add_point", 2) wren_add_point_from_gameobject,
         static(fn "insert_point", 3) wren_insert_point_from_gameobject,
-        static(fn "set_point", 3) wren_set_point_from_gameobject
+        static(fn "set_point", 3) wren_set_point_from_gameobject,
+        static(fn "set_material", 2) wren_set_material_from_gameobject,
+        static(fn "set_uniform", 3) wren_set_uniform_from_gameobject
     }
 
     module => game
@@ -3017,3 +5805,93 @@ create_module! (
 pub fn publish_modules(lib: &mut ModuleLibrary) {
     game::publish_module(lib);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sprite(cell: (u32, u32), fill: SpriteFill) -> Sprite {
+        let mut sprite = Sprite::new("test");
+        sprite.size = cell;
+        sprite.fill = fill;
+        sprite
+    }
+
+    #[test]
+    fn cameras_refuse_non_positive_zoom() {
+        let mut camera = Camera::new().with_zoom(2.0);
+        assert_eq!(camera.zoom, 2.0);
+
+        for zoom in [0.0, -1.0, f64::NAN] {
+            camera.set_zoom(zoom);
+            assert_eq!(camera.zoom, 2.0);
+        }
+        assert_eq!(Camera::new().with_zoom(0.0).zoom, 1.0);
+    }
+
+    #[test]
+    fn simple_sprites_are_one_piece() {
+        let pieces = sprite((16, 8), SpriteFill::Simple).pieces();
+
+        assert_eq!(pieces, vec![([-8.0, -4.0], [8.0, 4.0], [0.0, 0.0], [1.0, 1.0])]);
+    }
+
+    #[test]
+    fn sliced_sprites_keep_their_borders() {
+        let fill = SpriteFill::Sliced {
+            size: Vec2::new(32.0, 24.0),
+            border: [4.0; 4],
+        };
+        let pieces = sprite((16, 16), fill).pieces();
+
+        assert_eq!(pieces.len(), 9);
+        assert_eq!(pieces[0], ([-16.0, -12.0], [-12.0, -8.0], [0.0, 0.0], [0.25, 0.25]));
+        assert_eq!(pieces[4], ([-12.0, -8.0], [12.0, 8.0], [0.25, 0.25], [0.75, 0.75]));
+        assert_eq!(pieces[8], ([12.0, 8.0], [16.0, 12.0], [0.75, 0.75], [1.0, 1.0]));
+    }
+
+    #[test]
+    fn sliced_borders_shrink_to_fit() {
+        let fill = SpriteFill::Sliced {
+            size: Vec2::new(4.0, 24.0),
+            border: [4.0; 4],
+        };
+        let pieces = sprite((16, 16), fill).pieces();
+
+        // the middle column has no width left and is dropped
+        assert_eq!(pieces.len(), 6);
+        assert_eq!(pieces[0], ([-2.0, -12.0], [0.0, -8.0], [0.0, 0.0], [0.25, 0.25]));
+    }
+
+    #[test]
+    fn flipped_slices_swap_borders() {
+        let fill = SpriteFill::Sliced {
+            size: Vec2::new(32.0, 16.0),
+            border: [2.0, 6.0, 0.0, 0.0],
+        };
+        let mut flipped = sprite((16, 16), fill);
+        flipped.flip_x = true;
+        let pieces = flipped.pieces();
+
+        assert_eq!(pieces.len(), 3);
+        assert_eq!(pieces[0], ([-16.0, -8.0], [-10.0, 8.0], [0.0, 0.0], [0.375, 1.0]));
+    }
+
+    #[test]
+    fn tiled_sprites_clip_the_last_tile() {
+        let fill = SpriteFill::Tiled {
+            size: Vec2::new(25.0, 10.0),
+        };
+        let pieces = sprite((10, 10), fill.clone()).pieces();
+
+        assert_eq!(
+            pieces,
+            vec![
+                ([-12.5, -5.0], [-2.5, 5.0], [0.0, 0.0], [1.0, 1.0]),
+                ([-2.5, -5.0], [7.5, 5.0], [0.0, 0.0], [1.0, 1.0]),
+                ([7.5, -5.0], [12.5, 5.0], [0.0, 0.0], [0.5, 1.0]),
+            ]
+        );
+        assert!(sprite((0, 0), fill).pieces().is_empty());
+    }
+}
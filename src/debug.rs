@@ -0,0 +1,469 @@
+use std::ptr;
+
+use gl::types::*;
+use glam::Vec3;
+
+use crate::math::Vec2;
+use crate::renderer::{Buffer, Color, Pos, Shader, ShaderProgram, Tint, VertexArray};
+use crate::{set_attribute, LilahError};
+
+/// What debug draw coordinates are relative to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DebugSpace {
+    /// World units, drawn through every camera.
+    World,
+    /// Window pixels from the top left, drawn once over the whole view.
+    Screen,
+}
+
+#[derive(Clone, Debug)]
+enum DebugShape {
+    Line(Vec2, Vec2),
+    Arrow(Vec2, Vec2),
+    /// Center and size.
+    Rect(Vec2, Vec2),
+    Circle(Vec2, f64),
+    /// Closed loop.
+    Polygon(Vec<Vec2>),
+    /// Top left of the first glyph.
+    Text(Vec2, String),
+}
+
+struct DebugItem {
+    shape: DebugShape,
+    color: Color,
+    space: DebugSpace,
+    time_left: f64,
+}
+
+/// Vertex used by DebugDraw. Positions are already in normalized device coordinates.
+#[repr(C, packed)]
+struct DebugVertex(Pos, Tint);
+
+/// Immediate mode lines, boxes, circles, arrows and labels for debugging.
+/// Draws issued while enabled is false are dropped, with one error logged the first time.
+/// Everything is tessellated into triangles in pixels, so widths stay the same at any zoom,
+/// and each space is drawn in one call from a buffer kept between frames.
+pub struct DebugDraw {
+    /// Nothing is queued or drawn while false. Set from Lilah.debug in wren.
+    pub enabled: bool,
+    /// Outlines rigidbody colliders and contacts each frame while enabled.
+    pub colliders: bool,
+    /// Line width in pixels.
+    pub thickness: f32,
+    /// Pixels per font pixel for labels.
+    pub text_scale: f32,
+    items: Vec<DebugItem>,
+    /// Set once a draw was dropped for being disabled, so it is only reported once.
+    dropped: bool,
+    vertices: Vec<DebugVertex>,
+    capacity: usize,
+    program: ShaderProgram,
+    vertex_array: VertexArray,
+    vertex_buffer: Buffer,
+}
+
+impl DebugDraw {
+    const VERT: &'static str = r#"
+    #version 330
+    layout(location = 0) in vec2 position;
+    layout(location = 1) in vec4 vertexColor;
+    out vec4 color;
+
+    void main() {
+        color = vertexColor;
+        gl_Position = vec4(position, 0.0, 1.0);
+    }
+    "#;
+
+    const FRAG: &'static str = r#"
+    #version 330 core
+    in vec4 color;
+    out vec4 FragColor;
+
+    void main() {
+        FragColor = color;
+    }
+    "#;
+
+    pub unsafe fn new() -> Self {
+        let program = {
+            let vs = Shader::new(Self::VERT, gl::VERTEX_SHADER).unwrap();
+            let fs = Shader::new(Self::FRAG, gl::FRAGMENT_SHADER).unwrap();
+            ShaderProgram::new(&[fs, vs]).unwrap()
+        };
+
+        let vertex_array = VertexArray::new();
+        vertex_array.bind();
+
+        let vertex_buffer = Buffer::new(gl::ARRAY_BUFFER);
+        vertex_buffer.bind();
+
+        set_attribute!(vertex_array, 0, DebugVertex::0, gl::FLOAT);
+        set_attribute!(vertex_array, 1, DebugVertex::1, gl::FLOAT);
+
+        Self {
+            enabled: false,
+            colliders: false,
+            thickness: 2.0,
+            text_scale: 2.0,
+            items: vec![],
+            dropped: false,
+            vertices: vec![],
+            capacity: 0,
+            program,
+            vertex_array,
+            vertex_buffer,
+        }
+    }
+
+    fn push(&mut self, shape: DebugShape, color: Color, space: DebugSpace, duration: f64) {
+        if self.enabled {
+            self.items.push(DebugItem {
+                shape,
+                color,
+                space,
+                time_left: duration,
+            });
+        } else if !self.dropped {
+            self.dropped = true;
+            LilahError!(DebugDraw, "Debug draws are dropped while debug is off, set Lilah.debug");
+        }
+    }
+
+    /// duration is in seconds, 0 draws for one frame.
+    pub fn line(&mut self, start: Vec2, end: Vec2, color: Color, space: DebugSpace, duration: f64) {
+        self.push(DebugShape::Line(start, end), color, space, duration);
+    }
+
+    pub fn arrow(&mut self, start: Vec2, end: Vec2, color: Color, space: DebugSpace, duration: f64) {
+        self.push(DebugShape::Arrow(start, end), color, space, duration);
+    }
+
+    pub fn rect(&mut self, center: Vec2, size: Vec2, color: Color, space: DebugSpace, duration: f64) {
+        self.push(DebugShape::Rect(center, size), color, space, duration);
+    }
+
+    pub fn circle(&mut self, center: Vec2, radius: f64, color: Color, space: DebugSpace, duration: f64) {
+        self.push(DebugShape::Circle(center, radius), color, space, duration);
+    }
+
+    pub fn polygon(&mut self, points: Vec<Vec2>, color: Color, space: DebugSpace, duration: f64) {
+        self.push(DebugShape::Polygon(points), color, space, duration);
+    }
+
+    /// Labels use a built in 5x7 font and ignore the camera's zoom and rotation.
+    pub fn text(&mut self, position: Vec2, text: &str, color: Color, space: DebugSpace, duration: f64) {
+        self.push(DebugShape::Text(position, text.to_string()), color, space, duration);
+    }
+
+    /// Drops everything queued, including items still waiting out a duration.
+    pub fn clear(&mut self) {
+        self.items.clear();
+    }
+
+    /// Ages queued items. Call once after the frame is drawn.
+    pub fn tick(&mut self, dt: f64) {
+        if !self.enabled {
+            self.items.clear();
+            return;
+        }
+
+        for item in self.items.iter_mut() {
+            item.time_left -= dt;
+        }
+        self.items.retain(|i| i.time_left > 0.0);
+    }
+
+    /// Draws the items in space with the current view, projection and math::VIEWPORT.
+    /// Flush the batch first so debug draws land on top.
+    pub unsafe fn draw(&mut self, space: DebugSpace) {
+        if !self.enabled || self.items.is_empty() {
+            return;
+        }
+
+        let [vx, vy, vw, vh] = *crate::math::VIEWPORT;
+        let view_projection = *crate::math::PROJECTION_MATRIX * *crate::math::VIEW_MATRIX;
+        // into pixels from the top left of the viewport, y down
+        let to_px = |p: Vec2| -> [f32; 2] {
+            match space {
+                DebugSpace::World => {
+                    let ndc = view_projection.transform_point3(Vec3::new(p.x as f32, p.y as f32, 0.0));
+                    [(ndc.x + 1.0) / 2.0 * vw, (1.0 - ndc.y) / 2.0 * vh]
+                }
+                DebugSpace::Screen => [p.x as f32 - vx, p.y as f32 - vy],
+            }
+        };
+
+        let mut mesh = Mesh {
+            triangles: vec![],
+            thickness: self.thickness,
+        };
+        for item in self.items.iter().filter(|i| i.space == space) {
+            let start = mesh.triangles.len();
+
+            match &item.shape {
+                DebugShape::Line(a, b) => mesh.line(to_px(*a), to_px(*b)),
+                DebugShape::Arrow(a, b) => mesh.arrow(to_px(*a), to_px(*b)),
+                DebugShape::Rect(center, size) => {
+                    let half = *size / 2.0;
+                    mesh.outline(&[
+                        to_px(*center + Vec2::new(-half.x, -half.y)),
+                        to_px(*center + Vec2::new(half.x, -half.y)),
+                        to_px(*center + Vec2::new(half.x, half.y)),
+                        to_px(*center + Vec2::new(-half.x, half.y)),
+                    ]);
+                }
+                DebugShape::Circle(center, radius) => {
+                    let c = to_px(*center);
+                    let edge = to_px(*center + Vec2::new(*radius, 0.0));
+                    let radius_px = ((edge[0] - c[0]).powi(2) + (edge[1] - c[1]).powi(2)).sqrt();
+                    let segments = (radius_px.sqrt() * 4.0).clamp(12.0, 64.0) as usize;
+
+                    let points: Vec<[f32; 2]> = (0..segments)
+                        .map(|i| {
+                            let a = i as f64 / segments as f64 * std::f64::consts::TAU;
+                            to_px(*center + Vec2::new(a.cos() * radius, a.sin() * radius))
+                        })
+                        .collect();
+                    mesh.outline(&points);
+                }
+                DebugShape::Polygon(points) => {
+                    let points: Vec<[f32; 2]> = points.iter().map(|p| to_px(*p)).collect();
+                    mesh.outline(&points);
+                }
+                DebugShape::Text(position, text) => mesh.text(to_px(*position), text, self.text_scale),
+            }
+
+            let tint = [item.color.r, item.color.g, item.color.b, item.color.a];
+            for p in &mesh.triangles[start..] {
+                self.vertices.push(DebugVertex(
+                    [p[0] / vw * 2.0 - 1.0, 1.0 - p[1] / vh * 2.0],
+                    tint,
+                ));
+            }
+        }
+
+        if self.vertices.is_empty() {
+            return;
+        }
+
+        self.program.apply();
+        self.vertex_array.bind();
+        self.vertex_buffer.bind();
+
+        let (_, data_bytes, _) = self.vertices.align_to::<u8>();
+        if self.vertices.len() > self.capacity {
+            // grows only, later frames reuse the storage
+            self.capacity = self.vertices.len().next_power_of_two();
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                (self.capacity * std::mem::size_of::<DebugVertex>()) as GLsizeiptr,
+                ptr::null(),
+                gl::DYNAMIC_DRAW,
+            );
+        }
+        gl::BufferSubData(
+            gl::ARRAY_BUFFER,
+            0,
+            data_bytes.len() as GLsizeiptr,
+            data_bytes.as_ptr() as *const _,
+        );
+        gl::DrawArrays(gl::TRIANGLES, 0, self.vertices.len() as GLsizei);
+
+        self.vertices.clear();
+    }
+}
+
+/// Triangles in viewport pixels.
+struct Mesh {
+    triangles: Vec<[f32; 2]>,
+    thickness: f32,
+}
+
+impl Mesh {
+    fn quad(&mut self, corners: [[f32; 2]; 4]) {
+        let [a, b, c, d] = corners;
+        self.triangles.extend_from_slice(&[a, b, c, c, d, a]);
+    }
+
+    /// Extended by half the width on both ends so joined segments leave no gaps.
+    fn line(&mut self, a: [f32; 2], b: [f32; 2]) {
+        let (dx, dy) = (b[0] - a[0], b[1] - a[1]);
+        let len = (dx * dx + dy * dy).sqrt();
+        if len == 0.0 {
+            return;
+        }
+
+        let half = self.thickness / 2.0;
+        let (dx, dy) = (dx / len * half, dy / len * half);
+        let (a, b) = ([a[0] - dx, a[1] - dy], [b[0] + dx, b[1] + dy]);
+        self.quad([
+            [a[0] + dy, a[1] - dx],
+            [b[0] + dy, b[1] - dx],
+            [b[0] - dy, b[1] + dx],
+            [a[0] - dy, a[1] + dx],
+        ]);
+    }
+
+    fn outline(&mut self, points: &[[f32; 2]]) {
+        for i in 0..points.len() {
+            self.line(points[i], points[(i + 1) % points.len()]);
+        }
+    }
+
+    /// Head is a fixed size in pixels, shrunk for short arrows.
+    fn arrow(&mut self, a: [f32; 2], b: [f32; 2]) {
+        self.line(a, b);
+
+        let (dx, dy) = (b[0] - a[0], b[1] - a[1]);
+        let len = (dx * dx + dy * dy).sqrt();
+        if len == 0.0 {
+            return;
+        }
+
+        let head = (self.thickness * 5.0).min(len / 2.0);
+        let (ux, uy) = (dx / len * head, dy / len * head);
+        let base = [b[0] - ux, b[1] - uy];
+        self.line(b, [base[0] + uy / 2.0, base[1] - ux / 2.0]);
+        self.line(b, [base[0] - uy / 2.0, base[1] + ux / 2.0]);
+    }
+
+    fn text(&mut self, origin: [f32; 2], text: &str, scale: f32) {
+        let (mut x, mut y) = (origin[0], origin[1]);
+
+        for c in text.chars() {
+            if c == '\n' {
+                x = origin[0];
+                y += (FONT_HEIGHT + 1) as f32 * scale;
+                continue;
+            }
+
+            let glyph = glyph(c);
+            for (column, bits) in glyph.iter().enumerate() {
+                for row in 0..FONT_HEIGHT {
+                    if (bits >> row) & 1 != 0 {
+                        let (px, py) = (x + column as f32 * scale, y + row as f32 * scale);
+                        self.quad([
+                            [px, py],
+                            [px + scale, py],
+                            [px + scale, py + scale],
+                            [px, py + scale],
+                        ]);
+                    }
+                }
+            }
+            x += (glyph.len() + 1) as f32 * scale;
+        }
+    }
+}
+
+const FONT_HEIGHT: usize = 7;
+
+/// Columns of a printable ascii glyph, lowest bit at the top. Anything else draws as '?'.
+fn glyph(c: char) -> &'static [u8; 5] {
+    let index = c as usize;
+    if (0x20..0x7f).contains(&index) {
+        &FONT[index - 0x20]
+    } else {
+        &FONT['?' as usize - 0x20]
+    }
+}
+
+const FONT: [[u8; 5]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00], // ' '
+    [0x00, 0x00, 0x5f, 0x00, 0x00], // !
+    [0x00, 0x07, 0x00, 0x07, 0x00], // "
+    [0x14, 0x7f, 0x14, 0x7f, 0x14], // #
+    [0x24, 0x2a, 0x7f, 0x2a, 0x12], // $
+    [0x23, 0x13, 0x08, 0x64, 0x62], // %
+    [0x36, 0x49, 0x55, 0x22, 0x50], // &
+    [0x00, 0x05, 0x03, 0x00, 0x00], // '
+    [0x00, 0x1c, 0x22, 0x41, 0x00], // (
+    [0x00, 0x41, 0x22, 0x1c, 0x00], // )
+    [0x14, 0x08, 0x3e, 0x08, 0x14], // *
+    [0x08, 0x08, 0x3e, 0x08, 0x08], // +
+    [0x00, 0x50, 0x30, 0x00, 0x00], // ,
+    [0x08, 0x08, 0x08, 0x08, 0x08], // -
+    [0x00, 0x60, 0x60, 0x00, 0x00], // .
+    [0x20, 0x10, 0x08, 0x04, 0x02], // /
+    [0x3e, 0x51, 0x49, 0x45, 0x3e], // 0
+    [0x00, 0x42, 0x7f, 0x40, 0x00], // 1
+    [0x42, 0x61, 0x51, 0x49, 0x46], // 2
+    [0x21, 0x41, 0x45, 0x4b, 0x31], // 3
+    [0x18, 0x14, 0x12, 0x7f, 0x10], // 4
+    [0x27, 0x45, 0x45, 0x45, 0x39], // 5
+    [0x3c, 0x4a, 0x49, 0x49, 0x30], // 6
+    [0x01, 0x71, 0x09, 0x05, 0x03], // 7
+    [0x36, 0x49, 0x49, 0x49, 0x36], // 8
+    [0x06, 0x49, 0x49, 0x29, 0x1e], // 9
+    [0x00, 0x36, 0x36, 0x00, 0x00], // :
+    [0x00, 0x56, 0x36, 0x00, 0x00], // ;
+    [0x08, 0x14, 0x22, 0x41, 0x00], // <
+    [0x14, 0x14, 0x14, 0x14, 0x14], // =
+    [0x00, 0x41, 0x22, 0x14, 0x08], // >
+    [0x02, 0x01, 0x51, 0x09, 0x06], // ?
+    [0x32, 0x49, 0x79, 0x41, 0x3e], // @
+    [0x7e, 0x11, 0x11, 0x11, 0x7e], // A
+    [0x7f, 0x49, 0x49, 0x49, 0x36], // B
+    [0x3e, 0x41, 0x41, 0x41, 0x22], // C
+    [0x7f, 0x41, 0x41, 0x22, 0x1c], // D
+    [0x7f, 0x49, 0x49, 0x49, 0x41], // E
+    [0x7f, 0x09, 0x09, 0x09, 0x01], // F
+    [0x3e, 0x41, 0x49, 0x49, 0x7a], // G
+    [0x7f, 0x08, 0x08, 0x08, 0x7f], // H
+    [0x00, 0x41, 0x7f, 0x41, 0x00], // I
+    [0x20, 0x40, 0x41, 0x3f, 0x01], // J
+    [0x7f, 0x08, 0x14, 0x22, 0x41], // K
+    [0x7f, 0x40, 0x40, 0x40, 0x40], // L
+    [0x7f, 0x02, 0x0c, 0x02, 0x7f], // M
+    [0x7f, 0x04, 0x08, 0x10, 0x7f], // N
+    [0x3e, 0x41, 0x41, 0x41, 0x3e], // O
+    [0x7f, 0x09, 0x09, 0x09, 0x06], // P
+    [0x3e, 0x41, 0x51, 0x21, 0x5e], // Q
+    [0x7f, 0x09, 0x19, 0x29, 0x46], // R
+    [0x46, 0x49, 0x49, 0x49, 0x31], // S
+    [0x01, 0x01, 0x7f, 0x01, 0x01], // T
+    [0x3f, 0x40, 0x40, 0x40, 0x3f], // U
+    [0x1f, 0x20, 0x40, 0x20, 0x1f], // V
+    [0x3f, 0x40, 0x38, 0x40, 0x3f], // W
+    [0x63, 0x14, 0x08, 0x14, 0x63], // X
+    [0x07, 0x08, 0x70, 0x08, 0x07], // Y
+    [0x61, 0x51, 0x49, 0x45, 0x43], // Z
+    [0x00, 0x7f, 0x41, 0x41, 0x00], // [
+    [0x02, 0x04, 0x08, 0x10, 0x20], // \
+    [0x00, 0x41, 0x41, 0x7f, 0x00], // ]
+    [0x04, 0x02, 0x01, 0x02, 0x04], // ^
+    [0x40, 0x40, 0x40, 0x40, 0x40], // _
+    [0x00, 0x01, 0x02, 0x04, 0x00], // `
+    [0x20, 0x54, 0x54, 0x54, 0x78], // a
+    [0x7f, 0x48, 0x44, 0x44, 0x38], // b
+    [0x38, 0x44, 0x44, 0x44, 0x20], // c
+    [0x38, 0x44, 0x44, 0x48, 0x7f], // d
+    [0x38, 0x54, 0x54, 0x54, 0x18], // e
+    [0x08, 0x7e, 0x09, 0x01, 0x02], // f
+    [0x0c, 0x52, 0x52, 0x52, 0x3e], // g
+    [0x7f, 0x08, 0x04, 0x04, 0x78], // h
+    [0x00, 0x44, 0x7d, 0x40, 0x00], // i
+    [0x20, 0x40, 0x44, 0x3d, 0x00], // j
+    [0x7f, 0x10, 0x28, 0x44, 0x00], // k
+    [0x00, 0x41, 0x7f, 0x40, 0x00], // l
+    [0x7c, 0x04, 0x18, 0x04, 0x78], // m
+    [0x7c, 0x08, 0x04, 0x04, 0x78], // n
+    [0x38, 0x44, 0x44, 0x44, 0x38], // o
+    [0x7c, 0x14, 0x14, 0x14, 0x08], // p
+    [0x08, 0x14, 0x14, 0x18, 0x7c], // q
+    [0x7c, 0x08, 0x04, 0x04, 0x08], // r
+    [0x48, 0x54, 0x54, 0x54, 0x20], // s
+    [0x04, 0x3f, 0x44, 0x40, 0x20], // t
+    [0x3c, 0x40, 0x40, 0x20, 0x7c], // u
+    [0x1c, 0x20, 0x40, 0x20, 0x1c], // v
+    [0x3c, 0x40, 0x30, 0x40, 0x3c], // w
+    [0x44, 0x28, 0x10, 0x28, 0x44], // x
+    [0x0c, 0x50, 0x50, 0x50, 0x3c], // y
+    [0x44, 0x64, 0x54, 0x4c, 0x44], // z
+    [0x00, 0x08, 0x36, 0x41, 0x00], // {
+    [0x00, 0x00, 0x7f, 0x00, 0x00], // |
+    [0x00, 0x41, 0x36, 0x08, 0x00], // }
+    [0x08, 0x04, 0x08, 0x10, 0x08], // ~
+];
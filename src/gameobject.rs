@@ -1,15 +1,16 @@
 use crate::{
     application::App,
+    atlas::Atlas,
     components::{
-        Animator, Component, ComponentBehaviour, Line, Rigidbody, Scene, SceneData, Sfx, Sprite,
-        Text, Tickable, Transform,
+        Animator, Camera, Component, ComponentBehaviour, Light2D, Line, Rigidbody, Scene, SceneData,
+        Sfx, Shape, Sprite, Text, Tickable, Transform,
     },
     math::Vec2,
     renderer::LilahTexture,
+    text::LilahFont,
     world::StateUpdateContainer,
     LilahNotFoundError, LilahTypeError, LilahTypePanic,
 };
-use rusttype::Font;
 use ruwren::{send_foreign, Class, VM};
 use sdl2::mixer::Chunk;
 use std::collections::HashMap;
@@ -63,12 +64,34 @@ impl GameObjectId {
     }
 }
 
+/// Values rust changes on its own each frame. Compared before and after the rust side of a frame
+/// so only gameobjects that actually changed are sent to wren.
+#[derive(PartialEq)]
+pub(crate) struct SyncState {
+    transform: Option<(Vec2, Vec2, Vec2, f32)>,
+    body: Option<(Vec2, Vec2, Vec2, Option<GameObjectId>)>,
+    animator: Option<f64>,
+    sprite: Option<(i32, i32)>,
+    camera: Option<f64>,
+    text: Option<(Option<f64>, Vec2, usize, usize)>,
+    playing: usize,
+}
+
 pub struct GameObject {
     pub id: GameObjectId,
     pub components: Vec<Box<dyn Component>>,
+    /// Tags used for grouping. Indexed by WorldState on insert.
+    pub tags: Vec<String>,
     pub init: bool,
     pub start: bool,
     pub has_behaviour: bool,
+    /// Inactive gameobjects stay loaded but skip update, physics, behaviours and drawing.
+    pub active: bool,
+    /// Set by anything that borrows components mutably or changes the gameobject from wren,
+    /// cleared on clone. Picks up static setters called on a wren copy outside Lilah.write.
+    pub(crate) changed: bool,
+    /// Set by reset_from, holds start back for one load so behaviours start again.
+    pub(crate) restarting: bool,
 }
 
 impl Clone for GameObject {
@@ -76,9 +99,13 @@ impl Clone for GameObject {
         let mut g = Self {
             id: self.id.clone(),
             components: vec![],
+            tags: self.tags.clone(),
             has_behaviour: self.has_behaviour,
             init: self.init,
             start: self.start,
+            active: self.active,
+            changed: false,
+            restarting: self.restarting,
         };
 
         for i in &self.components {
@@ -104,9 +131,13 @@ impl GameObject {
         Self {
             id: GameObjectId::new(name),
             components: vec![],
+            tags: vec![],
             has_behaviour: false,
             init: false,
             start: false,
+            active: true,
+            changed: false,
+            restarting: false,
         }
     }
 
@@ -114,16 +145,14 @@ impl GameObject {
         &mut self,
         app: &mut App,
         tex: &HashMap<String, LilahTexture>,
-        fonts: &HashMap<String, Font>,
+        atlas: Option<&Atlas>,
+        fonts: &HashMap<String, LilahFont>,
         sfx: &HashMap<String, Chunk>,
         scenes: &HashMap<String, SceneData>,
     ) -> StateUpdateContainer {
-        let mut state_updates = StateUpdateContainer {
-            textures: None,
-            sfx: None,
-        };
+        let mut state_updates = StateUpdateContainer { sfx: None };
         if self.has::<Text>() {
-            state_updates.textures = self.get_mut::<Text>().load(app, fonts).textures;
+            self.get_mut::<Text>().load(app, fonts);
         }
 
         let mut sfx_updates = vec![];
@@ -149,6 +178,20 @@ impl GameObject {
         state_updates.sfx = Some(sfx_updates);
 
         if self.init {
+            // tiles changed since the scene loaded
+            if self.has::<Scene>() {
+                self.get_mut::<Scene>().rebuild_chunks(tex, atlas);
+            }
+
+            // behaviours start once the gameobject is first active
+            if !self.active {
+                return state_updates;
+            }
+            // respawned, start is called again on the next script tick
+            if self.restarting {
+                self.restarting = false;
+                return state_updates;
+            }
             if !self.start {
                 app.sort_dirty = true;
             }
@@ -157,10 +200,10 @@ impl GameObject {
         }
 
         if self.has::<Scene>() {
-            self.get_mut::<Scene>().load(app, tex, scenes);
+            self.get_mut::<Scene>().load(app, tex, atlas, scenes);
         }
         if self.has::<Sprite>() {
-            self.get_mut::<Sprite>().load(app, tex);
+            self.get_mut::<Sprite>().load(app, tex, atlas);
         }
         if self.has::<Rigidbody>() {
             if self.has::<Sprite>() {
@@ -174,10 +217,35 @@ impl GameObject {
             }
         }
 
+        // the first start is already a tick away
         self.init = true;
+        self.restarting = false;
         state_updates
     }
 
+    pub(crate) fn sync_state(&self) -> SyncState {
+        SyncState {
+            transform: self
+                .wrap_component::<Transform>()
+                .map(|t| (t.position, t.pivot, t.scale, t.rotation)),
+            body: self
+                .wrap_component::<Rigidbody>()
+                .map(|b| (b.position, b.velocity, b.bounds, b.colliding.clone())),
+            animator: self.wrap_component::<Animator>().map(|a| a.current_frame),
+            sprite: self.wrap_component::<Sprite>().map(|s| s.get_index()),
+            camera: self.wrap_component::<Camera>().map(|c| c.trauma),
+            text: self.wrap_component::<Text>().map(|t| {
+                (t.revealed, t.get_bounds(), t.get_line_count(), t.visible_glyphs())
+            }),
+            playing: self
+                .components
+                .iter()
+                .filter_map(|c| c.as_any().downcast_ref::<Sfx>())
+                .filter(|s| s.play_state)
+                .count(),
+        }
+    }
+
     pub fn update(&mut self, app: &mut App) {
         tick_component!(Rigidbody, Sprite, self, app);
         tick_component!(Rigidbody, Transform, self, app);
@@ -186,6 +254,134 @@ impl GameObject {
         tick_component!(Sprite, Animator, self, app);
     }
 
+    /// Copy with a fresh uuid that has not been loaded yet. Used to fill pools from a prefab.
+    pub fn instance(&self) -> GameObject {
+        let mut g = self.clone();
+        g.id = GameObjectId::new(self.id.name.clone());
+        g.init = false;
+        g.start = false;
+
+        let uuid = g.id.uuid.clone();
+        mut_all!(g, Transform, |c| c.parent = uuid.clone());
+        mut_all!(g, Rigidbody, |c| c.parent = uuid.clone());
+        mut_all!(g, Sprite, |c| c.parent = uuid.clone());
+        mut_all!(g, Text, |c| c.parent = uuid.clone());
+        mut_all!(g, Animator, |c| c.parent = uuid.clone());
+        mut_all!(g, Camera, |c| c.parent = uuid.clone());
+        mut_all!(g, Shape, |c| c.parent = uuid.clone());
+        mut_all!(g, Light2D, |c| c.parent = uuid.clone());
+        mut_all!(g, Sfx, |c| c.parent = uuid.clone());
+        mut_all!(g, Scene, |c| c.parent = uuid.clone());
+        mut_all!(g, Line, |c| c.parent = uuid.clone());
+        mut_all!(g, ComponentBehaviour, |c| c.parent = uuid.clone());
+        g
+    }
+
+    /// Resets component values to the ones on prefab, keeping id, parents and loaded buffers.
+    /// Behaviours start again once it is active. Scenes are left as is, behaviour data is reset
+    /// by Lilah.spawn in wren.
+    pub fn reset_from(&mut self, prefab: &GameObject) {
+        let uuid = self.id.uuid.clone();
+
+        for c in self.components.iter_mut() {
+            let c = c.as_any_mut();
+            if let Some(t) = c.downcast_mut::<Transform>() {
+                if let Some(p) = prefab.wrap_component::<Transform>() {
+                    *t = p.clone();
+                    t.parent = uuid.clone();
+                }
+            } else if let Some(body) = c.downcast_mut::<Rigidbody>() {
+                if let Some(p) = prefab.wrap_component::<Rigidbody>() {
+                    *body = p.clone();
+                    body.parent = uuid.clone();
+                }
+            } else if let Some(anim) = c.downcast_mut::<Animator>() {
+                if let Some(p) = prefab.wrap_component::<Animator>() {
+                    *anim = p.clone();
+                    anim.parent = uuid.clone();
+                }
+            } else if let Some(camera) = c.downcast_mut::<Camera>() {
+                if let Some(p) = prefab.wrap_component::<Camera>() {
+                    *camera = p.clone();
+                    camera.parent = uuid.clone();
+                }
+            } else if let Some(shape) = c.downcast_mut::<Shape>() {
+                if let Some(p) = prefab.wrap_component::<Shape>() {
+                    *shape = p.clone();
+                    shape.parent = uuid.clone();
+                    shape.sort_dirty = true;
+                }
+            } else if let Some(light) = c.downcast_mut::<Light2D>() {
+                if let Some(p) = prefab.wrap_component::<Light2D>() {
+                    *light = p.clone();
+                    light.parent = uuid.clone();
+                }
+            } else if let Some(spr) = c.downcast_mut::<Sprite>() {
+                if let Some(p) = prefab.wrap_component::<Sprite>() {
+                    spr.reset_from(p);
+                }
+            } else if let Some(text) = c.downcast_mut::<Text>() {
+                if let Some(p) = prefab.wrap_component::<Text>() {
+                    text.reset_from(p);
+                }
+            } else if let Some(line) = c.downcast_mut::<Line>() {
+                if let Some(p) = prefab.wrap_component::<Line>() {
+                    line.reset_from(p);
+                }
+            } else if let Some(sfx) = c.downcast_mut::<Sfx>() {
+                if let Some(p) = prefab.wrap_all::<Sfx>().into_iter().find(|p| p.name == sfx.name) {
+                    sfx.volume = p.volume;
+                    sfx.play_state = false;
+                }
+            }
+        }
+
+        if self.has::<Rigidbody>() && self.has::<Transform>() {
+            let transform = self.get::<Transform>().clone();
+            let body = self.get_mut::<Rigidbody>();
+
+            body.position = transform.position;
+            body.rotation = transform.rotation;
+        }
+
+        self.start = false;
+        self.restarting = true;
+    }
+
+    /// Adds tag if not already present.
+    pub fn add_tag(&mut self, tag: &str) {
+        if !self.has_tag(tag) {
+            self.tags.push(tag.to_string());
+        }
+    }
+
+    pub fn remove_tag(&mut self, tag: &str) {
+        self.tags.retain(|t| t != tag);
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
+
+    /// Checks if Component exist by its wren class name.
+    pub fn has_component_named(&self, name: &str) -> bool {
+        match name {
+            "Transform" => self.has::<Transform>(),
+            "Scene" => self.has::<Scene>(),
+            "Sprite" => self.has::<Sprite>(),
+            "Rigidbody" => self.has::<Rigidbody>(),
+            "Animator" => self.has::<Animator>(),
+            "Camera" => self.has::<Camera>(),
+            "Shape" => self.has::<Shape>(),
+            "Light2D" => self.has::<Light2D>(),
+            "ComponentBehaviour" => self.has::<ComponentBehaviour>(),
+            "Text" => self.has::<Text>(),
+            "Sfx" => self.has::<Sfx>(),
+            "Line" => self.has::<Line>(),
+            _ => false,
+        }
+    }
+
     /// Push component to GameObject with defaults
     pub fn push_component<T: 'static + Component + Default>(&mut self) {
         self.components.push(Box::new(T::default()));
@@ -232,6 +428,7 @@ impl GameObject {
 
     /// Gets Mutable Component but will panic if it does not exist.
     pub fn get_mut<T: 'static + Component>(&mut self) -> &mut T {
+        self.changed = true;
         let mut ret = None;
         for comp in &mut self.components {
             match comp.as_any_mut().downcast_mut::<T>() {
@@ -247,6 +444,7 @@ impl GameObject {
 
     /// Gets Mutable Component but wraps it in Option in the case that it does not exist.
     pub fn wrap_component_mut<T: 'static + Component>(&mut self) -> Option<&mut T> {
+        self.changed = true;
         for comp in &mut self.components {
             match comp.as_any_mut().downcast_mut::<T>() {
                 Some(val) => return Some(val),
@@ -269,6 +467,7 @@ impl GameObject {
     }
 
     pub fn wrap_all_mut<T: 'static + Component>(&mut self) -> Vec<&mut T> {
+        self.changed = true;
         let mut comps = vec![];
         for comp in &mut self.components {
             comps.push(match comp.as_any_mut().downcast_mut::<T>() {
@@ -290,6 +489,11 @@ impl GameObject {
         self
     }
 
+    pub fn with_tag(mut self, tag: &str) -> GameObject {
+        self.add_tag(tag);
+        self
+    }
+
     pub fn build(mut self) -> GameObject {
         if self.has::<Rigidbody>() && self.has::<Transform>() {
             let transform = self.get::<Transform>().clone();
@@ -314,6 +518,7 @@ impl GameObject {
     }
 
     pub fn wren_setter_name(&mut self, vm: &VM) {
+        self.changed = true;
         if let Some(new_name) = vm.get_slot_string(1) {
             self.id.name = new_name;
         } else {
@@ -329,11 +534,88 @@ impl GameObject {
         vm.set_slot_string(0, self.id.uuid.clone());
     }
 
+    pub fn wren_getter_changed(&self, vm: &VM) {
+        vm.set_slot_bool(0, self.changed);
+    }
+
+    pub fn wren_getter_active(&self, vm: &VM) {
+        vm.set_slot_bool(0, self.active);
+    }
+
+    pub fn wren_setter_active(&mut self, vm: &VM) {
+        self.changed = true;
+        if let Some(active) = vm.get_slot_bool(1) {
+            self.active = active;
+        } else {
+            LilahTypeError!(GameObject, 1, bool);
+        }
+    }
+
+    pub fn wren_reset_from(&mut self, vm: &VM) {
+        self.changed = true;
+        if let Some(prefab) = vm.get_slot_foreign::<GameObject>(1) {
+            let prefab = prefab.clone();
+            self.reset_from(&prefab);
+        } else {
+            LilahTypeError!(GameObject, 1, GameObject);
+        }
+    }
+
+    /// Copy with a fresh uuid, for pools to grow from their prefab.
+    pub fn wren_instance(&self, vm: &VM) {
+        self.instance().send_to_wren(0, vm);
+    }
+
+    pub fn wren_getter_tags(&self, vm: &VM) {
+        vm.set_slot_new_list(0);
+        for t in &self.tags {
+            vm.set_slot_string(1, t);
+            vm.insert_in_list(0, -1, 1);
+        }
+    }
+
+    pub fn wren_add_tag(&mut self, vm: &VM) {
+        self.changed = true;
+        if let Some(tag) = vm.get_slot_string(1) {
+            self.add_tag(&tag);
+        } else {
+            LilahTypeError!(GameObject, 1, String);
+        }
+    }
+
+    pub fn wren_remove_tag(&mut self, vm: &VM) {
+        self.changed = true;
+        if let Some(tag) = vm.get_slot_string(1) {
+            self.remove_tag(&tag);
+        } else {
+            LilahTypeError!(GameObject, 1, String);
+        }
+    }
+
+    pub fn wren_has_tag(&self, vm: &VM) {
+        if let Some(tag) = vm.get_slot_string(1) {
+            vm.set_slot_bool(0, self.has_tag(&tag));
+        } else {
+            LilahTypeError!(GameObject, 1, String);
+            vm.set_slot_bool(0, false);
+        }
+    }
+
+    pub fn wren_has_component(&self, vm: &VM) {
+        if let Some(name) = vm.get_slot_string(1) {
+            vm.set_slot_bool(0, self.has_component_named(&name));
+        } else {
+            LilahTypeError!(GameObject, 1, String);
+            vm.set_slot_bool(0, false);
+        }
+    }
+
     pub fn send_to_wren(&self, slot: usize, vm: &VM) {
         send_foreign!(vm, "game", "GameObject", self.clone() => slot);
     }
 
     pub fn wren_add_component(&mut self, vm: &VM) {
+        self.changed = true;
         if let Some(c) = vm.get_slot_foreign::<Box<dyn Component>>(1) {
             if let Some(_) = c.as_any().downcast_ref::<ComponentBehaviour>() {
                 self.has_behaviour = true;
@@ -360,6 +642,18 @@ impl GameObject {
             let mut new_c = c.clone();
             new_c.parent = self.id.uuid.clone();
             self.components.push(Box::new(new_c));
+        } else if let Some(c) = vm.get_slot_foreign::<Camera>(1) {
+            let mut new_c = c.clone();
+            new_c.parent = self.id.uuid.clone();
+            self.components.push(Box::new(new_c));
+        } else if let Some(c) = vm.get_slot_foreign::<Shape>(1) {
+            let mut new_c = c.clone();
+            new_c.parent = self.id.uuid.clone();
+            self.components.push(Box::new(new_c));
+        } else if let Some(c) = vm.get_slot_foreign::<Light2D>(1) {
+            let mut new_c = c.clone();
+            new_c.parent = self.id.uuid.clone();
+            self.components.push(Box::new(new_c));
         } else if let Some(c) = vm.get_slot_foreign::<Sfx>(1) {
             let mut new_c = c.clone();
             new_c.parent = self.id.uuid.clone();
@@ -418,6 +712,24 @@ impl GameObject {
                             return;
                         }
                     }
+                    "Camera" => {
+                        if let Some(b) = i.1.as_any().downcast_ref::<Camera>() {
+                            b.send_to_wren(0, vm);
+                            return;
+                        }
+                    }
+                    "Shape" => {
+                        if let Some(b) = i.1.as_any().downcast_ref::<Shape>() {
+                            b.send_to_wren(0, vm);
+                            return;
+                        }
+                    }
+                    "Light2D" => {
+                        if let Some(b) = i.1.as_any().downcast_ref::<Light2D>() {
+                            b.send_to_wren(0, vm);
+                            return;
+                        }
+                    }
                     "ComponentBehaviour" => {
                         if let Some(b) = i.1.as_any().downcast_ref::<ComponentBehaviour>() {
                             b.send_to_wren(0, vm);
@@ -436,6 +748,12 @@ impl GameObject {
                             return;
                         }
                     }
+                    "Text" => {
+                        if let Some(b) = i.1.as_any().downcast_ref::<Text>() {
+                            b.send_to_wren(0, vm);
+                            return;
+                        }
+                    }
                     _ => {}
                 }
             }
@@ -475,6 +793,21 @@ impl GameObject {
                 vm.set_slot_new_foreign_scratch("game", "Animator", b.clone(), 1, 2);
                 vm.insert_in_list(0, list_index, 1);
             }
+            if let Some(b) = i.1.as_any().downcast_ref::<Camera>() {
+                list_index += 1;
+                vm.set_slot_new_foreign_scratch("game", "Camera", b.clone(), 1, 2);
+                vm.insert_in_list(0, list_index, 1);
+            }
+            if let Some(b) = i.1.as_any().downcast_ref::<Shape>() {
+                list_index += 1;
+                vm.set_slot_new_foreign_scratch("game", "Shape", b.clone(), 1, 2);
+                vm.insert_in_list(0, list_index, 1);
+            }
+            if let Some(b) = i.1.as_any().downcast_ref::<Light2D>() {
+                list_index += 1;
+                vm.set_slot_new_foreign_scratch("game", "Light2D", b.clone(), 1, 2);
+                vm.insert_in_list(0, list_index, 1);
+            }
             if let Some(b) = i.1.as_any().downcast_ref::<ComponentBehaviour>() {
                 list_index += 1;
                 vm.set_slot_new_foreign_scratch("game", "ComponentBehaviour", b.clone(), 1, 2);
@@ -490,6 +823,11 @@ impl GameObject {
                 vm.set_slot_new_foreign_scratch("game", "Line", b.clone(), 1, 2);
                 vm.insert_in_list(0, list_index, 1);
             }
+            if let Some(b) = i.1.as_any().downcast_ref::<Text>() {
+                list_index += 1;
+                vm.set_slot_new_foreign_scratch("game", "Text", b.clone(), 1, 2);
+                vm.insert_in_list(0, list_index, 1);
+            }
         }
 
         if list_index == -1 {
@@ -499,6 +837,7 @@ impl GameObject {
 
     /*
     pub fn wren_set_component(&mut self, vm: &VM) {
+        self.changed = true;
         let mut finding = String::from("");
         if let Some(component_str) = vm.get_slot_string(1) {
             finding = component_str.clone();
@@ -9,6 +9,12 @@ pub mod time;
 pub mod math;
 #[macro_use] pub mod world;
 #[macro_use] pub mod renderer;
+pub mod atlas;
+pub mod capture;
+pub mod debug;
+pub mod lighting;
+pub mod text;
+pub mod tilemap;
 
 
 // #[cfg(test)]
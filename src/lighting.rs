@@ -0,0 +1,318 @@
+use std::ffi::CString;
+use std::ptr;
+
+use gl::types::*;
+
+use crate::components::Light2D;
+use crate::math::{self, Vec2};
+use crate::renderer::{Buffer, Color, RenderTarget, Shader, ShaderProgram, Vertex, VertexArray};
+use crate::set_attribute;
+
+/// Multiplies the world by a light map of ambient light plus every Light2D.
+/// Shadow coverage for one light at a time is accumulated in the map's alpha, then the light
+/// is added where the coverage leaves room, so soft edges from several samples blend linearly.
+pub struct LightMap {
+    /// Light everywhere before any Light2D, used when no Scene sets its own.
+    pub ambient: Color,
+    target: Option<RenderTarget>,
+    light: ShaderProgram,
+    shadow: ShaderProgram,
+    composite: ShaderProgram,
+    vertex_array: VertexArray,
+    vertex_buffer: Buffer,
+    capacity: usize,
+}
+
+impl LightMap {
+    /// Samples spread around the light for soft shadows.
+    const SOFT_SAMPLES: usize = 8;
+
+    const WORLD_VERT: &'static str = r#"
+    #version 330
+    layout(location = 0) in vec2 position;
+    out vec2 world;
+    uniform mat4 mvp;
+
+    void main() {
+        world = position;
+        gl_Position = mvp * vec4(position, 0.0, 1.0);
+    }
+    "#;
+
+    const LIGHT_FRAG: &'static str = r#"
+    #version 330
+    in vec2 world;
+    out vec4 FragColor;
+    uniform vec2 center;
+    uniform float radius;
+    uniform vec3 color;
+    uniform float falloff;
+
+    void main() {
+        float d = clamp(1.0 - length(world - center) / radius, 0.0, 1.0);
+        FragColor = vec4(color * pow(d, falloff), 1.0);
+    }
+    "#;
+
+    const SHADOW_FRAG: &'static str = r#"
+    #version 330
+    out vec4 FragColor;
+    uniform float amount;
+
+    void main() {
+        FragColor = vec4(0.0, 0.0, 0.0, amount);
+    }
+    "#;
+
+    const COMPOSITE_VERT: &'static str = r#"
+    #version 330
+    layout(location = 0) in vec2 position;
+    layout(location = 1) in vec2 vertexTexCoord;
+    out vec2 texCoord;
+
+    void main() {
+        texCoord = vertexTexCoord;
+        gl_Position = vec4(position, 0.0, 1.0);
+    }
+    "#;
+
+    const COMPOSITE_FRAG: &'static str = r#"
+    #version 330
+    in vec2 texCoord;
+    out vec4 FragColor;
+    uniform sampler2D texture0;
+
+    void main() {
+        FragColor = vec4(texture(texture0, texCoord).rgb, 1.0);
+    }
+    "#;
+
+    pub unsafe fn new() -> Self {
+        let program = |vert: &str, frag: &str| {
+            let vs = Shader::new(vert, gl::VERTEX_SHADER).unwrap();
+            let fs = Shader::new(frag, gl::FRAGMENT_SHADER).unwrap();
+            ShaderProgram::new(&[fs, vs]).unwrap()
+        };
+
+        let vertex_array = VertexArray::new();
+        vertex_array.bind();
+
+        let vertex_buffer = Buffer::new(gl::ARRAY_BUFFER);
+        vertex_buffer.bind();
+
+        set_attribute!(vertex_array, 0, Vertex::0, gl::FLOAT);
+        set_attribute!(vertex_array, 1, Vertex::1, gl::FLOAT);
+
+        Self {
+            ambient: Color::new(0.1, 0.1, 0.1, 1.0),
+            target: None,
+            light: program(Self::WORLD_VERT, Self::LIGHT_FRAG),
+            shadow: program(Self::WORLD_VERT, Self::SHADOW_FRAG),
+            composite: program(Self::COMPOSITE_VERT, Self::COMPOSITE_FRAG),
+            vertex_array,
+            vertex_buffer,
+            capacity: 0,
+        }
+    }
+
+    /// Lights the current gl viewport with the current view and projection.
+    /// Call once per camera after the world is drawn and the batch flushed.
+    /// lights are paired with their world position, occluders are convex world space outlines.
+    pub unsafe fn render(&mut self, lights: &[(Light2D, Vec2)], occluders: &[Vec<Vec2>], ambient: &Color) {
+        let mut framebuffer: GLint = 0;
+        gl::GetIntegerv(gl::FRAMEBUFFER_BINDING, &mut framebuffer);
+        let mut viewport: [GLint; 4] = [0; 4];
+        gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+        let mut clear_color: [GLfloat; 4] = [0.0; 4];
+        gl::GetFloatv(gl::COLOR_CLEAR_VALUE, clear_color.as_mut_ptr());
+        let [x, y, w, h] = viewport;
+
+        // the map mirrors the framebuffer's layout so each camera lights its own rect
+        let needed = ((x + w).max(1) as u32, (y + h).max(1) as u32);
+        let target = match self.target.take() {
+            Some(t) if t.size().0 >= needed.0 && t.size().1 >= needed.1 => t,
+            old => {
+                let old = old.map_or((0, 0), |t| t.size());
+                RenderTarget::new(old.0.max(needed.0), old.1.max(needed.1))
+            }
+        };
+        let size = target.size();
+
+        gl::BindFramebuffer(gl::FRAMEBUFFER, target.fbo);
+        gl::Viewport(x, y, w, h);
+        gl::Enable(gl::SCISSOR_TEST);
+        gl::Scissor(x, y, w, h);
+        gl::ClearColor(ambient.r, ambient.g, ambient.b, 0.0);
+        gl::Clear(gl::COLOR_BUFFER_BIT);
+
+        let view_projection = *math::PROJECTION_MATRIX * *math::VIEW_MATRIX;
+        let boxes: Vec<(Vec2, Vec2)> = occluders
+            .iter()
+            .map(|o| {
+                let points: Vec<[f32; 2]> = o.iter().map(|p| [p.x as f32, p.y as f32]).collect();
+                math::bounds_of(&points)
+            })
+            .collect();
+
+        for (light, position) in lights {
+            let reach = light.radius * 2.0 + light.softness;
+            let area = (
+                *position - Vec2::new(reach, reach),
+                *position + Vec2::new(reach, reach),
+            );
+
+            gl::ColorMask(gl::FALSE, gl::FALSE, gl::FALSE, gl::TRUE);
+            gl::ClearColor(0.0, 0.0, 0.0, 0.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT);
+
+            if light.shadows {
+                let samples = light.shadow_samples(*position, Self::SOFT_SAMPLES);
+                let mut vertices = vec![];
+                for (occluder, b) in occluders.iter().zip(&boxes) {
+                    if math::overlaps(*b, area) {
+                        for sample in &samples {
+                            Self::shadow_triangles(*sample, reach, occluder, &mut vertices);
+                        }
+                    }
+                }
+
+                if !vertices.is_empty() {
+                    gl::BlendFunc(gl::ONE, gl::ONE);
+                    self.shadow.apply();
+                    Self::set_mvp(self.shadow.id, &view_projection.to_cols_array());
+                    gl::Uniform1f(
+                        Self::location(self.shadow.id, "amount"),
+                        1.0 / samples.len() as f32,
+                    );
+                    self.draw(&vertices);
+                }
+            }
+
+            gl::ColorMask(gl::TRUE, gl::TRUE, gl::TRUE, gl::FALSE);
+            gl::BlendFunc(gl::ONE_MINUS_DST_ALPHA, gl::ONE);
+            self.light.apply();
+            Self::set_mvp(self.light.id, &view_projection.to_cols_array());
+            gl::Uniform2f(
+                Self::location(self.light.id, "center"),
+                position.x as f32,
+                position.y as f32,
+            );
+            gl::Uniform1f(Self::location(self.light.id, "radius"), light.radius as f32);
+            gl::Uniform1f(Self::location(self.light.id, "falloff"), light.falloff as f32);
+            let intensity = light.intensity as f32;
+            gl::Uniform3f(
+                Self::location(self.light.id, "color"),
+                light.color.r * intensity,
+                light.color.g * intensity,
+                light.color.b * intensity,
+            );
+
+            let (min, max) = (
+                *position - Vec2::new(light.radius, light.radius),
+                *position + Vec2::new(light.radius, light.radius),
+            );
+            self.draw(&Self::quad(
+                [min.x as f32, min.y as f32],
+                [max.x as f32, max.y as f32],
+                [0.0, 0.0],
+                [1.0, 1.0],
+            ));
+        }
+
+        gl::ColorMask(gl::TRUE, gl::TRUE, gl::TRUE, gl::TRUE);
+        gl::Disable(gl::SCISSOR_TEST);
+        gl::ClearColor(clear_color[0], clear_color[1], clear_color[2], clear_color[3]);
+
+        gl::BindFramebuffer(gl::FRAMEBUFFER, framebuffer as GLuint);
+        gl::Viewport(x, y, w, h);
+        gl::BlendFunc(gl::DST_COLOR, gl::ZERO);
+        self.composite.apply();
+        target.texture.activate(gl::TEXTURE0);
+        let (tw, th) = (size.0 as f32, size.1 as f32);
+        self.draw(&Self::quad(
+            [-1.0, -1.0],
+            [1.0, 1.0],
+            [x as f32 / tw, y as f32 / th],
+            [(x + w) as f32 / tw, (y + h) as f32 / th],
+        ));
+
+        gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+        self.target = Some(target);
+    }
+
+    /// Extrudes the edges of occluder facing away from light past reach.
+    /// Faces toward the light are left out so the occluder itself stays lit.
+    fn shadow_triangles(light: Vec2, reach: f64, occluder: &[Vec2], out: &mut Vec<Vertex>) {
+        if occluder.len() < 2 {
+            return;
+        }
+
+        let center = occluder.iter().fold(Vec2::ZERO, |a, p| a + *p) / occluder.len() as f64;
+        for i in 0..occluder.len() {
+            let (a, b) = (occluder[i], occluder[(i + 1) % occluder.len()]);
+            let mid = (a + b) / 2.0;
+            let mut normal = Vec2::new(b.y - a.y, a.x - b.x);
+            if Vec2::dot(normal, mid - center) < 0.0 {
+                normal = normal * -1.0;
+            }
+            if Vec2::dot(normal, light - mid) >= 0.0 {
+                continue;
+            }
+
+            let far_a = a + (a - light).normalized() * reach;
+            let far_b = b + (b - light).normalized() * reach;
+            for p in [a, b, far_b, far_b, far_a, a] {
+                out.push(Vertex([p.x as f32, p.y as f32], [0.0, 0.0]));
+            }
+        }
+    }
+
+    fn quad(min: [f32; 2], max: [f32; 2], uv_min: [f32; 2], uv_max: [f32; 2]) -> [Vertex; 6] {
+        [
+            Vertex([min[0], min[1]], [uv_min[0], uv_min[1]]),
+            Vertex([max[0], min[1]], [uv_max[0], uv_min[1]]),
+            Vertex([max[0], max[1]], [uv_max[0], uv_max[1]]),
+            Vertex([max[0], max[1]], [uv_max[0], uv_max[1]]),
+            Vertex([min[0], max[1]], [uv_min[0], uv_max[1]]),
+            Vertex([min[0], min[1]], [uv_min[0], uv_min[1]]),
+        ]
+    }
+
+    unsafe fn location(program: GLuint, name: &str) -> GLint {
+        let name = CString::new(name).unwrap();
+        gl::GetUniformLocation(program, name.as_ptr())
+    }
+
+    unsafe fn set_mvp(program: GLuint, mvp: &[f32; 16]) {
+        gl::UniformMatrix4fv(
+            Self::location(program, "mvp"),
+            1,
+            gl::FALSE as GLboolean,
+            &mvp[0],
+        );
+    }
+
+    unsafe fn draw(&mut self, vertices: &[Vertex]) {
+        self.vertex_array.bind();
+        self.vertex_buffer.bind();
+
+        let (_, data_bytes, _) = vertices.align_to::<u8>();
+        if vertices.len() > self.capacity {
+            // grows only, later lights reuse the storage
+            self.capacity = vertices.len().next_power_of_two();
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                (self.capacity * std::mem::size_of::<Vertex>()) as GLsizeiptr,
+                ptr::null(),
+                gl::DYNAMIC_DRAW,
+            );
+        }
+        gl::BufferSubData(
+            gl::ARRAY_BUFFER,
+            0,
+            data_bytes.len() as GLsizeiptr,
+            data_bytes.as_ptr() as *const _,
+        );
+        gl::DrawArrays(gl::TRIANGLES, 0, vertices.len() as GLsizei);
+    }
+}
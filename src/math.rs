@@ -17,6 +17,8 @@ use std::{
 lazy_mut! {
     pub static mut VIEW_MATRIX: Mat4 = Mat4::IDENTITY;
     pub static mut PROJECTION_MATRIX: Mat4 = Mat4::IDENTITY;
+    /// Window rect the world is drawn into as x, y from the top left, width, height. Kept in sync by App::on_resize.
+    pub static mut VIEWPORT: [f32; 4] = [0.0, 0.0, 1.0, 1.0];
 }
 
 pub fn remap(value: f32, from1: f32, to1: f32, from2: f32, to2: f32) -> f32 {
@@ -29,6 +31,106 @@ where
 {
     from + (to - from) * t
 }
+
+/// Min and max corners around points.
+pub fn bounds_of(points: &[[f32; 2]]) -> (Vec2, Vec2) {
+    let mut min = Vec2::new(f64::MAX, f64::MAX);
+    let mut max = Vec2::new(f64::MIN, f64::MIN);
+    for p in points {
+        min = Vec2::new(min.x.min(p[0] as f64), min.y.min(p[1] as f64));
+        max = Vec2::new(max.x.max(p[0] as f64), max.y.max(p[1] as f64));
+    }
+    (min, max)
+}
+
+/// Smallest min, max box holding both.
+pub fn union(a: (Vec2, Vec2), b: (Vec2, Vec2)) -> (Vec2, Vec2) {
+    (
+        Vec2::new(a.0.x.min(b.0.x), a.0.y.min(b.0.y)),
+        Vec2::new(a.1.x.max(b.1.x), a.1.y.max(b.1.y)),
+    )
+}
+
+/// Whether two min, max boxes overlap. Touching counts.
+pub fn overlaps(a: (Vec2, Vec2), b: (Vec2, Vec2)) -> bool {
+    a.0.x <= b.1.x && b.0.x <= a.1.x && a.0.y <= b.1.y && b.0.y <= a.1.y
+}
+
+/// World position the current view matrix translates to the origin, the camera position with shake.
+pub fn view_origin() -> Vec2 {
+    let view = unsafe { *crate::math::VIEW_MATRIX };
+    let p = view.inverse().transform_point3(Vec3::ZERO);
+    Vec2::new(p.x as f64, p.y as f64)
+}
+
+/// Most copies drawn along one repeated axis, in case something tiny repeats across a huge view.
+const MAX_REPEATS: f64 = 256.0;
+
+/// Offsets to draw something with bounds at a parallax factor, each one inside view.
+/// A factor of 1 moves with the world and 0 stays fixed to the camera. Repeated axes add a copy
+/// every bounds size so the view is covered. Empty when nothing would be seen.
+pub fn parallax_offsets(
+    bounds: (Vec2, Vec2),
+    factor: Vec2,
+    repeat: (bool, bool),
+    view: (Vec2, Vec2),
+) -> Vec<Vec2> {
+    parallax_offsets_at(view_origin(), bounds, factor, repeat, view)
+}
+
+/// parallax_offsets with the camera at origin instead of the current view matrix.
+fn parallax_offsets_at(
+    origin: Vec2,
+    bounds: (Vec2, Vec2),
+    factor: Vec2,
+    repeat: (bool, bool),
+    view: (Vec2, Vec2),
+) -> Vec<Vec2> {
+    let shift = Vec2::new(origin.x * (1.0 - factor.x), origin.y * (1.0 - factor.y));
+    let (min, max) = (bounds.0 + shift, bounds.1 + shift);
+
+    let copies = |repeat: bool, min: f64, max: f64, view_min: f64, view_max: f64| -> Vec<f64> {
+        let size = max - min;
+        if !repeat || size <= 0.0 {
+            return vec![0.0];
+        }
+        let first = ((view_min - max) / size).ceil();
+        let last = ((view_max - min) / size).floor().min(first + MAX_REPEATS);
+        let mut k = first;
+        let mut out = vec![];
+        while k <= last {
+            out.push(k * size);
+            k += 1.0;
+        }
+        out
+    };
+
+    let xs = copies(repeat.0, min.x, max.x, view.0.x, view.1.x);
+    let ys = copies(repeat.1, min.y, max.y, view.0.y, view.1.y);
+    let mut offsets = vec![];
+    for y in &ys {
+        for x in &xs {
+            let offset = shift + Vec2::new(*x, *y);
+            if overlaps((bounds.0 + offset, bounds.1 + offset), view) {
+                offsets.push(offset);
+            }
+        }
+    }
+    offsets
+}
+
+/// World space box seen through the current view and projection.
+pub fn view_bounds() -> (Vec2, Vec2) {
+    let view = unsafe { *crate::math::VIEW_MATRIX };
+    let projection = unsafe { *crate::math::PROJECTION_MATRIX };
+    let inverse = (projection * view).inverse();
+
+    let corners = [[-1.0, -1.0], [1.0, -1.0], [1.0, 1.0], [-1.0, 1.0]].map(|c| {
+        let p = inverse.transform_point3(Vec3::new(c[0], c[1], 0.0));
+        [p.x, p.y]
+    });
+    bounds_of(&corners)
+}
 /// Vector for 2d translations etc.
 /// # Examples
 /// ## Translations
@@ -199,6 +301,34 @@ impl Vec2 {
         }
     }
 
+    /// Window pixels (top left origin) to world space, through the viewport so letterboxing and scaling are accounted for.
+    pub fn screen_to_world(point: Vec2) -> Vec2 {
+        let [x, y, w, h] = unsafe { *crate::math::VIEWPORT };
+        let view = unsafe { *crate::math::VIEW_MATRIX };
+        let projection = unsafe { *crate::math::PROJECTION_MATRIX };
+
+        let ndc = Vec3::new(
+            (point.x as f32 - x) / w * 2.0 - 1.0,
+            1.0 - (point.y as f32 - y) / h * 2.0,
+            0.0,
+        );
+        let world = (projection * view).inverse().transform_point3(ndc);
+        Vec2::new(world.x as f64, world.y as f64)
+    }
+
+    /// World space to window pixels (top left origin).
+    pub fn world_to_screen(point: Vec2) -> Vec2 {
+        let [x, y, w, h] = unsafe { *crate::math::VIEWPORT };
+        let view = unsafe { *crate::math::VIEW_MATRIX };
+        let projection = unsafe { *crate::math::PROJECTION_MATRIX };
+
+        let ndc = (projection * view).transform_point3(Vec3::new(point.x as f32, point.y as f32, 0.0));
+        Vec2::new(
+            (x + (ndc.x + 1.0) / 2.0 * w) as f64,
+            (y + (1.0 - ndc.y) / 2.0 * h) as f64,
+        )
+    }
+
     //for wren
     fn wren_x(&self, vm: &VM) {
         vm.set_slot_double(0, self.x);
@@ -313,21 +443,7 @@ impl Vec2 {
 
     fn wren_screen_to_world(vm: &VM) {
         if let Some(coord) = vm.get_slot_foreign::<Vec2>(1) {
-            let model = Mat4::IDENTITY
-                * Mat4::from_translation(Vec3::new(coord.x as f32, coord.y as f32, 0.0));
-
-            let view = unsafe { *crate::math::VIEW_MATRIX };
-            let projection = unsafe { *crate::math::PROJECTION_MATRIX };
-
-            let mvp = (model * view.inverse() * projection);
-
-            let new_point = mvp.to_scale_rotation_translation().2;
-            let _ = vm.set_slot_new_foreign(
-                "math",
-                "Vec2",
-                Vec2::new(new_point.x as f64, new_point.y as f64),
-                0,
-            );
+            let _ = vm.set_slot_new_foreign("math", "Vec2", Vec2::screen_to_world(*coord), 0);
         } else {
             LilahTypeError!(Vec2, 1, Vec2);
         }
@@ -335,21 +451,7 @@ impl Vec2 {
 
     fn wren_world_to_screen(vm: &VM) {
         if let Some(coord) = vm.get_slot_foreign::<Vec2>(1) {
-            let model = Mat4::IDENTITY
-                * Mat4::from_translation(Vec3::new(coord.x as f32, coord.y as f32, 0.0));
-
-            let view = unsafe { *crate::math::VIEW_MATRIX };
-            let projection = unsafe { *crate::math::PROJECTION_MATRIX };
-
-            let mvp = (projection * view.inverse() * model);
-
-            let new_point = mvp.to_scale_rotation_translation().2;
-            let point_to_screen = Vec2::new(
-                new_point.x as f64,
-                new_point.y as f64, //remap(new_point.x, -1.0, 0.0, 1.0, 800.0) as f64/2.0,
-                                    //remap(new_point.y, -1.0, 0.0, 1.0, 600.0) as f64/2.0
-            );
-            let _ = vm.set_slot_new_foreign("math", "Vec2", point_to_screen, 0);
+            let _ = vm.set_slot_new_foreign("math", "Vec2", Vec2::world_to_screen(*coord), 0);
         } else {
             LilahTypeError!(Vec2, 1, Vec2);
         }
@@ -1049,3 +1151,50 @@ create_module! (
 pub fn publish_modules(lib: &mut ModuleLibrary) {
     math::publish_module(lib);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TILE: (Vec2, Vec2) = (Vec2::ZERO, Vec2 { x: 10.0, y: 10.0 });
+
+    fn boxed(min: (f64, f64), max: (f64, f64)) -> (Vec2, Vec2) {
+        (Vec2::new(min.0, min.1), Vec2::new(max.0, max.1))
+    }
+
+    #[test]
+    fn parallax_factor_moves_with_the_camera() {
+        let origin = Vec2::new(100.0, 50.0);
+        let view = boxed((50.0, 0.0), (150.0, 100.0));
+
+        // 1 stays in the world, 0 follows the camera, 0.5 goes half as far
+        assert!(parallax_offsets_at(origin, TILE, Vec2::ONE, (false, false), view).is_empty());
+        assert_eq!(
+            parallax_offsets_at(origin, TILE, Vec2::ZERO, (false, false), view),
+            vec![Vec2::new(100.0, 50.0)]
+        );
+        assert_eq!(
+            parallax_offsets_at(origin, TILE, Vec2::new(0.5, 0.5), (false, false), view),
+            vec![Vec2::new(50.0, 25.0)]
+        );
+    }
+
+    #[test]
+    fn parallax_repeats_cover_the_view() {
+        let view = boxed((-15.0, 0.0), (25.0, 10.0));
+        let offsets = parallax_offsets_at(Vec2::ZERO, TILE, Vec2::ONE, (true, false), view);
+
+        let xs: Vec<f64> = offsets.iter().map(|o| o.x).collect();
+        assert_eq!(xs, vec![-20.0, -10.0, 0.0, 10.0, 20.0]);
+        assert!(offsets.iter().all(|o| o.y == 0.0));
+    }
+
+    #[test]
+    fn parallax_repeats_are_capped() {
+        let tiny = boxed((0.0, 0.0), (1.0, 1.0));
+        let view = boxed((0.0, 0.0), (10_000.0, 1.0));
+        let offsets = parallax_offsets_at(Vec2::ZERO, tiny, Vec2::ONE, (true, false), view);
+
+        assert_eq!(offsets.len(), MAX_REPEATS as usize + 1);
+    }
+}
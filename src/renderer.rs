@@ -1,4 +1,4 @@
-use std::{path::Path, ffi::{CString, NulError}, ptr, string::FromUtf8Error};
+use std::{collections::HashMap, path::Path, ffi::{CString, NulError}, ptr, string::FromUtf8Error};
 
 use gl::types::*;
 use image::{ImageError, EncodableLayout, Rgba};
@@ -90,6 +90,60 @@ impl LilahTexture {
         Ok(())
     }
 
+    /// Allocates a transparent rgba texture of the given size.
+    pub unsafe fn allocate(&mut self, width: u32, height: u32) {
+        let pixels = vec![0u8; (width * height * 4) as usize];
+
+        self.bind();
+        gl::TexImage2D(
+            gl::TEXTURE_2D,
+            0,
+            gl::RGBA as i32,
+            width as i32,
+            height as i32,
+            0,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            pixels.as_ptr() as *const _,
+        );
+        self.size = Vec2::new(width as f64, height as f64);
+    }
+
+    /// Writes img into the texture with its top left corner at x, y.
+    pub unsafe fn write_region(&self, x: u32, y: u32, img: &image::ImageBuffer<Rgba<u8>, Vec<u8>>) {
+        self.bind();
+        gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+        gl::TexSubImage2D(
+            gl::TEXTURE_2D,
+            0,
+            x as i32,
+            y as i32,
+            img.width() as i32,
+            img.height() as i32,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            img.as_raw().as_ptr() as *const _,
+        );
+    }
+
+    /// Reads the texture back from the gpu.
+    pub unsafe fn read_pixels(&self) -> image::ImageBuffer<Rgba<u8>, Vec<u8>> {
+        let (width, height) = (self.size.x as u32, self.size.y as u32);
+        let mut pixels = vec![0u8; (width * height * 4) as usize];
+
+        self.bind();
+        gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+        gl::GetTexImage(
+            gl::TEXTURE_2D,
+            0,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            pixels.as_mut_ptr() as *mut _,
+        );
+
+        image::ImageBuffer::from_raw(width, height, pixels).unwrap()
+    }
+
     pub unsafe fn set_wrapping(&self, mode: GLuint) {
         self.bind();
         gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, mode as GLint);
@@ -147,7 +201,7 @@ impl VertexArray {
         gl::BindVertexArray(self.id);
     }
 
-    fn delete(&mut self) {
+    pub(crate) fn delete(&mut self) {
         unsafe {
             gl::DeleteVertexArrays(1, [self.id].as_ptr());
         }
@@ -321,7 +375,7 @@ impl Buffer {
         gl::BindBuffer(self.target, self.id);
     }
 
-    fn delete(&mut self) {
+    pub(crate) fn delete(&mut self) {
         unsafe {
             gl::DeleteBuffers(1, [self.id].as_ptr());
         }
@@ -330,10 +384,15 @@ impl Buffer {
 
 pub type Pos = [f32; 2];
 pub type TextureCoords = [f32; 2];
+pub type Tint = [f32; 4];
 
 #[repr(C, packed)]
 pub struct Vertex(pub Pos, pub TextureCoords);
 
+/// Vertex used by Batch. Positions are in world space, the view projection is applied per flush.
+#[repr(C, packed)]
+pub struct BatchVertex(pub Pos, pub TextureCoords, pub Tint);
+
 #[derive(Clone)]
 pub struct Color {
     pub r: f32,
@@ -355,3 +414,734 @@ impl Color {
     }
 }
 
+
+/// How a draw is combined with what is already on screen.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BlendMode {
+    /// Straight alpha, the default.
+    #[default]
+    Alpha,
+    /// Adds color weighted by alpha, for glows and particles.
+    Additive,
+    /// Multiplies what is underneath. Transparent texels should be black.
+    Multiply,
+    /// Inverse of multiply, brightens what is underneath. Transparent texels should be black.
+    Screen,
+    /// Alpha for textures whose color is already multiplied by alpha.
+    Premultiplied,
+}
+
+impl BlendMode {
+    pub fn from_name(name: &str) -> Option<BlendMode> {
+        match name {
+            "alpha" => Some(BlendMode::Alpha),
+            "additive" => Some(BlendMode::Additive),
+            "multiply" => Some(BlendMode::Multiply),
+            "screen" => Some(BlendMode::Screen),
+            "premultiplied" => Some(BlendMode::Premultiplied),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BlendMode::Alpha => "alpha",
+            BlendMode::Additive => "additive",
+            BlendMode::Multiply => "multiply",
+            BlendMode::Screen => "screen",
+            BlendMode::Premultiplied => "premultiplied",
+        }
+    }
+
+    /// Sets the gl blend function. Anything drawn outside the batch expects Alpha back afterwards.
+    pub unsafe fn apply(&self) {
+        match self {
+            BlendMode::Alpha => gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA),
+            BlendMode::Additive => gl::BlendFunc(gl::SRC_ALPHA, gl::ONE),
+            BlendMode::Multiply => gl::BlendFunc(gl::DST_COLOR, gl::ONE_MINUS_SRC_ALPHA),
+            BlendMode::Screen => gl::BlendFunc(gl::ONE_MINUS_DST_COLOR, gl::ONE),
+            BlendMode::Premultiplied => gl::BlendFunc(gl::ONE, gl::ONE_MINUS_SRC_ALPHA),
+        }
+    }
+}
+
+/// Value for a material uniform. Texture holds a texture id from WorldState::textures.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Uniform {
+    Float(f32),
+    Vec2([f32; 2]),
+    Vec4([f32; 4]),
+    Texture(String),
+}
+
+/// Uniform with its texture resolved to a gl id, ready for Batch.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum BoundUniform {
+    Float(f32),
+    Vec2([f32; 2]),
+    Vec4([f32; 4]),
+    Texture(GLuint),
+}
+
+/// Named uniform values, in the order they were first set.
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct Uniforms(Vec<(String, Uniform)>);
+
+impl Uniforms {
+    pub fn set(&mut self, name: &str, value: Uniform) {
+        match self.0.iter_mut().find(|u| u.0 == name) {
+            Some(u) => u.1 = value,
+            None => self.0.push((name.to_string(), value)),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Uniform> {
+        self.0.iter().find(|u| u.0 == name).map(|u| &u.1)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &(String, Uniform)> {
+        self.0.iter()
+    }
+}
+
+/// A shader program plus default uniform values. Components name a material and can override its uniforms.
+/// Sprite and Text materials are linked with App::DEFAULT_VERT or a vertex shader with the same inputs,
+/// Line materials with App::DEBUG_VERT.
+pub struct Material {
+    pub program: ShaderProgram,
+    pub uniforms: Uniforms,
+    /// Used by components that do not set their own blend.
+    pub blend: BlendMode,
+}
+
+impl Material {
+    pub unsafe fn new(vert: &str, frag: &str) -> Result<Self, ShaderError> {
+        let vs = Shader::new(vert, gl::VERTEX_SHADER)?;
+        let fs = Shader::new(frag, gl::FRAGMENT_SHADER)?;
+
+        Ok(Self {
+            program: ShaderProgram::new(&[fs, vs])?,
+            uniforms: Uniforms::default(),
+            blend: BlendMode::Alpha,
+        })
+    }
+
+    /// Material defaults overridden by overrides. Textures that are not loaded are skipped.
+    pub fn bind(&self, overrides: &Uniforms, textures: &HashMap<String, LilahTexture>) -> Vec<(String, BoundUniform)> {
+        let mut merged = self.uniforms.clone();
+        for (name, value) in overrides.iter() {
+            merged.set(name, value.clone());
+        }
+
+        merged
+            .iter()
+            .filter_map(|(name, value)| {
+                let bound = match value {
+                    Uniform::Float(v) => BoundUniform::Float(*v),
+                    Uniform::Vec2(v) => BoundUniform::Vec2(*v),
+                    Uniform::Vec4(v) => BoundUniform::Vec4(*v),
+                    Uniform::Texture(id) => BoundUniform::Texture(textures.get(id)?.id),
+                };
+                Some((name.clone(), bound))
+            })
+            .collect()
+    }
+}
+
+/// Program and uniforms to draw with. Falls back to default when material is not set or not loaded.
+pub fn select_material<'p>(
+    materials: &'p HashMap<String, Material>,
+    default: &'p ShaderProgram,
+    material: Option<&String>,
+    overrides: &Uniforms,
+    textures: &HashMap<String, LilahTexture>,
+) -> (&'p ShaderProgram, Vec<(String, BoundUniform)>) {
+    match material.and_then(|m| materials.get(m)) {
+        Some(m) => (&m.program, m.bind(overrides, textures)),
+        None => (default, vec![]),
+    }
+}
+
+/// Blend for a component, its own if set, otherwise its material's.
+pub fn select_blend(
+    materials: &HashMap<String, Material>,
+    material: Option<&String>,
+    blend: Option<BlendMode>,
+) -> BlendMode {
+    blend.unwrap_or_else(|| {
+        material
+            .and_then(|m| materials.get(m))
+            .map_or(BlendMode::Alpha, |m| m.blend)
+    })
+}
+
+/// Uploads uniforms to the program in use. Textures take units from 1 up, unit 0 is texture0.
+pub unsafe fn apply_uniforms(program: GLuint, uniforms: &[(String, BoundUniform)]) {
+    let mut unit = 1;
+    for (name, value) in uniforms {
+        let name = CString::new(name.as_str()).unwrap();
+        let location = gl::GetUniformLocation(program, name.as_ptr());
+        match value {
+            BoundUniform::Float(v) => gl::Uniform1f(location, *v),
+            BoundUniform::Vec2(v) => gl::Uniform2f(location, v[0], v[1]),
+            BoundUniform::Vec4(v) => gl::Uniform4f(location, v[0], v[1], v[2], v[3]),
+            BoundUniform::Texture(id) => {
+                gl::ActiveTexture(gl::TEXTURE0 + unit);
+                gl::BindTexture(gl::TEXTURE_2D, *id);
+                gl::Uniform1i(location, unit as GLint);
+                unit += 1;
+            }
+        }
+    }
+    gl::ActiveTexture(gl::TEXTURE0);
+}
+
+/// Per frame counters from Batch.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct RenderStats {
+    pub draw_calls: u32,
+    pub quads: u32,
+    /// Sprites, text, lines and tiles inside a camera's view.
+    pub drawn: u32,
+    /// Sprites, text, lines and tiles skipped for being outside a camera's view.
+    pub culled: u32,
+}
+
+/// Accumulates textured quads in submission order into one dynamic vertex buffer.
+/// Flushes when the texture, shader or material uniforms change or the buffer is full.
+pub struct Batch {
+    vertex_array: VertexArray,
+    vertex_buffer: Buffer,
+    _index_buffer: Buffer,
+    vertices: Vec<BatchVertex>,
+    texture: GLuint,
+    program: GLuint,
+    uniforms: Vec<(String, BoundUniform)>,
+    blend: BlendMode,
+    stats: RenderStats,
+    last_stats: RenderStats,
+}
+
+impl Batch {
+    pub const MAX_QUADS: usize = 4096;
+
+    /// Attributes are bound to the fixed locations used by App::DEFAULT_VERT.
+    pub unsafe fn new() -> Self {
+        let vertex_array = VertexArray::new();
+        vertex_array.bind();
+
+        let vertex_buffer = Buffer::new(gl::ARRAY_BUFFER);
+        vertex_buffer.bind();
+        gl::BufferData(
+            gl::ARRAY_BUFFER,
+            (Self::MAX_QUADS * 4 * std::mem::size_of::<BatchVertex>()) as GLsizeiptr,
+            ptr::null(),
+            gl::DYNAMIC_DRAW,
+        );
+
+        let mut indices: Vec<u32> = Vec::with_capacity(Self::MAX_QUADS * 6);
+        for i in 0..Self::MAX_QUADS as u32 {
+            let o = i * 4;
+            indices.extend_from_slice(&[o, o + 1, o + 2, o + 2, o + 3, o]);
+        }
+        let index_buffer = Buffer::new(gl::ELEMENT_ARRAY_BUFFER);
+        index_buffer.set_data(&indices, gl::STATIC_DRAW);
+
+        set_attribute!(vertex_array, 0, BatchVertex::0, gl::FLOAT);
+        set_attribute!(vertex_array, 1, BatchVertex::1, gl::FLOAT);
+        set_attribute!(vertex_array, 2, BatchVertex::2, gl::FLOAT);
+
+        Self {
+            vertex_array,
+            vertex_buffer,
+            _index_buffer: index_buffer,
+            vertices: Vec::with_capacity(Self::MAX_QUADS * 4),
+            texture: 0,
+            program: 0,
+            uniforms: vec![],
+            blend: BlendMode::Alpha,
+            stats: RenderStats::default(),
+            last_stats: RenderStats::default(),
+        }
+    }
+
+    /// Queues a quad. corners and uvs go counter clockwise from the bottom left.
+    /// uniforms are the material uniforms from select_material, empty for the built in programs.
+    pub unsafe fn push_quad(
+        &mut self,
+        program: &ShaderProgram,
+        texture: &LilahTexture,
+        uniforms: &[(String, BoundUniform)],
+        corners: [Pos; 4],
+        uvs: [TextureCoords; 4],
+        tint: &Color,
+    ) {
+        if texture.id != self.texture
+            || program.id != self.program
+            || uniforms != self.uniforms.as_slice()
+            || self.vertices.len() >= Self::MAX_QUADS * 4
+        {
+            self.flush();
+            self.texture = texture.id;
+            self.program = program.id;
+            self.uniforms = uniforms.to_vec();
+        }
+
+        let tint = [tint.r, tint.g, tint.b, tint.a];
+        for (corner, uv) in corners.into_iter().zip(uvs) {
+            self.vertices.push(BatchVertex(corner, uv, tint));
+        }
+    }
+
+    /// Blend for quads pushed from now on. Every drawer sets its own, so nothing inherits the last one.
+    pub unsafe fn set_blend(&mut self, blend: BlendMode) {
+        if blend != self.blend {
+            self.flush();
+            self.blend = blend;
+        }
+    }
+
+    /// Draws everything queued so far. Call before drawing anything that does not go through the batch.
+    pub unsafe fn flush(&mut self) {
+        if self.vertices.is_empty() {
+            return;
+        }
+
+        let view_projection = *crate::math::PROJECTION_MATRIX * *crate::math::VIEW_MATRIX;
+
+        gl::UseProgram(self.program);
+        let mvp = CString::new("mvp").unwrap();
+        gl::UniformMatrix4fv(
+            gl::GetUniformLocation(self.program, mvp.as_ptr()),
+            1,
+            gl::FALSE as GLboolean,
+            &view_projection.to_cols_array()[0],
+        );
+        apply_uniforms(self.program, &self.uniforms);
+
+        gl::ActiveTexture(gl::TEXTURE0);
+        gl::BindTexture(gl::TEXTURE_2D, self.texture);
+
+        self.vertex_array.bind();
+        self.vertex_buffer.bind();
+        let (_, data_bytes, _) = self.vertices.align_to::<u8>();
+        gl::BufferSubData(
+            gl::ARRAY_BUFFER,
+            0,
+            data_bytes.len() as GLsizeiptr,
+            data_bytes.as_ptr() as *const _,
+        );
+
+        let quads = self.vertices.len() / 4;
+        self.blend.apply();
+        gl::DrawElements(
+            gl::TRIANGLES,
+            (quads * 6) as GLsizei,
+            gl::UNSIGNED_INT,
+            ptr::null(),
+        );
+        if self.blend != BlendMode::Alpha {
+            BlendMode::Alpha.apply();
+        }
+
+        self.stats.draw_calls += 1;
+        self.stats.quads += quads as u32;
+        self.vertices.clear();
+    }
+
+    /// Flushes and rolls the counters over. Call once after the frame is drawn.
+    pub unsafe fn end_frame(&mut self) {
+        self.flush();
+        self.last_stats = self.stats;
+        self.stats = RenderStats::default();
+        self.texture = 0;
+        self.program = 0;
+        self.uniforms.clear();
+        self.blend = BlendMode::Alpha;
+    }
+
+    /// Adds to this frame's drawn and culled counters.
+    pub fn count(&mut self, drawn: u32, culled: u32) {
+        self.stats.drawn += drawn;
+        self.stats.culled += culled;
+    }
+
+    /// Adds a draw made outside the batch, like a tile chunk, to this frame's counters.
+    pub fn count_draw(&mut self, quads: u32) {
+        self.stats.draw_calls += 1;
+        self.stats.quads += quads;
+    }
+
+    /// Counters from the last finished frame.
+    pub fn stats(&self) -> RenderStats {
+        self.last_stats
+    }
+}
+
+/// Framebuffer that draws into its own texture.
+pub struct RenderTarget {
+    pub fbo: GLuint,
+    pub texture: LilahTexture,
+}
+
+impl Drop for RenderTarget {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteFramebuffers(1, [self.fbo].as_ptr());
+        }
+    }
+}
+
+impl RenderTarget {
+    pub unsafe fn new(width: u32, height: u32) -> Self {
+        let mut texture = LilahTexture::new();
+        texture.set_wrapping(gl::CLAMP_TO_EDGE);
+        texture.set_filtering(gl::NEAREST);
+        texture.allocate(width, height);
+
+        let mut fbo: GLuint = 0;
+        gl::GenFramebuffers(1, &mut fbo);
+        gl::BindFramebuffer(gl::FRAMEBUFFER, fbo);
+        gl::FramebufferTexture2D(
+            gl::FRAMEBUFFER,
+            gl::COLOR_ATTACHMENT0,
+            gl::TEXTURE_2D,
+            texture.id,
+            0,
+        );
+        gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+
+        Self { fbo, texture }
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.texture.size.x as u32, self.texture.size.y as u32)
+    }
+
+    /// Draws go to this target until another target or the window is bound.
+    pub unsafe fn bind(&self) {
+        gl::BindFramebuffer(gl::FRAMEBUFFER, self.fbo);
+        gl::Viewport(0, 0, self.texture.size.x as i32, self.texture.size.y as i32);
+    }
+}
+
+/// Full screen shader pass. material names a material linked with App::POST_VERT.
+pub struct PostPass {
+    pub name: String,
+    pub material: String,
+    pub enabled: bool,
+    pub uniforms: Uniforms,
+}
+
+/// Named band of draw order. Layers draw in App::sort_layers order, sort only orders within one.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SortLayer {
+    pub name: String,
+    /// Orders by world y instead of sort, higher y first so things lower down overlap them.
+    pub y_sort: bool,
+}
+
+impl SortLayer {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            y_sort: false,
+        }
+    }
+
+    /// background, world, foreground and ui.
+    pub fn defaults() -> Vec<SortLayer> {
+        ["background", "world", "foreground", "ui"]
+            .iter()
+            .map(|n| SortLayer::new(n))
+            .collect()
+    }
+}
+
+/// Position in draw order, compared field by field.
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub struct DrawKey {
+    /// Index into App::sort_layers.
+    pub layer: usize,
+    /// Negated world y in y-sorted layers, 0 otherwise.
+    pub depth: f64,
+    pub sort: u32,
+}
+
+/// How the world is fit into the window.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ScalingPolicy {
+    /// One world unit per window pixel, resizing shows more or less of the world.
+    Window,
+    /// Fixed virtual resolution scaled to fit, bars fill the leftover space.
+    Letterbox(Vec2),
+    /// Virtual resolution scaled to fit, the view grows along the leftover axis instead of showing bars.
+    Expand(Vec2),
+    /// Virtual resolution drawn offscreen and scaled by whole numbers only, for pixel art.
+    IntegerScale(Vec2),
+}
+
+/// Where and at what size the world is drawn for a window size.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ScreenLayout {
+    /// Pixel size of the image the world is drawn into.
+    pub render_size: (u32, u32),
+    /// Rect of the window the image covers as x, y from the top left, width, height.
+    pub viewport: (i32, i32, i32, i32),
+    /// World units visible, used for the projection.
+    pub view_size: Vec2,
+}
+
+impl ScalingPolicy {
+    pub fn layout(&self, window: (u32, u32)) -> ScreenLayout {
+        let (ww, wh) = (window.0.max(1) as f64, window.1.max(1) as f64);
+        let full = (0, 0, window.0 as i32, window.1 as i32);
+        let centered = |w: f64, h: f64| {
+            (((ww - w) / 2.0) as i32, ((wh - h) / 2.0) as i32, w as i32, h as i32)
+        };
+
+        match *self {
+            ScalingPolicy::Window => ScreenLayout {
+                render_size: window,
+                viewport: full,
+                view_size: Vec2::new(ww, wh),
+            },
+            ScalingPolicy::Letterbox(v) => {
+                let scale = (ww / v.x).min(wh / v.y);
+                let viewport = centered(v.x * scale, v.y * scale);
+                ScreenLayout {
+                    render_size: (viewport.2 as u32, viewport.3 as u32),
+                    viewport,
+                    view_size: v,
+                }
+            }
+            ScalingPolicy::Expand(v) => {
+                let scale = (ww / v.x).min(wh / v.y);
+                ScreenLayout {
+                    render_size: window,
+                    viewport: full,
+                    view_size: Vec2::new(ww / scale, wh / scale),
+                }
+            }
+            ScalingPolicy::IntegerScale(v) => {
+                let scale = (ww / v.x).min(wh / v.y);
+                let scale = if scale >= 1.0 { scale.floor() } else { scale };
+                ScreenLayout {
+                    render_size: (v.x as u32, v.y as u32),
+                    viewport: centered(v.x * scale, v.y * scale),
+                    view_size: v,
+                }
+            }
+        }
+    }
+}
+
+/// Renders the world offscreen, runs the enabled passes in order and draws the result into the viewport.
+/// Only goes offscreen when a pass is enabled or the render size differs from the viewport,
+/// otherwise the world draws straight to the window.
+pub struct PostProcess {
+    pub passes: Vec<PostPass>,
+    targets: Vec<RenderTarget>,
+    blit: ShaderProgram,
+    vertex_array: VertexArray,
+    _vertex_buffer: Buffer,
+    active: bool,
+}
+
+impl PostProcess {
+    #[rustfmt::skip]
+    const QUAD: [Vertex; 6] = [
+        Vertex([-1.0, -1.0], [0.0, 0.0]),
+        Vertex([ 1.0, -1.0], [1.0, 0.0]),
+        Vertex([ 1.0,  1.0], [1.0, 1.0]),
+        Vertex([ 1.0,  1.0], [1.0, 1.0]),
+        Vertex([-1.0,  1.0], [0.0, 1.0]),
+        Vertex([-1.0, -1.0], [0.0, 0.0]),
+    ];
+
+    pub unsafe fn new(vert: &str, frag: &str) -> Self {
+        let vertex_array = VertexArray::new();
+        vertex_array.bind();
+
+        let vertex_buffer = Buffer::new(gl::ARRAY_BUFFER);
+        vertex_buffer.set_data(&Self::QUAD, gl::STATIC_DRAW);
+
+        set_attribute!(vertex_array, 0, Vertex::0, gl::FLOAT);
+        set_attribute!(vertex_array, 1, Vertex::1, gl::FLOAT);
+
+        let vs = Shader::new(vert, gl::VERTEX_SHADER).unwrap();
+        let fs = Shader::new(frag, gl::FRAGMENT_SHADER).unwrap();
+
+        Self {
+            passes: vec![],
+            targets: vec![],
+            blit: ShaderProgram::new(&[fs, vs]).unwrap(),
+            vertex_array,
+            _vertex_buffer: vertex_buffer,
+            active: false,
+        }
+    }
+
+    /// Appends a pass, enabled. Passes run in the order they were added.
+    pub fn add_pass(&mut self, name: &str, material: &str) {
+        self.passes.push(PostPass {
+            name: name.to_string(),
+            material: material.to_string(),
+            enabled: true,
+            uniforms: Uniforms::default(),
+        });
+    }
+
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) {
+        for pass in self.passes.iter_mut().filter(|p| p.name == name) {
+            pass.enabled = enabled;
+        }
+    }
+
+    pub fn set_uniform(&mut self, name: &str, uniform: &str, value: Uniform) {
+        for pass in self.passes.iter_mut().filter(|p| p.name == name) {
+            pass.uniforms.set(uniform, value.clone());
+        }
+    }
+
+    /// Viewport rect converted to gl's bottom left origin.
+    unsafe fn set_viewport(layout: &ScreenLayout, window: (u32, u32)) {
+        let (x, y, w, h) = layout.viewport;
+        gl::Viewport(x, window.1 as i32 - y - h, w, h);
+    }
+
+    /// Rect the world is drawn into this frame in gl's bottom left origin, the whole target when offscreen.
+    pub fn render_area(&self, layout: &ScreenLayout, window: (u32, u32)) -> (i32, i32, i32, i32) {
+        if self.active {
+            (0, 0, layout.render_size.0 as i32, layout.render_size.1 as i32)
+        } else {
+            let (x, y, w, h) = layout.viewport;
+            (x, window.1 as i32 - y - h, w, h)
+        }
+    }
+
+    /// Redirects drawing into the first target or the viewport. Call before the world is drawn.
+    pub unsafe fn begin(&mut self, layout: &ScreenLayout, window: (u32, u32)) {
+        let size = layout.render_size;
+        self.active = self.passes.iter().any(|p| p.enabled)
+            || size != (layout.viewport.2 as u32, layout.viewport.3 as u32);
+
+        if !self.active {
+            Self::set_viewport(layout, window);
+            return;
+        }
+
+        if self.targets.is_empty() || self.targets[0].size() != size {
+            self.targets = vec![
+                RenderTarget::new(size.0, size.1),
+                RenderTarget::new(size.0, size.1),
+            ];
+        }
+
+        self.targets[0].bind();
+        gl::Clear(gl::COLOR_BUFFER_BIT);
+    }
+
+    /// Runs the passes and draws the result into the viewport. Call after the batch is flushed.
+    /// Every pass gets texture0, resolution and time along with its material uniforms.
+    pub unsafe fn end(
+        &mut self,
+        materials: &HashMap<String, Material>,
+        textures: &HashMap<String, LilahTexture>,
+        layout: &ScreenLayout,
+        window: (u32, u32),
+        time: f32,
+    ) {
+        if !self.active {
+            gl::Viewport(0, 0, window.0 as i32, window.1 as i32);
+            return;
+        }
+
+        let size = self.targets[0].size();
+        let resolution = CString::new("resolution").unwrap();
+        let time_name = CString::new("time").unwrap();
+        let mut src = 0;
+
+        self.vertex_array.bind();
+        for pass in self.passes.iter().filter(|p| p.enabled) {
+            let Some(material) = materials.get(&pass.material) else {
+                continue;
+            };
+            let dst = 1 - src;
+
+            self.targets[dst].bind();
+            gl::Clear(gl::COLOR_BUFFER_BIT);
+
+            material.program.apply();
+            gl::Uniform2f(
+                gl::GetUniformLocation(material.program.id, resolution.as_ptr()),
+                size.0 as f32,
+                size.1 as f32,
+            );
+            gl::Uniform1f(
+                gl::GetUniformLocation(material.program.id, time_name.as_ptr()),
+                time,
+            );
+            apply_uniforms(material.program.id, &material.bind(&pass.uniforms, textures));
+
+            self.targets[src].texture.activate(gl::TEXTURE0);
+            gl::DrawArrays(gl::TRIANGLES, 0, Self::QUAD.len() as i32);
+            src = dst;
+        }
+
+        gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        Self::set_viewport(layout, window);
+        self.blit.apply();
+        self.targets[src].texture.activate(gl::TEXTURE0);
+        gl::DrawArrays(gl::TRIANGLES, 0, Self::QUAD.len() as i32);
+
+        gl::Viewport(0, 0, window.0 as i32, window.1 as i32);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VIRTUAL: Vec2 = Vec2 { x: 320.0, y: 180.0 };
+
+    #[test]
+    fn window_scaling_matches_the_window() {
+        let layout = ScalingPolicy::Window.layout((800, 600));
+
+        assert_eq!(layout.render_size, (800, 600));
+        assert_eq!(layout.viewport, (0, 0, 800, 600));
+        assert_eq!(layout.view_size, Vec2::new(800.0, 600.0));
+    }
+
+    #[test]
+    fn letterbox_centers_between_bars() {
+        let layout = ScalingPolicy::Letterbox(VIRTUAL).layout((800, 600));
+
+        assert_eq!(layout.render_size, (800, 450));
+        assert_eq!(layout.viewport, (0, 75, 800, 450));
+        assert_eq!(layout.view_size, VIRTUAL);
+    }
+
+    #[test]
+    fn expand_grows_the_view() {
+        let layout = ScalingPolicy::Expand(VIRTUAL).layout((800, 600));
+
+        assert_eq!(layout.render_size, (800, 600));
+        assert_eq!(layout.viewport, (0, 0, 800, 600));
+        assert_eq!(layout.view_size, Vec2::new(320.0, 240.0));
+    }
+
+    #[test]
+    fn integer_scale_rounds_down() {
+        let layout = ScalingPolicy::IntegerScale(VIRTUAL).layout((800, 600));
+
+        assert_eq!(layout.render_size, (320, 180));
+        assert_eq!(layout.viewport, (80, 120, 640, 360));
+        assert_eq!(layout.view_size, VIRTUAL);
+
+        // windows smaller than the resolution still shrink it to fit
+        let small = ScalingPolicy::IntegerScale(VIRTUAL).layout((160, 90));
+        assert_eq!(small.viewport, (0, 0, 160, 90));
+    }
+}
@@ -0,0 +1,1040 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use image::{DynamicImage, ImageError, Rgba};
+use rusttype::{point, Font, Scale};
+use thiserror::Error;
+
+use crate::atlas::{Atlas, AtlasRegion, RgbaImage};
+use crate::renderer::{Color, LilahTexture};
+
+/// Empty pixels around the text box so glyphs reaching past their advance are not cut off.
+pub const PADDING: u32 = 2;
+
+#[derive(Debug, Error)]
+pub enum FontError {
+    #[error("Bitmap font has no {0}")]
+    Missing(&'static str),
+    #[error("Bitmap font page {0} was not given")]
+    MissingPage(usize),
+    #[error{"{0}"}]
+    ImageError(#[from] ImageError),
+    #[error{"{0}"}]
+    IoError(#[from] std::io::Error),
+}
+
+/// Font a Text can name, either kind lays out and draws the same way.
+pub enum LilahFont<'a> {
+    TrueType(Font<'a>),
+    Bitmap(BitmapFont),
+}
+
+impl<'a> From<Font<'a>> for LilahFont<'a> {
+    fn from(font: Font<'a>) -> Self {
+        LilahFont::TrueType(font)
+    }
+}
+
+impl<'a> LilahFont<'a> {
+    /// The TrueType font, None for bitmap fonts.
+    pub fn truetype(&self) -> Option<&Font<'a>> {
+        match self {
+            LilahFont::TrueType(font) => Some(font),
+            LilahFont::Bitmap(_) => None,
+        }
+    }
+}
+
+impl LilahFont<'_> {
+    /// Distance the pen moves after c.
+    pub fn advance(&self, c: char, size: f32) -> f32 {
+        match self {
+            LilahFont::TrueType(font) => font
+                .glyph(c)
+                .scaled(Scale::uniform(size))
+                .h_metrics()
+                .advance_width,
+            LilahFont::Bitmap(font) => font
+                .glyphs
+                .get(&c)
+                .map_or(0.0, |g| g.advance * font.scale(size)),
+        }
+    }
+
+    /// Adjustment to the pen between a and b.
+    pub fn kerning(&self, a: char, b: char, size: f32) -> f32 {
+        match self {
+            LilahFont::TrueType(font) => font.pair_kerning(Scale::uniform(size), a, b),
+            LilahFont::Bitmap(font) => font
+                .kerning
+                .get(&(a, b))
+                .map_or(0.0, |k| k * font.scale(size)),
+        }
+    }
+
+    /// Ascent above the baseline, descent below it (negative) and the gap between lines.
+    pub fn v_metrics(&self, size: f32) -> (f32, f32, f32) {
+        match self {
+            LilahFont::TrueType(font) => {
+                let v = font.v_metrics(Scale::uniform(size));
+                (v.ascent, v.descent, v.line_gap)
+            }
+            LilahFont::Bitmap(font) => {
+                let scale = font.scale(size);
+                (
+                    font.base * scale,
+                    (font.base - font.line_height) * scale,
+                    0.0,
+                )
+            }
+        }
+    }
+
+    /// White glyph image with coverage in alpha, or the bitmap font's own pixels,
+    /// with its offset from the pen and the size to draw it at. None if c draws nothing.
+    fn rasterize(&self, c: char, size: f32) -> Option<(RgbaImage, (f32, f32), (f32, f32))> {
+        match self {
+            LilahFont::TrueType(font) => {
+                let glyph = font
+                    .glyph(c)
+                    .scaled(Scale::uniform(size))
+                    .positioned(point(0.0, 0.0));
+                let bounding_box = glyph.pixel_bounding_box()?;
+                let (width, height) = (bounding_box.width() as u32, bounding_box.height() as u32);
+
+                let mut image = DynamicImage::new_rgba8(width, height).to_rgba8();
+                glyph.draw(|x, y, v| {
+                    image.put_pixel(x, y, Rgba([255, 255, 255, (v * 255.0) as u8]))
+                });
+
+                Some((
+                    image,
+                    (bounding_box.min.x as f32, bounding_box.min.y as f32),
+                    (width as f32, height as f32),
+                ))
+            }
+            LilahFont::Bitmap(font) => {
+                let g = font.glyphs.get(&c)?;
+                if g.width == 0 || g.height == 0 {
+                    return None;
+                }
+
+                let page = font.pages.get(g.page)?;
+                let image = image::imageops::crop_imm(page, g.x, g.y, g.width, g.height).to_image();
+                let scale = font.scale(size);
+
+                // bitmap offsets are from the top of the line, the pen sits on the baseline
+                Some((
+                    image,
+                    (g.offset.0 * scale, (g.offset.1 - font.base) * scale),
+                    (g.width as f32 * scale, g.height as f32 * scale),
+                ))
+            }
+        }
+    }
+}
+
+/// Glyph in a bitmap font page, in the page's pixels.
+#[derive(Clone, Debug)]
+pub struct BitmapGlyph {
+    pub page: usize,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    /// From the pen to the top left of the glyph, y down from the top of the line.
+    pub offset: (f32, f32),
+    pub advance: f32,
+}
+
+/// Font drawn from pre-rendered pages, from BMFont text or XML files or a fixed grid sheet.
+/// A Text size equal to size draws the pages 1:1, other sizes scale them.
+pub struct BitmapFont {
+    pub size: f32,
+    pub line_height: f32,
+    /// Baseline distance from the top of the line.
+    pub base: f32,
+    pub glyphs: HashMap<char, BitmapGlyph>,
+    pub kerning: HashMap<(char, char), f32>,
+    pub pages: Vec<RgbaImage>,
+}
+
+impl BitmapFont {
+    /// Loads a BMFont .fnt in the text or XML format. Page files are read relative to it.
+    pub fn load(file: &str) -> Result<BitmapFont, FontError> {
+        let path = Path::new(file);
+        let source = fs::read_to_string(path)?;
+
+        let mut font = BitmapFont::parse(&source)?;
+        for page in BitmapFont::page_files(&source) {
+            font.pages.push(image::open(path.with_file_name(page))?.into_rgba8());
+        }
+        font.check_pages()?;
+        Ok(font)
+    }
+
+    /// Parses BMFont text or XML, with the page images in page id order.
+    pub fn from_bytes(source: &[u8], pages: &[&[u8]]) -> Result<BitmapFont, FontError> {
+        let mut font = BitmapFont::parse(&String::from_utf8_lossy(source))?;
+        for page in pages {
+            font.pages.push(image::load_from_memory(page)?.into_rgba8());
+        }
+        font.check_pages()?;
+        Ok(font)
+    }
+
+    /// Cuts a sheet into cell sized glyphs, left to right and top to bottom, in the order of chars.
+    /// Every glyph advances one cell.
+    pub fn from_grid(sheet: RgbaImage, cell: (u32, u32), chars: &str) -> BitmapFont {
+        let columns = (sheet.width() / cell.0.max(1)).max(1);
+        let glyphs = chars
+            .chars()
+            .enumerate()
+            .map(|(i, c)| {
+                let (column, row) = (i as u32 % columns, i as u32 / columns);
+                (
+                    c,
+                    BitmapGlyph {
+                        page: 0,
+                        x: column * cell.0,
+                        y: row * cell.1,
+                        width: cell.0,
+                        height: cell.1,
+                        offset: (0.0, 0.0),
+                        advance: cell.0 as f32,
+                    },
+                )
+            })
+            // cells past the sheet are dropped
+            .filter(|(_, g)| g.x + g.width <= sheet.width() && g.y + g.height <= sheet.height())
+            .collect();
+
+        BitmapFont {
+            size: cell.1 as f32,
+            line_height: cell.1 as f32,
+            base: cell.1 as f32,
+            glyphs,
+            kerning: HashMap::new(),
+            pages: vec![sheet],
+        }
+    }
+
+    fn scale(&self, size: f32) -> f32 {
+        size / self.size
+    }
+
+    fn check_pages(&self) -> Result<(), FontError> {
+        match self.glyphs.values().map(|g| g.page).max() {
+            Some(page) if page >= self.pages.len() => Err(FontError::MissingPage(page)),
+            _ => Ok(()),
+        }
+    }
+
+    fn parse(source: &str) -> Result<BitmapFont, FontError> {
+        let mut size = None;
+        let mut common = None;
+        let mut glyphs = HashMap::new();
+        let mut kerning = HashMap::new();
+
+        for (tag, attributes) in bmfont_elements(source) {
+            let number = |key: &str| {
+                attributes
+                    .iter()
+                    .find(|(k, _)| k == key)
+                    .and_then(|(_, v)| v.parse::<f32>().ok())
+            };
+            let char_of = |key: &str| number(key).and_then(|id| char::from_u32(id as u32));
+
+            match tag.as_str() {
+                // negative sizes mean the size matches the glyph height instead of the em
+                "info" => size = number("size").map(f32::abs),
+                "common" => common = number("lineHeight").zip(number("base")),
+                "char" => {
+                    if let Some(c) = char_of("id") {
+                        let value = |key: &str| number(key).unwrap_or(0.0);
+                        glyphs.insert(
+                            c,
+                            BitmapGlyph {
+                                page: value("page") as usize,
+                                x: value("x") as u32,
+                                y: value("y") as u32,
+                                width: value("width") as u32,
+                                height: value("height") as u32,
+                                offset: (value("xoffset"), value("yoffset")),
+                                advance: value("xadvance"),
+                            },
+                        );
+                    }
+                }
+                "kerning" => {
+                    if let (Some(a), Some(b), Some(amount)) =
+                        (char_of("first"), char_of("second"), number("amount"))
+                    {
+                        kerning.insert((a, b), amount);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let (line_height, base) = common.ok_or(FontError::Missing("common line"))?;
+        Ok(BitmapFont {
+            size: size.filter(|s| *s > 0.0).unwrap_or(line_height),
+            line_height,
+            base,
+            glyphs,
+            kerning,
+            pages: vec![],
+        })
+    }
+
+    /// Page file names in id order.
+    fn page_files(source: &str) -> Vec<String> {
+        let mut pages: Vec<(usize, String)> = bmfont_elements(source)
+            .into_iter()
+            .filter(|(tag, _)| tag == "page")
+            .filter_map(|(_, attributes)| {
+                let value = |key: &str| {
+                    attributes
+                        .iter()
+                        .find(|(k, _)| k == key)
+                        .map(|(_, v)| v.clone())
+                };
+                Some((value("id")?.parse().ok()?, value("file")?))
+            })
+            .collect();
+        pages.sort_by_key(|(id, _)| *id);
+        pages.into_iter().map(|(_, file)| file).collect()
+    }
+}
+
+/// Tags and key=value attributes of a BMFont file. Text files have one element per line,
+/// XML files one per tag, so both read the same way.
+fn bmfont_elements(source: &str) -> Vec<(String, Vec<(String, String)>)> {
+    let xml = source.trim_start().starts_with('<');
+    let elements: Vec<&str> = if xml {
+        source.split('<').collect()
+    } else {
+        source.lines().collect()
+    };
+
+    elements
+        .into_iter()
+        .filter_map(|element| {
+            let element = element.trim().trim_end_matches('>').trim_end_matches('/');
+            let (tag, mut rest) = element.split_once(char::is_whitespace)?;
+
+            let mut attributes = vec![];
+            while let Some((key, after)) = rest.split_once('=') {
+                let after = after.trim_start();
+                let (value, remaining) = match after.strip_prefix('"') {
+                    Some(quoted) => quoted.split_once('"').unwrap_or((quoted, "")),
+                    None => after.split_once(char::is_whitespace).unwrap_or((after, "")),
+                };
+                attributes.push((key.trim().to_string(), value.to_string()));
+                rest = remaining;
+            }
+            Some((tag.to_string(), attributes))
+        })
+        .collect()
+}
+
+/// Horizontal placement of each line inside the text box.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TextAlign {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+impl TextAlign {
+    pub fn from_name(name: &str) -> Option<TextAlign> {
+        match name {
+            "left" => Some(TextAlign::Left),
+            "center" => Some(TextAlign::Center),
+            "right" => Some(TextAlign::Right),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TextAlign::Left => "left",
+            TextAlign::Center => "center",
+            TextAlign::Right => "right",
+        }
+    }
+}
+
+/// Vertical placement of the lines inside the text box.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum VerticalAlign {
+    #[default]
+    Top,
+    Middle,
+    Bottom,
+}
+
+impl VerticalAlign {
+    pub fn from_name(name: &str) -> Option<VerticalAlign> {
+        match name {
+            "top" => Some(VerticalAlign::Top),
+            "middle" => Some(VerticalAlign::Middle),
+            "bottom" => Some(VerticalAlign::Bottom),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            VerticalAlign::Top => "top",
+            VerticalAlign::Middle => "middle",
+            VerticalAlign::Bottom => "bottom",
+        }
+    }
+}
+
+/// Box and spacing for a layout, sizes are in pixels.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LayoutOptions {
+    /// Size of text outside any size markup.
+    pub size: f32,
+    /// Lines wrap at spaces to fit, words longer than the box break anywhere. None never wraps.
+    pub max_width: Option<f32>,
+    /// Height of the box the lines are aligned in, it still grows to fit every line.
+    pub min_height: Option<f32>,
+    pub align: TextAlign,
+    pub valign: VerticalAlign,
+    /// Multiplier on the distance between baselines.
+    pub line_spacing: f32,
+}
+
+/// Per character motion for Text, in pixels of the text box.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TextEffects {
+    /// Height of the wave, 0 for none.
+    pub wave_amplitude: f32,
+    /// Radians per second.
+    pub wave_speed: f32,
+    /// Radians between neighbouring characters.
+    pub wave_spacing: f32,
+    /// Largest random offset, 0 for none.
+    pub shake: f32,
+}
+
+impl TextEffects {
+    /// New shake offsets per second.
+    const SHAKE_RATE: f32 = 30.0;
+
+    /// Offset of the glyph at index, y down, time in seconds.
+    pub fn offset(&self, index: usize, time: f32) -> (f32, f32) {
+        let mut offset = (0.0, 0.0);
+        if self.wave_amplitude != 0.0 {
+            let phase = time * self.wave_speed + index as f32 * self.wave_spacing;
+            offset.1 -= phase.sin() * self.wave_amplitude;
+        }
+        if self.shake != 0.0 {
+            let step = (time * Self::SHAKE_RATE) as u32;
+            let seed = (index as u32).wrapping_mul(7919) ^ step.wrapping_mul(104_729);
+            offset.0 += noise(seed) * self.shake;
+            offset.1 += noise(seed ^ 0x5bd1_e995) * self.shake;
+        }
+        offset
+    }
+
+    /// Furthest a glyph can be moved.
+    pub fn reach(&self) -> f32 {
+        self.wave_amplitude.abs() + self.shake.abs()
+    }
+}
+
+/// Hash of seed mapped to -1..1.
+fn noise(seed: u32) -> f32 {
+    let mut x = seed.wrapping_mul(0x9e37_79b9);
+    x ^= x >> 16;
+    x = x.wrapping_mul(0x85eb_ca6b);
+    x ^= x >> 13;
+    x as f32 / u32::MAX as f32 * 2.0 - 1.0
+}
+
+/// Run of text sharing a color and size.
+#[derive(Clone)]
+pub struct Span {
+    pub text: String,
+    pub color: Color,
+    pub size: f32,
+}
+
+/// Glyph placed in the box, x right and y down from the top left, y on the baseline.
+#[derive(Clone)]
+pub struct PlacedGlyph {
+    pub c: char,
+    pub x: f32,
+    pub y: f32,
+    pub size: f32,
+    pub color: Color,
+}
+
+pub struct TextLayout {
+    pub glyphs: Vec<PlacedGlyph>,
+    /// Size of the box the glyphs were aligned in.
+    pub width: f32,
+    pub height: f32,
+    /// Size of the lines themselves, without the box.
+    pub bounds: (f32, f32),
+    pub lines: usize,
+}
+
+enum Tag {
+    Color(Color),
+    EndColor,
+    Size(f32),
+    EndSize,
+}
+
+/// Splits text into spans by its markup.
+/// [color=#rrggbb] or [color=#rrggbbaa] and [size=px] apply until their [/color] or [/size],
+/// [[ is a literal [ and anything else in brackets is kept as written.
+pub fn parse_markup(text: &str, color: &Color, size: f32) -> Vec<Span> {
+    let mut spans = vec![];
+    let mut colors = vec![color.clone()];
+    let mut sizes = vec![size];
+    let mut current = String::new();
+    let mut rest = text;
+
+    while let Some(open) = rest.find('[') {
+        current.push_str(&rest[..open]);
+        rest = &rest[open..];
+
+        if let Some(after) = rest.strip_prefix("[[") {
+            current.push('[');
+            rest = after;
+            continue;
+        }
+
+        let Some(close) = rest.find(']') else {
+            break;
+        };
+
+        match parse_tag(&rest[1..close]) {
+            Some(tag) => {
+                push_span(&mut spans, &mut current, &colors, &sizes);
+                match tag {
+                    Tag::Color(c) => colors.push(c),
+                    Tag::Size(s) => sizes.push(s),
+                    Tag::EndColor => {
+                        if colors.len() > 1 {
+                            colors.pop();
+                        }
+                    }
+                    Tag::EndSize => {
+                        if sizes.len() > 1 {
+                            sizes.pop();
+                        }
+                    }
+                }
+            }
+            None => current.push_str(&rest[..=close]),
+        }
+        rest = &rest[close + 1..];
+    }
+
+    current.push_str(rest);
+    push_span(&mut spans, &mut current, &colors, &sizes);
+    spans
+}
+
+fn push_span(spans: &mut Vec<Span>, current: &mut String, colors: &[Color], sizes: &[f32]) {
+    if current.is_empty() {
+        return;
+    }
+
+    spans.push(Span {
+        text: std::mem::take(current),
+        color: colors.last().unwrap().clone(),
+        size: *sizes.last().unwrap(),
+    });
+}
+
+fn parse_tag(tag: &str) -> Option<Tag> {
+    match tag.split_once('=') {
+        Some(("color", value)) => parse_hex(value).map(Tag::Color),
+        Some(("size", value)) => value
+            .parse::<f32>()
+            .ok()
+            .filter(|s| *s > 0.0)
+            .map(Tag::Size),
+        None if tag == "/color" => Some(Tag::EndColor),
+        None if tag == "/size" => Some(Tag::EndSize),
+        _ => None,
+    }
+}
+
+fn parse_hex(value: &str) -> Option<Color> {
+    let hex = value.strip_prefix('#')?;
+    if hex.len() != 6 && hex.len() != 8 {
+        return None;
+    }
+
+    let channel = |i: usize| {
+        u8::from_str_radix(hex.get(i..i + 2)?, 16)
+            .ok()
+            .map(|v| v as f32 / 255.0)
+    };
+    let alpha = if hex.len() == 8 { channel(6)? } else { 1.0 };
+    Some(Color::new(channel(0)?, channel(2)?, channel(4)?, alpha))
+}
+
+/// Glyph waiting for its line to be finished, x is from the line start.
+struct Pending {
+    c: char,
+    span: usize,
+    x: f32,
+    advance: f32,
+}
+
+/// Breaks spans into lines and places every glyph inside the box.
+pub fn layout(font: &LilahFont, spans: &[Span], options: &LayoutOptions) -> TextLayout {
+    let mut lines: Vec<Vec<Pending>> = vec![];
+    let mut line: Vec<Pending> = vec![];
+    let mut x = 0.0;
+    // index in line just after the last space, where a wrap can happen
+    let mut last_break: Option<usize> = None;
+    let mut prev: Option<(char, f32)> = None;
+
+    for (span_index, span) in spans.iter().enumerate() {
+        for c in span.text.chars() {
+            if c == '\n' {
+                lines.push(std::mem::take(&mut line));
+                x = 0.0;
+                last_break = None;
+                prev = None;
+                continue;
+            }
+            let c = if c == '\t' { ' ' } else { c };
+            if c.is_control() {
+                continue;
+            }
+
+            let advance = font.advance(c, span.size);
+            if let Some((p, size)) = prev {
+                if size == span.size {
+                    x += font.kerning(p, c, span.size);
+                }
+            }
+            prev = Some((c, span.size));
+
+            if let Some(max) = options.max_width {
+                if !c.is_whitespace()
+                    && line.iter().any(|p| !p.c.is_whitespace())
+                    && x + advance > max
+                {
+                    let carried = match last_break {
+                        Some(i) => line.split_off(i),
+                        None => vec![],
+                    };
+                    lines.push(std::mem::take(&mut line));
+                    last_break = None;
+
+                    let start = carried.first().map_or(x, |p| p.x);
+                    line = carried
+                        .into_iter()
+                        .map(|p| Pending { x: p.x - start, ..p })
+                        .collect();
+                    x -= start;
+                }
+            }
+
+            line.push(Pending {
+                c,
+                span: span_index,
+                x,
+                advance,
+            });
+            x += advance;
+            if c.is_whitespace() {
+                last_break = Some(line.len());
+            }
+        }
+    }
+    lines.push(line);
+
+    // trailing spaces do not count toward a line's width
+    let widths: Vec<f32> = lines
+        .iter()
+        .map(|l| {
+            l.iter()
+                .filter(|p| !p.c.is_whitespace())
+                .map(|p| p.x + p.advance)
+                .fold(0.0, f32::max)
+        })
+        .collect();
+
+    let metrics: Vec<(f32, f32, f32)> = lines
+        .iter()
+        .map(|l| {
+            let sizes = l.iter().map(|p| spans[p.span].size);
+            let size = sizes.fold(0.0, f32::max);
+            font.v_metrics(if size > 0.0 { size } else { options.size })
+        })
+        .collect();
+
+    let mut baselines = vec![];
+    let mut y = 0.0;
+    let mut content_height = 0.0;
+    for (ascent, descent, line_gap) in &metrics {
+        baselines.push(y + ascent);
+        content_height = y + ascent - descent;
+        y += (ascent - descent + line_gap) * options.line_spacing;
+    }
+
+    let content_width = widths.iter().copied().fold(0.0, f32::max);
+    let width = options.max_width.map_or(content_width, |w| w.max(content_width));
+    let height = options
+        .min_height
+        .map_or(content_height, |h| h.max(content_height));
+
+    let top = match options.valign {
+        VerticalAlign::Top => 0.0,
+        VerticalAlign::Middle => (height - content_height) / 2.0,
+        VerticalAlign::Bottom => height - content_height,
+    };
+
+    let mut glyphs = vec![];
+    for ((line, line_width), baseline) in lines.iter().zip(&widths).zip(&baselines) {
+        let left = match options.align {
+            TextAlign::Left => 0.0,
+            TextAlign::Center => (width - line_width) / 2.0,
+            TextAlign::Right => width - line_width,
+        };
+
+        for p in line.iter().filter(|p| !p.c.is_whitespace()) {
+            let span = &spans[p.span];
+            glyphs.push(PlacedGlyph {
+                c: p.c,
+                x: left + p.x,
+                y: top + baseline,
+                size: span.size,
+                color: span.color.clone(),
+            });
+        }
+    }
+
+    TextLayout {
+        glyphs,
+        width,
+        height,
+        bounds: (content_width, content_height),
+        lines: lines.len(),
+    }
+}
+
+/// Where a cached glyph was packed, its offset from the pen position and the size it draws at.
+#[derive(Clone, Copy)]
+pub struct CachedGlyph {
+    pub region: AtlasRegion,
+    pub offset: (f32, f32),
+    pub size: (f32, f32),
+}
+
+/// Glyphs rasterized once per font, whole pixel size and character, shared by every Text.
+/// Text draws as a quad per glyph from the pages, so changing it only uploads glyphs not seen yet.
+/// Past MAX_PAGES pages everything is dropped and texts cache their glyphs again, see generation.
+/// A layout over the budget by itself keeps what fit, and later glyphs are left out until clear.
+pub struct GlyphCache {
+    atlas: Atlas,
+    /// font -> (pixel size, char) -> glyph, None for glyphs with nothing to draw such as spaces
+    /// or glyphs left out over the budget.
+    fonts: HashMap<String, HashMap<(u32, char), Option<CachedGlyph>>>,
+    generation: usize,
+    /// Set when a layout did not fit even in an empty cache, dropping it again would not help.
+    over_budget: bool,
+}
+
+impl Default for GlyphCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GlyphCache {
+    const PAGE_SIZE: u32 = 1024;
+    const MAX_PAGES: usize = 4;
+
+    pub fn new() -> Self {
+        Self {
+            atlas: Atlas::new(Self::PAGE_SIZE),
+            fonts: HashMap::new(),
+            generation: 0,
+            over_budget: false,
+        }
+    }
+
+    /// Sizes are rasterized at whole pixels and scaled to the rest when drawn.
+    fn pixel_size(size: f32) -> u32 {
+        size.round().max(1.0) as u32
+    }
+
+    /// Goes up every time the cache is dropped, glyphs cached before then have to be cached again.
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
+    /// Rasterizes and uploads every glyph of a layout that is not cached yet.
+    /// Drops the whole cache first when the layout would go over the page budget.
+    pub unsafe fn cache(&mut self, font_name: &str, font: &LilahFont, glyphs: &[PlacedGlyph]) {
+        let empty = self.fonts.values().all(HashMap::is_empty);
+        if self.try_cache(font_name, font, glyphs) || self.over_budget {
+            return;
+        }
+        // a layout that did not fit in an empty cache would not fit after clearing either,
+        // clearing anyway would relayout every text every frame
+        if empty {
+            self.over_budget = true;
+            return;
+        }
+
+        self.clear();
+        // glyphs that still do not fit are left out
+        self.over_budget = !self.try_cache(font_name, font, glyphs);
+    }
+
+    /// Drops every glyph, texts cache theirs again on their next layout.
+    pub fn clear(&mut self) {
+        self.atlas = Atlas::new(Self::PAGE_SIZE);
+        self.fonts.clear();
+        self.generation += 1;
+        self.over_budget = false;
+    }
+
+    /// false if a glyph was left out for going past the budget, such glyphs are not tried again.
+    unsafe fn try_cache(
+        &mut self,
+        font_name: &str,
+        font: &LilahFont,
+        glyphs: &[PlacedGlyph],
+    ) -> bool {
+        let cached = self.fonts.entry(font_name.to_string()).or_default();
+        let mut fit = true;
+        for g in glyphs {
+            let size = Self::pixel_size(g.size);
+            let key = (size, g.c);
+            if cached.contains_key(&key) {
+                continue;
+            }
+            if self.over_budget || self.atlas.pages.len() > Self::MAX_PAGES {
+                cached.insert(key, None);
+                fit = false;
+                continue;
+            }
+
+            let entry = font.rasterize(g.c, size as f32).and_then(|(image, offset, size)| {
+                let name = format!("{}:{}:{}", font_name, key.0, g.c as u32);
+                // glyphs larger than a page are left out
+                self.atlas
+                    .insert(&name, &image)
+                    .map(|region| CachedGlyph { region, offset, size })
+            });
+            cached.insert(key, entry);
+        }
+        fit && self.atlas.pages.len() <= Self::MAX_PAGES
+    }
+
+    /// Page and placement of a cached glyph scaled to size. None if it was never cached or
+    /// draws nothing.
+    pub fn get(&self, font: &str, size: f32, c: char) -> Option<(&LilahTexture, CachedGlyph)> {
+        let pixel_size = Self::pixel_size(size);
+        let glyph = self.fonts.get(font)?.get(&(pixel_size, c))?.as_ref()?;
+        let scale = size / pixel_size as f32;
+        let scaled = CachedGlyph {
+            offset: (glyph.offset.0 * scale, glyph.offset.1 * scale),
+            size: (glyph.size.0 * scale, glyph.size.1 * scale),
+            ..*glyph
+        };
+        Some((&self.atlas.pages[glyph.region.page], scaled))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attributes(element: &(String, Vec<(String, String)>)) -> Vec<(&str, &str)> {
+        element.1.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect()
+    }
+
+    #[test]
+    fn bmfont_text_quoted_values() {
+        let elements = bmfont_elements(
+            "info face=\"My Font\" size=32 charset=\"\"\npage id=0 file=\"font 0.png\"",
+        );
+
+        assert_eq!(elements.len(), 2);
+        assert_eq!(elements[0].0, "info");
+        assert_eq!(
+            attributes(&elements[0]),
+            vec![("face", "My Font"), ("size", "32"), ("charset", "")]
+        );
+        assert_eq!(elements[1].0, "page");
+        assert_eq!(attributes(&elements[1]), vec![("id", "0"), ("file", "font 0.png")]);
+    }
+
+    #[test]
+    fn bmfont_xml_matches_text() {
+        let xml = bmfont_elements(
+            "<?xml version=\"1.0\"?>\n<font>\n  <common lineHeight=\"20\" base=\"16\"/>\n</font>",
+        );
+        let common = xml.iter().find(|(tag, _)| tag == "common").unwrap();
+
+        assert_eq!(attributes(common), vec![("lineHeight", "20"), ("base", "16")]);
+    }
+
+    #[test]
+    fn bmfont_missing_keys() {
+        let elements = bmfont_elements("chars\n\nchar id=65 x=\ncommon");
+
+        // tags without attributes are dropped, a key without a value reads as empty
+        assert_eq!(elements.len(), 1);
+        assert_eq!(elements[0].0, "char");
+        assert_eq!(attributes(&elements[0]), vec![("id", "65"), ("x", "")]);
+    }
+
+    #[test]
+    fn bmfont_kerning_lines() {
+        let elements = bmfont_elements(concat!(
+            "kernings count=2\n",
+            "kerning first=65 second=86 amount=-2\n",
+            "kerning  first=86   second=65 amount=-1",
+        ));
+        let kernings: Vec<_> = elements.iter().filter(|(tag, _)| tag == "kerning").collect();
+
+        assert_eq!(kernings.len(), 2);
+        assert_eq!(
+            attributes(kernings[0]),
+            vec![("first", "65"), ("second", "86"), ("amount", "-2")]
+        );
+        assert_eq!(
+            attributes(kernings[1]),
+            vec![("first", "86"), ("second", "65"), ("amount", "-1")]
+        );
+    }
+
+    /// Grid font where every glyph, space included, is 10 wide at size 10.
+    fn grid_font() -> LilahFont<'static> {
+        LilahFont::Bitmap(BitmapFont::from_grid(RgbaImage::new(80, 10), (10, 10), " abcdefg"))
+    }
+
+    fn options(max_width: Option<f32>, align: TextAlign) -> LayoutOptions {
+        LayoutOptions {
+            size: 10.0,
+            max_width,
+            min_height: None,
+            align,
+            valign: VerticalAlign::Top,
+            line_spacing: 1.0,
+        }
+    }
+
+    fn placed(layout: &TextLayout) -> Vec<(char, f32, f32)> {
+        layout.glyphs.iter().map(|g| (g.c, g.x, g.y)).collect()
+    }
+
+    #[test]
+    fn over_budget_layouts_keep_the_cache() {
+        let font = grid_font();
+        let spans = parse_markup("ab", &Color::WHITE, 10.0);
+        let text = layout(&font, &spans, &options(None, TextAlign::Left));
+        let mut cache = GlyphCache::new();
+        // as left by a layout that did not fit in an empty cache
+        cache.over_budget = true;
+
+        unsafe {
+            cache.cache("grid", &font, &text.glyphs);
+            cache.cache("grid", &font, &text.glyphs);
+        }
+
+        // glyphs past the budget are left out once instead of clearing every call
+        assert_eq!(cache.generation(), 0);
+        assert!(cache.get("grid", 10.0, 'a').is_none());
+        assert_eq!(cache.fonts["grid"].len(), 2);
+
+        cache.clear();
+        assert_eq!(cache.generation(), 1);
+        assert!(!cache.over_budget && cache.fonts.is_empty());
+    }
+
+    #[test]
+    fn markup_unclosed_tags() {
+        // a bracket that never closes is kept as written
+        let spans = parse_markup("a[color=#ff0000", &Color::WHITE, 10.0);
+        assert_eq!(spans.len(), 1);
+        assert_eq!(spans[0].text, "a[color=#ff0000");
+        assert_eq!(spans[0].color.g, 1.0);
+
+        // a tag that is never ended runs to the end of the text
+        let spans = parse_markup("a[color=#ff0000][size=20]b", &Color::WHITE, 10.0);
+        assert_eq!(spans.len(), 2);
+        assert_eq!((spans[1].text.as_str(), spans[1].size), ("b", 20.0));
+        assert_eq!((spans[1].color.r, spans[1].color.g), (1.0, 0.0));
+    }
+
+    #[test]
+    fn markup_escapes() {
+        let spans = parse_markup("[[color=#ff0000] [b] [/size]c", &Color::WHITE, 10.0);
+
+        // [[ is a literal bracket, unknown tags are text, unmatched ends do nothing
+        let text: Vec<&str> = spans.iter().map(|s| s.text.as_str()).collect();
+        assert_eq!(text, vec!["[color=#ff0000] [b] ", "c"]);
+        assert!(spans.iter().all(|s| s.color.g == 1.0 && s.size == 10.0));
+    }
+
+    #[test]
+    fn layout_breaks_long_words() {
+        let font = grid_font();
+        let spans = parse_markup("abcdefg", &Color::WHITE, 10.0);
+        let layout = layout(&font, &spans, &options(Some(30.0), TextAlign::Left));
+
+        assert_eq!(layout.lines, 3);
+        assert_eq!(layout.width, 30.0);
+        assert_eq!(
+            placed(&layout),
+            vec![
+                ('a', 0.0, 10.0),
+                ('b', 10.0, 10.0),
+                ('c', 20.0, 10.0),
+                ('d', 0.0, 20.0),
+                ('e', 10.0, 20.0),
+                ('f', 20.0, 20.0),
+                ('g', 0.0, 30.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn layout_wraps_at_spaces() {
+        let font = grid_font();
+        let spans = parse_markup("ab cd", &Color::WHITE, 10.0);
+        let layout = layout(&font, &spans, &options(Some(40.0), TextAlign::Left));
+
+        assert_eq!(layout.lines, 2);
+        assert_eq!(
+            placed(&layout),
+            vec![('a', 0.0, 10.0), ('b', 10.0, 10.0), ('c', 0.0, 20.0), ('d', 10.0, 20.0)]
+        );
+    }
+
+    #[test]
+    fn layout_aligns_without_trailing_spaces() {
+        let font = grid_font();
+        let spans = parse_markup("ab  ", &Color::WHITE, 10.0);
+
+        let right = layout(&font, &spans, &options(Some(40.0), TextAlign::Right));
+        assert_eq!(placed(&right), vec![('a', 20.0, 10.0), ('b', 30.0, 10.0)]);
+
+        let center = layout(&font, &spans, &options(Some(40.0), TextAlign::Center));
+        assert_eq!(placed(&center), vec![('a', 10.0, 10.0), ('b', 20.0, 10.0)]);
+        assert_eq!(center.bounds, (20.0, 10.0));
+    }
+}
@@ -0,0 +1,319 @@
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::CString;
+use std::ptr;
+use std::rc::Rc;
+
+use gl::types::*;
+use glam::{Mat4, Vec3};
+
+use crate::atlas::Atlas;
+use crate::components::{Sprite, Transform};
+use crate::math::{self, Vec2};
+use crate::renderer::{
+    self, Batch, BatchVertex, BlendMode, BoundUniform, Buffer, LilahTexture, ShaderProgram,
+    VertexArray,
+};
+use crate::set_attribute;
+
+/// Tiles along each side of a chunk.
+pub const CHUNK_TILES: f64 = 32.0;
+
+/// Static buffers with the quads of one texture in a chunk.
+/// Shared by clones of the Scene and deleted with the last of them.
+struct ChunkMesh {
+    vertex_array: VertexArray,
+    vertex_buffer: Buffer,
+    index_buffer: Buffer,
+    texture: GLuint,
+    quads: usize,
+}
+
+impl ChunkMesh {
+    /// Attributes are bound to the same locations as Batch, so tile layer materials work unchanged.
+    unsafe fn new(texture: GLuint, vertices: &[BatchVertex]) -> Self {
+        let vertex_array = VertexArray::new();
+        vertex_array.bind();
+
+        let vertex_buffer = Buffer::new(gl::ARRAY_BUFFER);
+        vertex_buffer.set_data(vertices, gl::STATIC_DRAW);
+
+        let quads = vertices.len() / 4;
+        let mut indices: Vec<u32> = Vec::with_capacity(quads * 6);
+        for i in 0..quads as u32 {
+            let o = i * 4;
+            indices.extend_from_slice(&[o, o + 1, o + 2, o + 2, o + 3, o]);
+        }
+        let index_buffer = Buffer::new(gl::ELEMENT_ARRAY_BUFFER);
+        index_buffer.set_data(&indices, gl::STATIC_DRAW);
+
+        set_attribute!(vertex_array, 0, BatchVertex::0, gl::FLOAT);
+        set_attribute!(vertex_array, 1, BatchVertex::1, gl::FLOAT);
+        set_attribute!(vertex_array, 2, BatchVertex::2, gl::FLOAT);
+
+        // keep index buffers bound later from landing in this array
+        gl::BindVertexArray(0);
+
+        Self {
+            vertex_array,
+            vertex_buffer,
+            index_buffer,
+            texture,
+            quads,
+        }
+    }
+}
+
+impl Drop for ChunkMesh {
+    fn drop(&mut self) {
+        self.vertex_array.delete();
+        self.vertex_buffer.delete();
+        self.index_buffer.delete();
+    }
+}
+
+/// Tiles of a layer whose min corner falls in the same CHUNK_TILES square.
+#[derive(Clone)]
+pub struct TileChunk {
+    /// Indices into the layer's tiles.
+    pub tiles: Vec<usize>,
+    /// Min and max corners around the tiles relative to the scene.
+    pub bounds: (Vec2, Vec2),
+    meshes: Vec<Rc<ChunkMesh>>,
+    dirty: bool,
+}
+
+impl TileChunk {
+    /// Bakes the tiles into one mesh per texture they use, in the order the textures first appear.
+    unsafe fn build(
+        &mut self,
+        tiles: &[Sprite],
+        transforms: &[Transform],
+        textures: &HashMap<String, LilahTexture>,
+        atlas: Option<&Atlas>,
+    ) {
+        let mut groups: Vec<(GLuint, Vec<BatchVertex>)> = vec![];
+        for &i in &self.tiles {
+            let (Some(tile), Some(trans)) = (tiles.get(i), transforms.get(i)) else {
+                continue;
+            };
+            let Some((texture, quads)) = tile.quads(textures, atlas, trans) else {
+                continue;
+            };
+
+            let group = match groups.iter().position(|(id, _)| *id == texture.id) {
+                Some(group) => group,
+                None => {
+                    groups.push((texture.id, vec![]));
+                    groups.len() - 1
+                }
+            };
+            let tint = [tile.tint.r, tile.tint.g, tile.tint.b, tile.tint.a];
+            for (corners, uvs) in quads {
+                for (corner, uv) in corners.into_iter().zip(uvs) {
+                    groups[group].1.push(BatchVertex(corner, uv, tint));
+                }
+            }
+        }
+
+        self.meshes = groups
+            .into_iter()
+            .map(|(texture, vertices)| Rc::new(ChunkMesh::new(texture, &vertices)))
+            .collect();
+        self.dirty = false;
+    }
+}
+
+/// A tile layer split into chunks baked into static meshes.
+/// Chunks are rebuilt only once marked dirty, and culled as a whole.
+#[derive(Clone, Default)]
+pub struct ChunkedLayer {
+    pub chunks: Vec<TileChunk>,
+    /// tile index -> chunk index
+    chunk_of: Vec<usize>,
+}
+
+impl ChunkedLayer {
+    /// Groups tiles by the chunk their min corner falls in, with every chunk left to build.
+    /// Chunks are sized by the first tile, layers from the editor use a single tile size.
+    pub fn new(tile_bounds: &[(Vec2, Vec2)]) -> Self {
+        let Some(first) = tile_bounds.first() else {
+            return Self::default();
+        };
+        let size = (first.1 - first.0) * CHUNK_TILES;
+        let size = Vec2::new(size.x.max(1.0), size.y.max(1.0));
+
+        let mut cells: BTreeMap<(i64, i64), Vec<usize>> = BTreeMap::new();
+        for (i, (min, _)) in tile_bounds.iter().enumerate() {
+            let cell = ((min.x / size.x).floor() as i64, (min.y / size.y).floor() as i64);
+            cells.entry(cell).or_default().push(i);
+        }
+
+        let mut chunk_of = vec![0; tile_bounds.len()];
+        let chunks = cells
+            .into_values()
+            .enumerate()
+            .map(|(c, tiles)| {
+                for &tile in &tiles {
+                    chunk_of[tile] = c;
+                }
+                let bounds = tiles
+                    .iter()
+                    .map(|&tile| tile_bounds[tile])
+                    .reduce(math::union)
+                    .unwrap();
+
+                TileChunk {
+                    tiles,
+                    bounds,
+                    meshes: vec![],
+                    dirty: true,
+                }
+            })
+            .collect();
+
+        Self { chunks, chunk_of }
+    }
+
+    /// Rebuilds the chunk holding tile on the next rebuild.
+    pub fn mark_dirty(&mut self, tile: usize) {
+        if let Some(&chunk) = self.chunk_of.get(tile) {
+            self.chunks[chunk].dirty = true;
+        }
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.chunks.iter().any(|c| c.dirty)
+    }
+
+    /// Builds every dirty chunk from the layer's tiles and their transforms relative to the scene.
+    pub unsafe fn rebuild(
+        &mut self,
+        tiles: &[Sprite],
+        transforms: &[Transform],
+        textures: &HashMap<String, LilahTexture>,
+        atlas: Option<&Atlas>,
+    ) {
+        for chunk in self.chunks.iter_mut().filter(|c| c.dirty) {
+            chunk.build(tiles, transforms, textures, atlas);
+        }
+    }
+
+    /// Draws the chunks overlapping view with the scene at position, returning the tiles drawn
+    /// and culled. Flushes the batch first, chunks draw straight from their own buffers.
+    pub unsafe fn draw(
+        &self,
+        batch: &mut Batch,
+        program: &ShaderProgram,
+        uniforms: &[(String, BoundUniform)],
+        blend: BlendMode,
+        position: Vec2,
+        view: (Vec2, Vec2),
+    ) -> (u32, u32) {
+        // bounds are relative to the scene, so move the view instead of every chunk
+        let view = (view.0 - position, view.1 - position);
+        let (visible, hidden): (Vec<&TileChunk>, Vec<&TileChunk>) = self
+            .chunks
+            .iter()
+            .partition(|c| math::overlaps(c.bounds, view));
+        let culled = hidden.iter().map(|c| c.tiles.len() as u32).sum();
+        if visible.is_empty() {
+            return (0, culled);
+        }
+
+        batch.flush();
+
+        let model = Mat4::from_translation(Vec3::new(position.x as f32, position.y as f32, 0.0));
+        let mvp = *math::PROJECTION_MATRIX * *math::VIEW_MATRIX * model;
+        program.apply();
+        let name = CString::new("mvp").unwrap();
+        gl::UniformMatrix4fv(
+            gl::GetUniformLocation(program.id, name.as_ptr()),
+            1,
+            gl::FALSE as GLboolean,
+            &mvp.to_cols_array()[0],
+        );
+        renderer::apply_uniforms(program.id, uniforms);
+        blend.apply();
+
+        let mut drawn = 0;
+        for chunk in visible {
+            for mesh in &chunk.meshes {
+                gl::ActiveTexture(gl::TEXTURE0);
+                gl::BindTexture(gl::TEXTURE_2D, mesh.texture);
+                mesh.vertex_array.bind();
+                gl::DrawElements(
+                    gl::TRIANGLES,
+                    (mesh.quads * 6) as GLsizei,
+                    gl::UNSIGNED_INT,
+                    ptr::null(),
+                );
+                batch.count_draw(mesh.quads as u32);
+            }
+            drawn += chunk.tiles.len() as u32;
+        }
+
+        gl::BindVertexArray(0);
+        if blend != BlendMode::Alpha {
+            BlendMode::Alpha.apply();
+        }
+        (drawn, culled)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 16 unit tiles with their min corners at each position.
+    fn tiles(positions: &[(f64, f64)]) -> Vec<(Vec2, Vec2)> {
+        positions
+            .iter()
+            .map(|&(x, y)| (Vec2::new(x, y), Vec2::new(x + 16.0, y + 16.0)))
+            .collect()
+    }
+
+    #[test]
+    fn empty_layers_have_no_chunks() {
+        let layer = ChunkedLayer::new(&[]);
+
+        assert!(layer.chunks.is_empty());
+        assert!(!layer.is_dirty());
+    }
+
+    #[test]
+    fn tiles_group_by_their_min_corner() {
+        // chunks are 32 tiles of 16, so 512 units a side
+        let bounds = tiles(&[(0.0, 0.0), (496.0, 0.0), (512.0, 0.0), (-16.0, 0.0), (0.0, 512.0)]);
+        let layer = ChunkedLayer::new(&bounds);
+
+        let grouped: Vec<Vec<usize>> = layer.chunks.iter().map(|c| c.tiles.clone()).collect();
+        assert_eq!(grouped, vec![vec![3], vec![0, 1], vec![4], vec![2]]);
+        assert_eq!(layer.chunk_of, vec![1, 1, 3, 0, 2]);
+        assert_eq!(layer.chunks[1].bounds, (Vec2::new(0.0, 0.0), Vec2::new(512.0, 16.0)));
+        assert!(layer.chunks.iter().all(|c| c.dirty));
+    }
+
+    #[test]
+    fn marking_a_tile_dirties_its_chunk() {
+        let bounds = tiles(&[(0.0, 0.0), (512.0, 0.0)]);
+        let mut layer = ChunkedLayer::new(&bounds);
+        for chunk in &mut layer.chunks {
+            chunk.dirty = false;
+        }
+
+        layer.mark_dirty(1);
+        layer.mark_dirty(7);
+        let dirty: Vec<bool> = layer.chunks.iter().map(|c| c.dirty).collect();
+        assert_eq!(dirty, vec![false, true]);
+    }
+
+    #[test]
+    fn empty_tiles_still_partition() {
+        let point = Vec2::new(3.5, -2.5);
+        let layer = ChunkedLayer::new(&[(point, point), (Vec2::ZERO, Vec2::ZERO)]);
+
+        // chunks fall back to one unit a side, ordered by cell
+        assert_eq!(layer.chunk_of, vec![1, 0]);
+        assert_eq!(layer.chunks.len(), 2);
+    }
+}
@@ -1,19 +1,21 @@
-use crate::components::{Debug, Line, Scene};
-use crate::gameobject::GameObject;
-use crate::renderer::Color;
+use crate::atlas::{Atlas, AtlasError, AtlasLayout, RgbaImage};
+use crate::components::{Camera, Component, Light2D, Line, Scene, Sfx, Shape};
+use crate::debug::DebugSpace;
+use crate::gameobject::{GameObject, SyncState};
+use crate::renderer::{Color, DrawKey};
+use crate::text::{BitmapFont, LilahFont};
 use crate::time::Timer;
 use crate::{
     application::{App, Scripting},
     components::{Rigidbody, SceneData, Sprite, Text, Transform},
     gameobject::GameObjectId,
-    math::Vec2,
+    math::{self, Vec2},
     renderer::LilahTexture,
     LilahError, LilahPanic,
 };
 use data2sound::decode_bytes;
 use debug_print::debug_println;
-use glam::{Mat4, Quat, Vec3};
-use image::Rgba;
+use indexmap::{IndexMap, IndexSet};
 use rusttype::Font;
 use serde_json;
 use std::cmp::Ordering;
@@ -21,7 +23,8 @@ use std::fs::File;
 use std::io::Read;
 use std::rc::Rc;
 use std::time::{Duration, Instant};
-use std::{collections::HashMap, path::Path};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
 
 #[macro_export]
 macro_rules! embed_texture {
@@ -36,12 +39,46 @@ macro_rules! embed_font {
     ($path: expr, $state:ident) => {
         $state.fonts.insert(
             $path.to_string(),
-            rusttype::Font::try_from_bytes(include_bytes!($path)).unwrap(),
+            $crate::text::LilahFont::TrueType(
+                rusttype::Font::try_from_bytes(include_bytes!($path)).unwrap(),
+            ),
         );
     };
 }
 pub use embed_font;
 
+#[macro_export]
+macro_rules! load_bitmap_font {
+    ($path: expr, $state:ident) => {
+        $state.load_bitmap_font($path, $path);
+    };
+}
+pub use load_bitmap_font;
+
+#[macro_export]
+macro_rules! embed_bitmap_font {
+    ($path: expr, [$($page: expr),*], $state:ident) => {
+        $state.load_bitmap_font_bytes($path, include_bytes!($path), &[$(include_bytes!($page)),*]);
+    };
+}
+pub use embed_bitmap_font;
+
+#[macro_export]
+macro_rules! load_grid_font {
+    ($path: expr, $cell: expr, $chars: expr, $state:ident) => {
+        $state.load_grid_font($path, $path, $cell, $chars);
+    };
+}
+pub use load_grid_font;
+
+#[macro_export]
+macro_rules! embed_grid_font {
+    ($path: expr, $cell: expr, $chars: expr, $state:ident) => {
+        $state.load_grid_font_bytes($path, include_bytes!($path), $cell, $chars);
+    };
+}
+pub use embed_grid_font;
+
 #[macro_export]
 macro_rules! embed_music {
     ($path: expr, $state:ident) => {
@@ -98,28 +135,109 @@ macro_rules! embed_scene_data {
 }
 pub use embed_scene_data;
 
+#[macro_export]
+macro_rules! embed_atlas {
+    ($layout: expr, [$($page: expr),*], $state:ident) => {
+        $state.load_atlas_bytes(include_bytes!($layout), &[$(include_bytes!($page)),*]);
+    };
+}
+pub use embed_atlas;
+
 pub struct StateUpdateContainer {
-    pub textures: Option<(String, image::ImageBuffer<Rgba<u8>, Vec<u8>>)>,
     pub sfx: Option<Vec<(String, i32)>>,
 }
 
+/// Lights with their world position, occluder outlines and ambient, gathered once per frame.
+type Lighting = (Vec<(Light2D, Vec2)>, Vec<Vec<Vec2>>, Color);
+
+/// One step of the draw order.
+struct DrawEntry {
+    uuid: String,
+    key: DrawKey,
+    /// Scene layer index and, in y-sorted sort layers, the single tile drawn.
+    scene: Option<(usize, Option<usize>)>,
+    /// Keyed by the gameobject's Text rather than its Sprite or Shape.
+    text: bool,
+    /// Position among all entries when built, breaks ties between equal keys.
+    order: usize,
+}
+
+impl DrawEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key
+            .partial_cmp(&other.key)
+            .unwrap_or(Ordering::Equal)
+            .then(self.order.cmp(&other.order))
+    }
+}
+
+/// Pre-created instances of a prefab. Free instances stay loaded but inactive.
+/// Shared with wren, Lilah.createPool and Lilah.spawn work on the same pools.
+pub struct Pool {
+    pub prefab: GameObject,
+    free: Vec<String>,
+    members: Vec<String>,
+}
+
+impl Pool {
+    /// Instances waiting to be spawned.
+    pub fn free(&self) -> &[String] {
+        &self.free
+    }
+
+    pub fn members(&self) -> &[String] {
+        &self.members
+    }
+}
+
 pub struct WorldState<'a> {
-    pub gameobjects: HashMap<String, GameObject>,
+    /// uuid -> gameobject. Insertion ordered so updates, physics and draw ties resolve the same every run.
+    /// Mutating through this directly skips dirty tracking, use get_mut/wrap_mut or mark_dirty.
+    pub gameobjects: IndexMap<String, GameObject>,
     pub textures: HashMap<String, LilahTexture>,
-    pub fonts: HashMap<String, Font<'a>>,
+    /// TrueType and bitmap fonts by the name Text uses.
+    pub fonts: HashMap<String, LilahFont<'a>>,
     pub music: HashMap<String, sdl2::mixer::Music<'a>>,
     pub sfx: HashMap<String, sdl2::mixer::Chunk>,
     pub scenes: HashMap<String, SceneData>,
+    /// When set, sprites and text draw from atlas pages for any texture id it has a region for.
+    pub atlas: Option<Atlas>,
+    /// name -> uuids. Kept in sync by insert and remove.
+    names: HashMap<String, Vec<String>>,
+    /// tag -> uuids. Kept in sync by insert and remove.
+    tags: HashMap<String, Vec<String>>,
+    /// uuids changed on the rust side since the last send to wren.
+    dirty: IndexSet<String>,
+    /// uuids removed on the rust side since the last send to wren.
+    removed: Vec<String>,
+    /// uuids added, removed, received from wren or marked dirty since the last draw.
+    /// Only these have their cached bounds recomputed.
+    changed: HashSet<String>,
+    /// pool name -> pool.
+    pools: HashMap<String, Pool>,
+    /// Set when a gameobject is added or removed, so the draw order is rebuilt.
+    order_changed: bool,
+    /// uuids spawned on the rust side since the last send, their wren data is reset on send.
+    spawned: Vec<String>,
 }
 
 impl<'a> WorldState<'a> {
+    fn lookup(&self, key: &str) -> Option<&GameObject> {
+        self.gameobjects.get(key).or_else(|| {
+            self.names
+                .get(key)
+                .and_then(|uuids| uuids.first())
+                .and_then(|uuid| self.gameobjects.get(uuid))
+        })
+    }
+
+    fn lookup_uuid(&self, key: &str) -> Option<String> {
+        self.lookup(key).map(|g| g.id.uuid.clone())
+    }
+
     pub fn wrap(&self, key: &str) -> Option<&GameObject> {
-        if let Some(g) = self
-            .gameobjects
-            .iter()
-            .find(|(_, k)| (k.id.name == key || k.id.uuid == key))
-        {
-            Some(g.1)
+        if let Some(g) = self.lookup(key) {
+            Some(g)
         } else {
             LilahError!(World, format!("Tried to get gameobject->{} got None", key));
             None
@@ -127,12 +245,9 @@ impl<'a> WorldState<'a> {
     }
 
     pub fn wrap_mut(&mut self, key: &str) -> Option<&mut GameObject> {
-        if let Some(g) = self
-            .gameobjects
-            .iter_mut()
-            .find(|(_, k)| (k.id.name == key || k.id.uuid == key))
-        {
-            Some(g.1)
+        if let Some(uuid) = self.lookup_uuid(key) {
+            self.touch(uuid.clone());
+            self.gameobjects.get_mut(&uuid)
         } else {
             LilahError!(World, format!("Tried to get gameobject->{} got None", key));
             None
@@ -140,32 +255,279 @@ impl<'a> WorldState<'a> {
     }
 
     pub fn get(&self, key: &str) -> &GameObject {
-        if let Some(g) = self
-            .gameobjects
-            .iter()
-            .find(|(_, k)| (k.id.name == key || k.id.uuid == key))
-        {
-            g.1
+        if let Some(g) = self.lookup(key) {
+            g
         } else {
             LilahPanic!(World, format!("Tried to get gameobject->{} got None", key))
         }
     }
 
     pub fn get_mut(&mut self, key: &str) -> &mut GameObject {
-        if let Some(g) = self
-            .gameobjects
-            .iter_mut()
-            .find(|(_, k)| (k.id.name == key || k.id.uuid == key))
-        {
-            g.1
+        if let Some(uuid) = self.lookup_uuid(key) {
+            self.touch(uuid.clone());
+            self.gameobjects.get_mut(&uuid).unwrap()
         } else {
             LilahPanic!(World, format!("Tried to get gameobject->{} got None", key))
         }
     }
 
+    /// Inserts or replaces a gameobject by uuid and updates the name and tag lookups.
+    /// Renaming a gameobject in place goes through rename, retagging needs a reinsert.
     pub fn insert(&mut self, g: &GameObject) {
-        let mut g2 = g.clone();
-        self.gameobjects.insert(g2.id.uuid.clone(), g2);
+        self.touch(g.id.uuid.clone());
+        self.receive(g);
+    }
+
+    /// insert without marking dirty, for gameobjects that already match the wren copy.
+    pub(crate) fn receive(&mut self, g: &GameObject) {
+        let g2 = g.clone();
+        self.changed.insert(g2.id.uuid.clone());
+        self.unindex(&g2.id.uuid);
+        self.index(&g2);
+        if self.gameobjects.insert(g2.id.uuid.clone(), g2).is_none() {
+            self.order_changed = true;
+        }
+    }
+
+    /// Removes a gameobject by uuid and drops it from the name and tag lookups.
+    pub fn remove(&mut self, uuid: &str) -> Option<GameObject> {
+        self.unindex(uuid);
+        for pool in self.pools.values_mut() {
+            pool.members.retain(|u| u != uuid);
+            pool.free.retain(|u| u != uuid);
+        }
+        self.dirty.shift_remove(uuid);
+        self.changed.insert(uuid.to_string());
+        let removed = self.gameobjects.shift_remove(uuid);
+        if removed.is_some() {
+            self.order_changed = true;
+            self.removed.push(uuid.to_string());
+        }
+        removed
+    }
+
+    /// Creates count inactive instances of prefab under name. They are loaded with everything else
+    /// so spawning them later skips gpu setup.
+    pub fn create_pool(&mut self, name: &str, prefab: &GameObject, count: usize) {
+        self.pools.insert(
+            name.to_string(),
+            Pool {
+                prefab: prefab.clone(),
+                free: vec![],
+                members: vec![],
+            },
+        );
+
+        for _ in 0..count {
+            self.grow_pool(name);
+        }
+    }
+
+    fn grow_pool(&mut self, name: &str) {
+        if let Some(pool) = self.pools.get(name) {
+            let mut g = pool.prefab.instance();
+            g.active = false;
+            let uuid = g.id.uuid.clone();
+            self.insert(&g);
+
+            let pool = self.pools.get_mut(name).unwrap();
+            pool.free.push(uuid.clone());
+            pool.members.push(uuid);
+        }
+    }
+
+    /// Takes a free instance from the pool, resets it to the prefab and activates it.
+    /// The pool grows by one if every instance is in use. Its behaviours start again, with their
+    /// wren data reset on the next send.
+    pub fn spawn(&mut self, name: &str) -> Option<&mut GameObject> {
+        if !self.pools.contains_key(name) {
+            LilahError!(World, format!("Tried to spawn from pool->{} got None", name));
+            return None;
+        }
+
+        if self.pools[name].free.is_empty() {
+            self.grow_pool(name);
+        }
+
+        let pool = self.pools.get_mut(name).unwrap();
+        let uuid = pool.free.pop()?;
+        self.dirty.insert(uuid.clone());
+        self.changed.insert(uuid.clone());
+        self.spawned.push(uuid.clone());
+
+        let g = self.gameobjects.get_mut(&uuid)?;
+        g.reset_from(&pool.prefab);
+        g.active = true;
+        Some(g)
+    }
+
+    pub fn pools(&self) -> impl Iterator<Item = (&String, &Pool)> {
+        self.pools.iter()
+    }
+
+    /// Registers a pool created in wren, keeping the members of one already made under name.
+    pub(crate) fn adopt_pool(&mut self, name: &str, prefab: &GameObject) {
+        match self.pools.get_mut(name) {
+            Some(pool) => pool.prefab = prefab.clone(),
+            None => {
+                self.pools.insert(
+                    name.to_string(),
+                    Pool {
+                        prefab: prefab.clone(),
+                        free: vec![],
+                        members: vec![],
+                    },
+                );
+            }
+        }
+    }
+
+    /// Adds a free instance instantiated in wren to a pool.
+    pub(crate) fn add_to_pool(&mut self, name: &str, uuid: &str) {
+        if let Some(pool) = self.pools.get_mut(name) {
+            if !pool.members.iter().any(|u| u == uuid) {
+                pool.members.push(uuid.to_string());
+                pool.free.push(uuid.to_string());
+            }
+        }
+    }
+
+    /// Marks an instance spawned in wren as in use. Wren has already reset it.
+    pub(crate) fn take_from_pool(&mut self, uuid: &str) {
+        for pool in self.pools.values_mut() {
+            pool.free.retain(|u| u != uuid);
+        }
+    }
+
+    /// Drains the uuids spawned on the rust side.
+    pub(crate) fn take_spawned(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.spawned)
+    }
+
+    /// Deactivates a pooled gameobject and returns it to its pool. Returns false if uuid is not pooled.
+    pub fn despawn(&mut self, uuid: &str) -> bool {
+        let pool = self
+            .pools
+            .values_mut()
+            .find(|p| p.members.iter().any(|u| u == uuid));
+
+        if let Some(pool) = pool {
+            if !pool.free.iter().any(|u| u == uuid) {
+                pool.free.push(uuid.to_string());
+            }
+            if let Some(g) = self.gameobjects.get_mut(uuid) {
+                g.active = false;
+                self.touch(uuid.to_string());
+            }
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Flags a gameobject to be sent to wren on the next send_state.
+    pub fn mark_dirty(&mut self, uuid: &str) {
+        if self.gameobjects.contains_key(uuid) {
+            self.touch(uuid.to_string());
+        }
+    }
+
+    /// Flags every gameobject to be sent to wren on the next send_state.
+    pub fn mark_all_dirty(&mut self) {
+        self.dirty.extend(self.gameobjects.keys().cloned());
+        self.changed.extend(self.gameobjects.keys().cloned());
+    }
+
+    /// Marks a gameobject to be sent to wren and to have its bounds recomputed.
+    fn touch(&mut self, uuid: String) {
+        self.changed.insert(uuid.clone());
+        self.dirty.insert(uuid);
+    }
+
+    /// Drains the dirty and removed uuids, in that order.
+    pub(crate) fn take_changes(&mut self) -> (IndexSet<String>, Vec<String>) {
+        (
+            std::mem::take(&mut self.dirty),
+            std::mem::take(&mut self.removed),
+        )
+    }
+
+    fn index(&mut self, g: &GameObject) {
+        let uuids = self.names.entry(g.id.name.clone()).or_insert_with(Vec::new);
+        if !uuids.contains(&g.id.uuid) {
+            uuids.push(g.id.uuid.clone());
+        }
+
+        for tag in &g.tags {
+            let uuids = self.tags.entry(tag.clone()).or_insert_with(Vec::new);
+            if !uuids.contains(&g.id.uuid) {
+                uuids.push(g.id.uuid.clone());
+            }
+        }
+    }
+
+    fn unindex(&mut self, uuid: &str) {
+        if let Some(old) = self.gameobjects.get(uuid) {
+            Self::unindex_key(&mut self.names, &old.id.name, uuid);
+            for tag in &old.tags {
+                Self::unindex_key(&mut self.tags, tag, uuid);
+            }
+        }
+    }
+
+    fn unindex_key(index: &mut HashMap<String, Vec<String>>, key: &str, uuid: &str) {
+        let empty = match index.get_mut(key) {
+            Some(uuids) => {
+                uuids.retain(|u| u != uuid);
+                uuids.is_empty()
+            }
+            None => false,
+        };
+
+        if empty {
+            index.remove(key);
+        }
+    }
+
+    /// Gets every gameobject with the tag without cloning.
+    pub fn find_all_with_tag(&self, tag: &str) -> impl Iterator<Item = &GameObject> {
+        let gameobjects = &self.gameobjects;
+        self.tags
+            .get(tag)
+            .into_iter()
+            .flatten()
+            .filter_map(move |uuid| gameobjects.get(uuid))
+    }
+
+    /// Gets every gameobject with Component T without cloning.
+    /// Unlike tags there is no index, this checks every gameobject. Keep it out of per frame code,
+    /// or tag the gameobjects and use find_all_with_tag.
+    pub fn find_all_with_component<T: 'static + Component>(
+        &self,
+    ) -> impl Iterator<Item = &GameObject> {
+        self.gameobjects.values().filter(|g| g.has::<T>())
+    }
+
+    /// tag -> uuids of every tagged gameobject.
+    pub fn tag_index(&self) -> &HashMap<String, Vec<String>> {
+        &self.tags
+    }
+
+    pub fn name_index(&self) -> &HashMap<String, Vec<String>> {
+        &self.names
+    }
+
+    /// Renames a gameobject and moves it in the name lookup.
+    pub fn rename(&mut self, key: &str, name: &str) {
+        if let Some(uuid) = self.lookup_uuid(key) {
+            let g = self.gameobjects.get_mut(&uuid).unwrap();
+            let old = std::mem::replace(&mut g.id.name, name.to_string());
+            Self::unindex_key(&mut self.names, &old, &uuid);
+            self.names.entry(name.to_string()).or_default().push(uuid.clone());
+            self.touch(uuid);
+        } else {
+            LilahError!(World, format!("Tried to rename gameobject->{} got None", key));
+        }
     }
 
     pub fn load_texture(&mut self, file: &str, wrap: u32, filter: u32, _app: &App) {
@@ -210,6 +572,143 @@ impl<'a> WorldState<'a> {
         debug_println!("Texture loaded: {}", name);
     }
 
+    /// Packs every loaded texture into a runtime atlas so sprites sharing a page batch together.
+    /// Textures relying on REPEAT wrapping should be loaded after this instead.
+    pub fn build_atlas(&mut self, page_size: u32) {
+        let mut atlas = Atlas::new(page_size);
+
+        let mut names: Vec<&String> = self.textures.keys().collect();
+        names.sort();
+        for name in names {
+            let img = unsafe { self.textures[name].read_pixels() };
+            if unsafe { atlas.insert(name, &img) }.is_none() {
+                let e = AtlasError::TooLarge(name.clone());
+                LilahError!(Atlas, e);
+            }
+        }
+
+        self.atlas = Some(atlas);
+    }
+
+    /// Loads an atlas saved by AtlasBuilder::save. Pages are expected next to the layout file.
+    pub fn load_atlas(&mut self, layout: &str) {
+        let result = (|| -> Result<Atlas, AtlasError> {
+            let path = Path::new(layout);
+            let parsed: AtlasLayout = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+            let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+
+            let mut pages: Vec<RgbaImage> = vec![];
+            for i in 0..parsed.pages {
+                let page = path.with_file_name(format!("{}_{}.png", stem, i));
+                pages.push(image::open(page)?.into_rgba8());
+            }
+
+            Ok(unsafe { Atlas::from_pages(parsed, pages) })
+        })();
+
+        match result {
+            Ok(atlas) => {
+                debug_println!("Atlas loaded: {}", layout);
+                self.atlas = Some(atlas);
+            }
+            Err(e) => {
+                LilahError!(Atlas, e);
+            }
+        }
+    }
+
+    pub fn load_atlas_bytes(&mut self, layout: &[u8], pages: &[&[u8]]) {
+        let result = (|| -> Result<Atlas, AtlasError> {
+            let parsed: AtlasLayout = serde_json::from_slice(layout)?;
+
+            let mut images: Vec<RgbaImage> = vec![];
+            for page in pages {
+                images.push(image::load_from_memory(page)?.into_rgba8());
+            }
+
+            Ok(unsafe { Atlas::from_pages(parsed, images) })
+        })();
+
+        match result {
+            Ok(atlas) => {
+                debug_println!("Atlas loaded");
+                self.atlas = Some(atlas);
+            }
+            Err(e) => {
+                LilahError!(Atlas, e);
+            }
+        }
+    }
+
+    /// Adds a TrueType font under name, as fonts.insert did before bitmap fonts.
+    pub fn insert_font(&mut self, name: &str, font: Font<'a>) {
+        self.fonts.insert(name.to_string(), font.into());
+    }
+
+    /// The TrueType font named name, None if missing or a bitmap font.
+    pub fn font(&self, name: &str) -> Option<&Font<'a>> {
+        self.fonts.get(name).and_then(LilahFont::truetype)
+    }
+
+    /// Loads a BMFont .fnt in the text or XML format, its pages are read relative to it.
+    pub fn load_bitmap_font(&mut self, name: &str, file: &str) {
+        match BitmapFont::load(file) {
+            Ok(font) => {
+                debug_println!("Font loaded: {}", name);
+                self.fonts.insert(name.to_string(), LilahFont::Bitmap(font));
+            }
+            Err(e) => {
+                LilahError!(BitmapFont, e);
+            }
+        }
+    }
+
+    /// BMFont text or XML with its page images in page id order.
+    pub fn load_bitmap_font_bytes(&mut self, name: &str, source: &[u8], pages: &[&[u8]]) {
+        match BitmapFont::from_bytes(source, pages) {
+            Ok(font) => {
+                debug_println!("Font loaded: {}", name);
+                self.fonts.insert(name.to_string(), LilahFont::Bitmap(font));
+            }
+            Err(e) => {
+                LilahError!(BitmapFont, e);
+            }
+        }
+    }
+
+    /// Loads a sheet of cell sized glyphs, laid out left to right and top to bottom in the order of chars.
+    pub fn load_grid_font(&mut self, name: &str, file: &str, cell: (u32, u32), chars: &str) {
+        match image::open(file) {
+            Ok(sheet) => {
+                debug_println!("Font loaded: {}", name);
+                let font = BitmapFont::from_grid(sheet.into_rgba8(), cell, chars);
+                self.fonts.insert(name.to_string(), LilahFont::Bitmap(font));
+            }
+            Err(e) => {
+                LilahError!(BitmapFont, e);
+            }
+        }
+    }
+
+    pub fn load_grid_font_bytes(
+        &mut self,
+        name: &str,
+        source: &[u8],
+        cell: (u32, u32),
+        chars: &str,
+    ) {
+        match image::load_from_memory(source) {
+            Ok(sheet) => {
+                debug_println!("Font loaded: {}", name);
+                let font = BitmapFont::from_grid(sheet.into_rgba8(), cell, chars);
+                self.fonts.insert(name.to_string(), LilahFont::Bitmap(font));
+            }
+            Err(e) => {
+                LilahError!(BitmapFont, e);
+            }
+        }
+    }
+
     pub fn load_music(&mut self, name: &str, source: &str) {
         match sdl2::mixer::Music::from_file(Path::new(source)) {
             Ok(music) => {
@@ -311,7 +810,17 @@ pub struct World<'a> {
     pub setup_callback: Option<Box<dyn Fn(&mut App, &mut WorldState, &mut Scripting)>>,
     pub start_callback: Option<Box<dyn Fn(&mut App, &mut WorldState, &mut Scripting)>>,
     pub update_callback: Option<Box<dyn Fn(&mut App, &mut WorldState, &mut Scripting)>>,
-    sort_fudge: Vec<(String, u32)>,
+    /// Scene layers, tiles and anything outside y-sorted layers, sorted when the order is rebuilt.
+    static_draws: Vec<DrawEntry>,
+    /// Sprites, shapes and text in y-sorted layers, keyed and sorted again every frame.
+    moving_draws: Vec<DrawEntry>,
+    /// Scenes with y-sorted tiles and the y their tiles were keyed at.
+    sorted_scenes: Vec<(String, f64)>,
+    /// Indices into static_draws followed by moving_draws, in draw order.
+    draw_order: Vec<usize>,
+    /// uuid -> world space bounds around its Sprite, Text and Line, kept for culling.
+    /// Recomputed only for gameobjects in WorldState::changed.
+    bounds: HashMap<String, (Vec2, Vec2)>,
 }
 
 impl<'a> World<'a> {
@@ -319,17 +828,30 @@ impl<'a> World<'a> {
     pub fn new() -> Self {
         Self {
             state: WorldState {
-                gameobjects: HashMap::new(),
+                gameobjects: IndexMap::new(),
                 textures: HashMap::new(),
                 fonts: HashMap::new(),
                 music: HashMap::new(),
                 sfx: HashMap::new(),
                 scenes: HashMap::new(),
+                atlas: None,
+                names: HashMap::new(),
+                tags: HashMap::new(),
+                dirty: IndexSet::new(),
+                removed: vec![],
+                changed: HashSet::new(),
+                pools: HashMap::new(),
+                order_changed: true,
+                spawned: vec![],
             },
             setup_callback: None,
             start_callback: None,
             update_callback: None,
-            sort_fudge: vec![],
+            static_draws: vec![],
+            moving_draws: vec![],
+            sorted_scenes: vec![],
+            draw_order: vec![],
+            bounds: HashMap::new(),
         }
     }
 
@@ -349,13 +871,12 @@ impl<'a> World<'a> {
     }
 
     pub fn run(mut self, app: &mut App, scripting: &mut Scripting) -> World<'a> {
-        let mut camera_pos = Vec2::new(-1.0, -1000.0);
         self.state.insert(
             &GameObject::new("Camera".to_string())
                 .with_specific::<Transform>(Transform::new(Vec2::new(0f64, 0f64)))
+                .with_specific::<Camera>(Camera::new())
                 .build(),
         );
-        let camera_id = self.state.wrap("Camera").unwrap().id.clone();
 
         if self.setup_callback.is_some() {
             self.setup_callback.as_mut().unwrap()(app, &mut self.state, scripting);
@@ -367,11 +888,13 @@ impl<'a> World<'a> {
             i.load(
                 app,
                 &self.state.textures,
+                self.state.atlas.as_ref(),
                 &self.state.fonts,
                 &self.state.sfx,
                 &self.state.scenes,
             );
         }
+        self.state.mark_all_dirty();
 
         if self.start_callback.is_some() {
             self.start_callback.as_ref().unwrap()(app, &mut self.state, scripting);
@@ -388,30 +911,6 @@ impl<'a> World<'a> {
             }
             scripting.handle_input(app, &mut self.state);
 
-            let camera_pos_temp = self.state.gameobjects[&camera_id.uuid]
-                .get::<Transform>()
-                .position;
-            let camera_scale_temp = self.state.gameobjects[&camera_id.uuid]
-                .get::<Transform>()
-                .scale;
-            let camera_rot_temp = self.state.gameobjects[&camera_id.uuid]
-                .get::<Transform>()
-                .rotation;
-            if camera_pos != camera_pos_temp {
-                camera_pos = camera_pos_temp;
-                unsafe {
-                    *crate::math::VIEW_MATRIX = Mat4::from_scale_rotation_translation(
-                        Vec3::new(camera_scale_temp.x as f32, camera_scale_temp.y as f32, 1f32),
-                        Quat::from_rotation_z(camera_rot_temp),
-                        Vec3::new(
-                            -camera_pos.x as f32 * camera_scale_temp.x as f32,
-                            -camera_pos.y as f32 * camera_scale_temp.y as f32,
-                            0.0,
-                        ),
-                    );
-                }
-            }
-
             //let a = Instant::now();
             scripting.tick(app, &mut self.state);
             //println!("tick ->{}", a.elapsed().as_secs_f64());
@@ -420,9 +919,13 @@ impl<'a> World<'a> {
                 self.update_callback.as_mut().unwrap()(app, &mut self.state, scripting);
             }
 
+            let before: Vec<SyncState> =
+                self.state.gameobjects.values().map(GameObject::sync_state).collect();
             self.update(app);
+            self.update_cameras(app);
 
             self.update_go(app);
+            self.mark_ticked(&before);
 
             scripting.send_state(app, &mut self.state);
 
@@ -448,39 +951,28 @@ impl<'a> World<'a> {
             state_updates.push(i.load(
                 app,
                 &self.state.textures,
+                self.state.atlas.as_ref(),
                 &self.state.fonts,
                 &self.state.sfx,
                 &self.state.scenes,
             ));
+            if !i.active {
+                continue;
+            }
             i.update(&mut app);
 
             if let Some(spr) = i.wrap_component_mut::<Sprite>() {
-                app.sort_dirty = spr.check_dirty();
+                app.sort_dirty |= spr.check_dirty();
             }
             if let Some(line) = i.wrap_component_mut::<Line>() {
-                app.sort_dirty = line.check_dirty();
+                app.sort_dirty |= line.check_dirty();
+            }
+            if let Some(shape) = i.wrap_component_mut::<Shape>() {
+                app.sort_dirty |= shape.check_dirty();
             }
         }
 
         for su in state_updates {
-            if let Some(ftu) = su.textures {
-                if let Some(t) = self.state.textures.get_mut(&ftu.0) {
-                    unsafe {
-                        let _ = t.load_as_dyn(ftu.1);
-                    }
-                } else {
-                    let t = unsafe {
-                        let mut nt = LilahTexture::new();
-
-                        nt.set_wrapping(gl::REPEAT);
-                        nt.set_filtering(gl::NEAREST);
-
-                        let _ = nt.load_as_dyn(ftu.1);
-                        nt
-                    };
-                    self.state.textures.insert(ftu.0, t);
-                }
-            }
             if let Some(stu) = su.sfx {
                 for i in stu {
                     if let Some(j) = self.state.sfx.get_mut(&i.0) {
@@ -491,55 +983,484 @@ impl<'a> World<'a> {
         }
     }
 
-    pub fn draw(&mut self, app: &mut App) {
-        if app.sort_dirty || self.state.gameobjects.len() != self.sort_fudge.len() {
-            self.sort_fudge = vec![];
-            for i in &self.state.gameobjects {
-                if let Some(s) = i.1.wrap_component::<Scene>() {
-                    for j in 0..s.tiles.len() {
-                        self.sort_fudge.push((i.0.clone(), j as u32));
-                    }
-                } else if let Some(s) = i.1.wrap_component::<Sprite>() {
-                    if i.1.start {
-                        self.sort_fudge.push((i.0.clone(), s.get_sort()));
+    /// Marks gameobjects still starting up, and those whose ticked values differ from before,
+    /// to be sent to wren. before is in gameobject order from the start of the rust update.
+    fn mark_ticked(&mut self, before: &[SyncState]) {
+        for (index, (uuid, g)) in self.state.gameobjects.iter().enumerate() {
+            if !g.init
+                || (g.active && !g.start)
+                || before.get(index).map_or(true, |b| *b != g.sync_state())
+            {
+                self.state.dirty.insert(uuid.clone());
+                self.state.changed.insert(uuid.clone());
+            }
+        }
+    }
+
+    /// Keys for a gameobject's Sprite, or Shape without one, and its Text.
+    fn object_keys(app: &App, g: &GameObject) -> (Option<DrawKey>, Option<DrawKey>) {
+        let trans = g.wrap_component::<Transform>();
+        let body = if let Some(s) = g.wrap_component::<Sprite>() {
+            let y = trans.map_or(0.0, |t| s.sort_y(t));
+            g.start.then(|| app.draw_key(&s.sort_layer, y, s.get_sort()))
+        } else {
+            g.wrap_component::<Shape>().map(|s| {
+                let y = trans.map_or(0.0, |t| s.sort_y(t));
+                app.draw_key(&s.sort_layer, y, s.get_sort())
+            })
+        };
+        let text = g.wrap_component::<Text>().map(|t| {
+            let y = trans.map_or(0.0, |tr| t.sort_y(tr));
+            app.draw_key(&t.sort_layer, y, t.get_sort())
+        });
+        (body, text)
+    }
+
+    /// Splits draw entries into static ones, sorted here, and moving ones keyed every frame.
+    fn build_draw_order(&mut self, app: &App) {
+        let y_sorted = |layer: &str| {
+            app.sort_layers
+                .get(app.sort_layer_index(layer))
+                .is_some_and(|l| l.y_sort)
+        };
+        self.static_draws.clear();
+        self.moving_draws.clear();
+        self.sorted_scenes.clear();
+
+        let mut order = 0;
+        for (uuid, g) in &self.state.gameobjects {
+            let mut entry = |key: DrawKey, scene, text| {
+                order += 1;
+                DrawEntry {
+                    uuid: uuid.clone(),
+                    key,
+                    scene,
+                    text,
+                    order,
+                }
+            };
+
+            if let Some(s) = g.wrap_component::<Scene>() {
+                let trans = g.wrap_component::<Transform>();
+                for j in 0..s.tiles.len() {
+                    let layer = s.layer_sort_layers.get(&j).map_or("world", |l| l.as_str());
+                    match trans {
+                        Some(t) if y_sorted(layer) => {
+                            if self.sorted_scenes.last().map_or(true, |(u, _)| u != uuid) {
+                                self.sorted_scenes.push((uuid.clone(), t.position.y));
+                            }
+                            for tile in 0..s.tiles[j].len() {
+                                let y = s.tile_sort_y(j, tile, t);
+                                let key = app.draw_key(layer, y, j as u32);
+                                self.static_draws.push(entry(key, Some((j, Some(tile))), false));
+                            }
+                        }
+                        _ => {
+                            let key = app.draw_key(layer, 0.0, j as u32);
+                            self.static_draws.push(entry(key, Some((j, None)), false));
+                        }
                     }
                 }
-                if let Some(t) = i.1.wrap_component::<Text>() {
-                    self.sort_fudge.push((i.0.clone(), t.get_sort()));
+                continue;
+            }
+
+            let layers = (
+                g.wrap_component::<Sprite>()
+                    .map(|s| s.sort_layer.as_str())
+                    .or(g.wrap_component::<Shape>().map(|s| s.sort_layer.as_str())),
+                g.wrap_component::<Text>().map(|t| t.sort_layer.as_str()),
+            );
+            let (body, text) = Self::object_keys(app, g);
+            for (key, layer, text) in [(body, layers.0, false), (text, layers.1, true)] {
+                let (Some(key), Some(layer)) = (key, layer) else {
+                    continue;
+                };
+                let e = entry(key, None, text);
+                if y_sorted(layer) {
+                    self.moving_draws.push(e);
+                } else {
+                    self.static_draws.push(e);
                 }
+            }
+        }
 
-                // if let Some(s) = i.1.wrap_component::<Line>() {
-                //     self.sort_fudge.push((i.0.clone(), s.get_sort()));
-                // }
+        self.static_draws.sort_by(DrawEntry::cmp);
+        self.draw_order = (0..self.static_draws.len()).collect();
+    }
+
+    /// Keys and sorts the moving entries again, then merges them into the sorted static ones.
+    fn merge_draw_order(&mut self, app: &App) {
+        if self.moving_draws.is_empty() {
+            return;
+        }
+        for e in &mut self.moving_draws {
+            if let Some(g) = self.state.gameobjects.get(&e.uuid) {
+                let (body, text) = Self::object_keys(app, g);
+                if let Some(key) = if e.text { text } else { body } {
+                    e.key = key;
+                }
             }
-            self.sort_fudge.sort_by(|a, b| a.1.cmp(&b.1));
+        }
+        self.moving_draws.sort_by(DrawEntry::cmp);
+
+        let offset = self.static_draws.len();
+        self.draw_order.clear();
+        let (mut s, mut m) = (0, 0);
+        while s < offset || m < self.moving_draws.len() {
+            let take_static = match (self.static_draws.get(s), self.moving_draws.get(m)) {
+                (Some(a), Some(b)) => a.cmp(b) != Ordering::Greater,
+                (a, _) => a.is_some(),
+            };
+            if take_static {
+                self.draw_order.push(s);
+                s += 1;
+            } else {
+                self.draw_order.push(offset + m);
+                m += 1;
+            }
+        }
+    }
+
+    fn draw_entry(&self, index: usize) -> &DrawEntry {
+        match index.checked_sub(self.static_draws.len()) {
+            Some(m) => &self.moving_draws[m],
+            None => &self.static_draws[index],
+        }
+    }
+
+    pub fn draw(&mut self, app: &mut App) {
+        app.begin_render();
+
+        // static keys only change with sort settings, added or removed gameobjects or moved scenes
+        let scenes_moved = self.sorted_scenes.iter().any(|(uuid, y)| {
+            self.state
+                .gameobjects
+                .get(uuid)
+                .and_then(|g| g.wrap_component::<Transform>())
+                .map_or(true, |t| t.position.y != *y)
+        });
+        if app.sort_dirty || self.state.order_changed || scenes_moved {
+            self.build_draw_order(app);
             app.sort_dirty = false;
+            self.state.order_changed = false;
+        }
+        self.merge_draw_order(app);
+
+        // immediate shapes are keyed once, then sorted by those keys
+        let mut shapes: Vec<(DrawKey, Shape, Transform)> = std::mem::take(&mut app.shapes)
+            .into_iter()
+            .map(|(s, t)| (app.draw_key(&s.sort_layer, s.sort_y(&t), s.get_sort()), s, t))
+            .collect();
+        shapes.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+
+        self.update_bounds();
+        if app.debug.colliders {
+            self.draw_colliders(app);
+        }
+
+        let lighting = self.lighting(app);
+        let cameras = self.cameras();
+        if cameras.is_empty() {
+            self.draw_view(app, lighting.as_ref(), &shapes);
+        }
+        for (camera, trans) in &cameras {
+            camera.apply(app, trans);
+            self.draw_view(app, lighting.as_ref(), &shapes);
+        }
+
+        // screen space debug draws cover the whole view once
+        app.set_camera_viewport([0.0, 0.0, 1.0, 1.0]);
+        unsafe {
+            app.debug.draw(DebugSpace::Screen);
+        }
+        let dt = app.delta_time();
+        app.debug.tick(dt);
+
+        // the primary camera stays current so screen_to_world and collisions see it between frames
+        if let Some((camera, trans)) = cameras.first() {
+            camera.apply(app, trans);
+        }
+        app.end_render(&self.state.textures);
+    }
+
+    /// Outlines every active rigidbody, red while colliding.
+    fn draw_colliders(&self, app: &mut App) {
+        for i in self.state.gameobjects.values().filter(|i| i.active) {
+            if let Some(body) = i.wrap_component::<Rigidbody>() {
+                let color = match body.colliding {
+                    Some(_) => Color::new(1.0, 0.2, 0.2, 1.0),
+                    None => Color::new(0.2, 1.0, 0.2, 1.0),
+                };
+                app.debug
+                    .polygon(body.collider_corners(), color, DebugSpace::World, 0.0);
+            }
+        }
+    }
+
+    /// Lights at their Transform and pivot, occluder outlines and the ambient for the light map.
+    /// None when no Light2D or Scene ambient is active, so unlit games skip the pass.
+    fn lighting(&self, app: &App) -> Option<Lighting> {
+        let mut lights = vec![];
+        let mut occluders = vec![];
+        let mut ambient = None;
+
+        for i in self.state.gameobjects.values().filter(|i| i.active) {
+            // a light with no radius lights nothing and would divide by zero in the shader
+            let light = i.wrap_component::<Light2D>().filter(|l| l.radius > 0.0);
+            if let (Some(light), Some(t)) = (light, i.wrap_component::<Transform>()) {
+                // drawn where a sprite on the same Transform is, pivot included
+                lights.push((light.clone(), t.position + t.pivot));
+            }
+            if let Some(body) = i.wrap_component::<Rigidbody>() {
+                if body.occluder {
+                    occluders.push(body.collider_corners());
+                }
+            }
+            if let Some(scene) = i.wrap_component::<Scene>() {
+                if ambient.is_none() {
+                    ambient = scene.ambient.clone();
+                }
+                if scene.occluders {
+                    occluders.extend(scene.rigidbodies.iter().map(|r| r.collider_corners()));
+                }
+            }
+        }
+
+        if lights.is_empty() && ambient.is_none() {
+            return None;
+        }
+        Some((lights, occluders, ambient.unwrap_or(app.lights.ambient.clone())))
+    }
+
+    /// Active cameras and their Transforms, in draw order.
+    fn cameras(&self) -> Vec<(Camera, Transform)> {
+        let mut cameras: Vec<(Camera, Transform)> = self
+            .state
+            .gameobjects
+            .values()
+            .filter(|i| i.active)
+            .filter_map(|i| {
+                match (i.wrap_component::<Camera>(), i.wrap_component::<Transform>()) {
+                    (Some(c), Some(t)) => Some((c.clone(), t.clone())),
+                    _ => None,
+                }
+            })
+            .collect();
+        // stable, equal orders keep gameobject insertion order
+        cameras.sort_by_key(|c| c.0.order);
+        cameras
+    }
+
+    /// Caches world space bounds so every camera culls against the same boxes.
+    /// Only gameobjects changed since the last draw are recomputed, the rest keep theirs.
+    fn update_bounds(&mut self) {
+        for uuid in std::mem::take(&mut self.state.changed) {
+            let bounds = self
+                .state
+                .gameobjects
+                .get(&uuid)
+                .filter(|g| g.active)
+                .and_then(Self::object_bounds);
+
+            match bounds {
+                Some(b) => {
+                    self.bounds.insert(uuid, b);
+                }
+                None => {
+                    self.bounds.remove(&uuid);
+                }
+            }
         }
+    }
 
-        for index in self.sort_fudge.iter() {
-            let i = self.state.gameobjects.get(&index.0);
-            if let Some(i) = i {
+    /// World space bounds around a gameobject's Sprite, Text, Line and Shape.
+    fn object_bounds(g: &GameObject) -> Option<(Vec2, Vec2)> {
+        let trans = g.wrap_component::<Transform>()?;
+        [
+            // parallax sprites move per camera and cull themselves
+            g.wrap_component::<Sprite>()
+                .filter(|s| !s.has_parallax())
+                .map(|s| s.world_bounds(trans)),
+            g.wrap_component::<Text>().and_then(|t| t.world_bounds(trans)),
+            g.wrap_component::<Line>().and_then(|l| l.world_bounds()),
+            g.wrap_component::<Shape>().and_then(|s| s.world_bounds(trans)),
+        ]
+        .into_iter()
+        .flatten()
+        .reduce(math::union)
+    }
+
+    /// Draws everything inside the current view, with the frame's immediate shapes sorted by key.
+    fn draw_view(
+        &self,
+        app: &mut App,
+        lighting: Option<&Lighting>,
+        shapes: &[(DrawKey, Shape, Transform)],
+    ) {
+        let view = math::view_bounds();
+        let (mut drawn, mut culled) = (0, 0);
+        // immediate shapes go in before the first entry sorted above them
+        let mut next_shape = 0;
+        let mut draw_shapes = |app: &mut App, until: Option<DrawKey>| {
+            let (mut drawn, mut culled) = (0, 0);
+            while let Some((key, shape, trans)) = shapes.get(next_shape) {
+                if until.is_some_and(|u| *key >= u) {
+                    break;
+                }
+                next_shape += 1;
+
+                if shape
+                    .world_bounds(trans)
+                    .map_or(true, |b| math::overlaps(b, view))
+                {
+                    shape.draw(app, trans);
+                    drawn += 1;
+                } else {
+                    culled += 1;
+                }
+            }
+            (drawn, culled)
+        };
+
+        for index in self.draw_order.iter().map(|&i| self.draw_entry(i)) {
+            let (d, c) = draw_shapes(app, Some(index.key));
+            drawn += d;
+            culled += c;
+            let i = self.state.gameobjects.get(&index.uuid);
+            if let Some(i) = i.filter(|i| i.active) {
                 if let Some(trans) = i.wrap_component::<Transform>() {
+                    let visible = self
+                        .bounds
+                        .get(&index.uuid)
+                        .map_or(true, |b| math::overlaps(*b, view));
+
                     if let Some(s) = i.wrap_component::<Sprite>() {
-                        s.draw(app, &self.state.textures, trans);
+                        if s.has_parallax() {
+                            let offsets = math::parallax_offsets(
+                                s.world_bounds(trans),
+                                s.parallax,
+                                s.repeat,
+                                view,
+                            );
+                            if offsets.is_empty() {
+                                culled += 1;
+                            }
+                            for offset in offsets {
+                                let mut moved = trans.clone();
+                                moved.position += offset;
+                                s.draw(
+                                    app,
+                                    &self.state.textures,
+                                    self.state.atlas.as_ref(),
+                                    &moved,
+                                );
+                                drawn += 1;
+                            }
+                        } else if visible {
+                            s.draw(app, &self.state.textures, self.state.atlas.as_ref(), trans);
+                            drawn += 1;
+                        } else {
+                            culled += 1;
+                        }
+                    }
+                    if let Some(s) = i.wrap_component::<Shape>() {
+                        if visible {
+                            s.draw(app, trans);
+                            drawn += 1;
+                        } else {
+                            culled += 1;
+                        }
                     }
                     if let Some(t) = i.wrap_component::<Text>() {
-                        t.draw(app, &self.state.textures, trans);
+                        if visible {
+                            t.draw(app, &self.state.textures, trans);
+                            drawn += 1;
+                        } else {
+                            culled += 1;
+                        }
                     }
                     if let Some(s) = i.wrap_component::<Scene>() {
-                        s.draw(index.1 as usize, app, &self.state.textures, trans);
+                        match index.scene {
+                            Some((layer, Some(tile))) => s.draw_tile(
+                                (layer, tile),
+                                app,
+                                &self.state.textures,
+                                self.state.atlas.as_ref(),
+                                trans,
+                                view,
+                            ),
+                            Some((layer, None)) => {
+                                s.draw(layer, app, &self.state.textures, trans, view)
+                            }
+                            None => {}
+                        }
                     }
                     if let Some(s) = i.wrap_component::<Line>() {
-                        s.draw(trans);
+                        if visible {
+                            unsafe {
+                                app.batch.flush();
+                            }
+                            s.draw(app, &self.state.textures, trans);
+                            drawn += 1;
+                        } else {
+                            culled += 1;
+                        }
                     }
                 }
             }
         }
+        let (d, c) = draw_shapes(app, None);
+        app.batch.count(drawn + d, culled + c);
         unsafe {
-            for line in crate::application::LINES.iter() {
-                Debug::draw_line(line.0, line.1, line.2.clone());
+            app.batch.flush();
+            if let Some((lights, occluders, ambient)) = lighting {
+                app.lights.render(lights, occluders, ambient);
+            }
+            app.debug.draw(DebugSpace::World);
+        }
+    }
+
+    /// Follows targets, clamps to bounds and advances shake for every active camera.
+    fn update_cameras(&mut self, app: &App) {
+        let dt = app.delta_time();
+        let view_size = app.get_view_size();
+        let keys: Vec<String> = self
+            .state
+            .gameobjects
+            .iter()
+            .filter(|(_, i)| i.active && i.has::<Camera>() && i.has::<Transform>())
+            .map(|(k, _)| k.clone())
+            .collect();
+
+        for key in keys {
+            let camera = self.state.gameobjects[&key].get::<Camera>().clone();
+
+            let target = camera
+                .target
+                .as_ref()
+                .and_then(|t| self.state.lookup(t))
+                .and_then(|g| g.wrap_component::<Transform>())
+                .map(|t| t.position);
+            let bounds = match &camera.bounds_scene {
+                Some(scene) => self.state.lookup(scene).and_then(|g| {
+                    match (g.wrap_component::<Scene>(), g.wrap_component::<Transform>()) {
+                        (Some(s), Some(t)) => s.extents(t),
+                        _ => None,
+                    }
+                }),
+                None => camera.bounds,
+            };
+
+            let go = self.state.gameobjects.get_mut(&key).unwrap();
+            let trans = go.get_mut::<Transform>();
+            if let Some(target) = target {
+                trans.position = camera.follow(trans.position, target, dt);
             }
-            crate::application::LINES.clear();
+            if let Some(bounds) = bounds {
+                let half = camera.half_extents(view_size, trans);
+                trans.position = Camera::clamp(trans.position, half, bounds);
+            }
+            go.get_mut::<Camera>().update(dt);
         }
     }
 
@@ -576,12 +1497,14 @@ impl<'a> World<'a> {
                         body.colliding = Some(coll.1.clone());
                         if g2_is_solid {
                             body.position.x -= ((body.velocity) * 1.5 * coll.2 .1.magnitude()).x;
-                            unsafe {
-                                crate::application::LINES.push((
+                            if app.debug.colliders {
+                                app.debug.arrow(
                                     body.position,
                                     body.position - ((body.velocity) * coll.2 .1.magnitude()),
                                     Color::WHITE,
-                                ));
+                                    DebugSpace::World,
+                                    0.0,
+                                );
                             }
                         }
 
@@ -605,12 +1528,14 @@ impl<'a> World<'a> {
                         body.colliding = Some(coll.1.clone());
                         if g2_is_solid {
                             body.position.y -= ((body.velocity) * 1.5 * coll.2 .1.magnitude()).y;
-                            unsafe {
-                                crate::application::LINES.push((
+                            if app.debug.colliders {
+                                app.debug.arrow(
                                     body.position,
                                     body.position - ((body.velocity) * 1.1 * coll.2 .1.magnitude()),
                                     Color::WHITE,
-                                ));
+                                    DebugSpace::World,
+                                    0.0,
+                                );
                             }
                         }
 
@@ -629,8 +1554,11 @@ impl<'a> World<'a> {
     ) {
         let mut others = false;
         for (k, i) in &self.state.gameobjects {
+            if !i.active {
+                continue;
+            }
             for (k2, j) in &self.state.gameobjects {
-                if k != k2 {
+                if k != k2 && j.active {
                     others = true;
 
                     if let (Some(ii), Some(jj)) = (
@@ -663,6 +1591,9 @@ impl<'a> World<'a> {
 
     fn update_vel_x(&mut self, dt: f64) {
         for (_, i) in &mut self.state.gameobjects {
+            if !i.active {
+                continue;
+            }
             if let Some(ii) = i.wrap_component_mut::<Rigidbody>() {
                 let body = ii;
                 body.colliding = None;
@@ -673,6 +1604,9 @@ impl<'a> World<'a> {
 
     fn update_vel_y(&mut self, dt: f64) {
         for (_, i) in &mut self.state.gameobjects {
+            if !i.active {
+                continue;
+            }
             if let Some(ii) = i.wrap_component_mut::<Rigidbody>() {
                 let body = ii;
                 body.update_vel_y(dt);